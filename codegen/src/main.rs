@@ -382,8 +382,12 @@ pub enum AccessoryCategory {
 ";
 
 static HAP_TYPE: &'static str = "// this file is auto-generated by hap-codegen\n
+use std::str::FromStr;
+
 use serde::ser::{Serialize, Serializer};
 
+use crate::{characteristic::Format, Error};
+
 /// HAP Service and Characteristic type representation.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum HapType {
@@ -410,6 +414,68 @@ impl ToString for HapType {
     }
 }
 
+impl FromStr for HapType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<HapType, Error> {
+        match s {
+            \"unknown\" => Ok(HapType::Unknown),
+{{#each Characteristics as |c|}}\
+\t\t\t\"{{uuid c.UUID}}\" => Ok(HapType::{{pascal_case c.Name}}),
+{{/each}}\
+{{#each Services as |s|}}\
+\t\t\t\"{{uuid s.UUID}}\" => Ok(HapType::{{pascal_case s.Name}}),
+{{/each}}\
+\t\t\t_ => Err(Error::InvalidHapType(s.into())),
+        }
+    }
+}
+
+impl HapType {
+    /// Returns the full UUID of the type, e.g. `00000008-0000-1000-8000-0026BB765291` for `HapType::Brightness`.
+    pub fn to_uuid_string(&self) -> String {
+        match self {
+            HapType::Unknown => \"unknown\".into(),
+            _ => format!(\"{:0>8}-0000-1000-8000-0026BB765291\", self.to_string()),
+        }
+    }
+
+    /// Returns the human-readable name of the type, e.g. `Current Temperature` for `HapType::CurrentTemperature`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HapType::Unknown => \"Unknown\",
+{{#each Characteristics as |c|}}\
+\t\t\tHapType::{{pascal_case c.Name}} => \"{{c.Name}}\",
+{{/each}}\
+{{#each Services as |s|}}\
+\t\t\tHapType::{{pascal_case s.Name}} => \"{{s.Name}}\",
+{{/each}}\
+\t\t}
+    }
+
+    /// Returns the `Format` the HAP specification defines for a Characteristic type, e.g. `Format::Int32` for
+    /// `HapType::Brightness`. Returns `None` for Service types.
+    pub fn format(&self) -> Option<Format> {
+        match self {
+{{#each Characteristics as |c|}}\
+\t\t\tHapType::{{pascal_case c.Name}} => Some({{format c.Format}}),
+{{/each}}\
+\t\t\t_ => None,
+\t\t}
+    }
+
+    /// Returns the Characteristic types the HAP specification requires for a Service type, e.g. `HapType::On` for
+    /// `HapType::Lightbulb`. Returns an empty slice for Characteristic types.
+    pub fn required_characteristics(&self) -> &'static [HapType] {
+        match self {
+{{#each ServiceRequirements as |s|}}\
+\t\t\tHapType::{{pascal_case s.Name}} => &[{{#each s.Required as |r|}}HapType::{{pascal_case r}}{{#unless @last}}, {{/unless}}{{/each}}],
+{{/each}}\
+\t\t\t_ => &[],
+\t\t}
+    }
+}
+
 impl Default for HapType {
     fn default() -> HapType { HapType::Unknown }
 }
@@ -422,6 +488,8 @@ impl Serialize for HapType {
 ";
 
 static CHARACTERISTIC: &'static str = "// this file is auto-generated by hap-codegen\n
+use std::{any::Any, time::Duration};
+
 use async_trait::async_trait;
 use serde::Serialize;
 use serde_json::json;
@@ -435,15 +503,17 @@ use crate::{
         HapCharacteristic,
         HapCharacteristicSetup,
         HapType,
+        LinkRuleFn,
+        OnCommittedFn,
         OnReadFn,
         OnReadFuture,
         OnUpdateFn,
         OnUpdateFuture,
         Perm,
-        Unit,
+        Unit,{{#if_eq characteristic.Unit \"celsius\"}}
+        UnitSystem,{{/if_eq}}
     },
     pointer,
-    Error,
     Result,
 };
 
@@ -471,6 +541,44 @@ impl {{pascal_case characteristic.Name}}Characteristic {
             ..Default::default()
         })
     }
+
+    /// Creates a new {{characteristic.Name}} Characteristic with an initial value computed by `f`, e.g. from the
+    /// current time. `f` is called once and, unlike a subsequent `set_value`, neither invokes callbacks nor emits an
+    /// event.
+    pub fn with_initial_value(id: u64, accessory_id: u64, f: impl FnOnce() -> {{type characteristic.Format}}) -> Self {
+        let mut characteristic = Self::new(id, accessory_id);
+        characteristic.0.value = f();
+        characteristic
+    }
+
+    /// Returns a reference to the stored value of the {{characteristic.Name}} Characteristic without invoking any
+    /// read callbacks.
+    pub fn value_ref(&self) -> &{{type characteristic.Format}} { self.0.value_ref() }{{#if_eq characteristic.Format \"float\"}}
+
+    /// Sets the number of decimals the value of the {{characteristic.Name}} Characteristic is rounded to when
+    /// serialized.
+    pub fn set_precision(&mut self, precision: Option<u8>) { self.0.set_precision(precision) }{{#if_eq characteristic.Unit \"celsius\"}}
+
+    /// Returns the value of the {{characteristic.Name}} Characteristic converted to the given `UnitSystem`.
+    pub fn to_display(&self, unit_system: UnitSystem) -> f32 { self.0.to_display(unit_system) }
+
+    /// Converts a value in the given `UnitSystem` to the unit of the {{characteristic.Name}} Characteristic.
+    pub fn from_display(&self, value: f32, unit_system: UnitSystem) -> f32 { self.0.from_display(value, unit_system) }{{/if_eq}}{{/if_eq}}{{#if_eq characteristic.Format \"bool\"}}
+
+    /// Inverts the value of the {{characteristic.Name}} Characteristic and returns the new value.
+    pub async fn toggle(&mut self) -> Result<bool> { self.0.toggle().await }{{/if_eq}}{{#if characteristic.Constraints.StepValue includeZero=true}}
+
+    /// Sets the step value of the {{characteristic.Name}} Characteristic advertised to controllers. Values written by
+    /// controllers are snapped to the advertised step.
+    pub fn set_advertised_step(&mut self, val: Option<{{type characteristic.Format}}>) -> Result<()> {
+        self.0.set_advertised_step(val)
+    }{{/if}}
+}
+
+impl From<{{pascal_case characteristic.Name}}Characteristic> for Characteristic<{{type characteristic.Format}}> {
+    /// Returns the `Characteristic` configured with the format, permissions, unit and bounds of the
+    /// {{characteristic.Name}} Characteristic.
+    fn from(characteristic: {{pascal_case characteristic.Name}}Characteristic) -> Self { characteristic.0 }
 }
 
 #[async_trait]
@@ -489,28 +597,25 @@ impl HapCharacteristic for {{pascal_case characteristic.Name}}Characteristic {
         self.0.set_event_notifications(event_notifications)
     }
 
-    async fn get_value(&mut self) -> Result<serde_json::Value> {
-        let value = self.0.get_value().await?;
-        Ok(json!(value))
-    }
+    fn is_reachable(&self) -> bool { self.0.is_reachable() }
 
-    async fn set_value(&mut self, value: serde_json::Value) -> Result<()> {
-        let v;
-        // for whatever reason, the controller is setting boolean values either as a boolean or as an integer
-        if self.0.format == Format::Bool && value.is_number() {
-            let num_v: u8 = serde_json::from_value(value)?;
-            if num_v == 0 {
-                v = serde_json::from_value(json!(false))?;
-            } else if num_v == 1 {
-                v = serde_json::from_value(json!(true))?;
-            } else {
-                return Err(Error::InvalidValue(self.get_format()));
-            }
-        } else {
-            v = serde_json::from_value(value).map_err(|_| Error::InvalidValue(self.get_format()))?;
-        }
-        self.0.set_value(v).await
-    }
+    fn set_reachable(&mut self, reachable: bool) { self.0.set_reachable(reachable) }
+
+    async fn handle_subscribe(&self) { self.0.handle_subscribe().await }
+
+    fn handle_unsubscribe(&self) { self.0.handle_unsubscribe() }
+
+    fn subscriber_count(&self) -> usize { self.0.subscriber_count() }
+
+    fn needs_refresh(&self, interval: Duration) -> bool { self.0.needs_refresh(interval) }
+
+    async fn tick(&mut self) -> Result<bool> { self.0.tick().await }
+
+    async fn get_value(&mut self) -> Result<serde_json::Value> { self.0.get_json_value().await }
+
+    fn get_cached_value(&self) -> serde_json::Value { self.0.get_cached_value() }
+
+    async fn set_value(&mut self, value: serde_json::Value) -> Result<()> { self.0.set_json_value(value).await }
 
     fn get_unit(&self) -> Option<Unit> { self.0.get_unit() }
 
@@ -518,9 +623,23 @@ impl HapCharacteristic for {{pascal_case characteristic.Name}}Characteristic {
 
     fn get_min_value(&self) -> Option<serde_json::Value> { self.0.get_min_value().map(|v| json!(v)) }
 
-    fn get_step_value(&self) -> Option<serde_json::Value> { self.0.get_step_value().map(|v| json!(v)) }
+    fn get_step_value(&self) -> Option<serde_json::Value> { self.0.get_advertised_step().map(|v| json!(v)) }
 
     fn get_max_len(&self) -> Option<u16> { self.0.get_max_len() }
+
+    fn metadata_hash(&self) -> u64 { self.0.metadata_hash() }
+
+    fn detach(&mut self) { self.0.detach() }
+
+    fn take_linked_updates(&mut self) -> Vec<(u64, serde_json::Value)> { self.0.take_linked_updates() }
+
+    async fn set_linked_value(&mut self, value: serde_json::Value) -> Result<()> {
+        self.0.set_linked_value(value).await
+    }
+
+    fn as_any(&self) -> &dyn Any { &self.0 }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any { &mut self.0 }
 }
 
 impl HapCharacteristicSetup for {{pascal_case characteristic.Name}}Characteristic {
@@ -533,6 +652,10 @@ impl CharacteristicCallbacks<{{type characteristic.Format}}> for {{pascal_case c
     fn on_read(&mut self, f: Option<impl OnReadFn<{{type characteristic.Format}}>>) { self.0.on_read(f) }
 
     fn on_update(&mut self, f: Option<impl OnUpdateFn<{{type characteristic.Format}}>>) { self.0.on_update(f) }
+
+    fn on_committed(&mut self, f: Option<impl OnCommittedFn<{{type characteristic.Format}}>>) { self.0.on_committed(f) }
+
+    fn link_rule(&mut self, other_iid: u64, f: impl LinkRuleFn<{{type characteristic.Format}}>) { self.0.link_rule(other_iid, f) }
 }
 
 impl AsyncCharacteristicCallbacks<{{type characteristic.Format}}> for {{pascal_case characteristic.Name}}Characteristic {
@@ -887,7 +1010,20 @@ fn main() {
     let mut categories_file = File::create(&categories_path).unwrap();
     categories_file.write_all(categories.as_bytes()).unwrap();
 
-    let hap_type = handlebars.render("hap_type", &metadata).unwrap();
+    let mut hap_type_context = serde_json::to_value(metadata).unwrap();
+    hap_type_context["ServiceRequirements"] = json!(metadata
+        .services
+        .iter()
+        .map(|s| {
+            let required = s
+                .required_characteristics
+                .iter()
+                .map(|c| &metadata_ex.characteristics[c].name)
+                .collect::<Vec<_>>();
+            json!({ "Name": s.name, "Required": required })
+        })
+        .collect::<Vec<_>>());
+    let hap_type = handlebars.render("hap_type", &hap_type_context).unwrap();
     let hap_type_path = "src/hap_type.rs".to_owned();
     let mut hap_type_file = File::create(&hap_type_path).unwrap();
     hap_type_file.write_all(hap_type.as_bytes()).unwrap();
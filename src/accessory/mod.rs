@@ -1,3 +1,4 @@
+use async_trait::async_trait;
 use erased_serde::serialize_trait_object;
 use futures::executor;
 
@@ -9,6 +10,7 @@ use crate::{
     },
     pointer,
     service::{accessory_information::AccessoryInformationService, HapService},
+    Error,
     HapType,
     Result,
 };
@@ -37,22 +39,105 @@ pub trait HapAccessory: HapAccessorySetup + erased_serde::Serialize + Send + Syn
 
 serialize_trait_object!(HapAccessory);
 
+/// Returns an iterator over the Characteristics of all Services of `accessories` as triples of accessory ID, instance
+/// ID and Characteristic, e.g. to validate every Characteristic of a bridge without nesting loops over its accessories
+/// and their Services.
+pub fn characteristics<'a>(
+    accessories: impl IntoIterator<Item = &'a dyn HapAccessory> + 'a,
+) -> impl Iterator<Item = (u64, u64, &'a dyn HapCharacteristic)> + 'a {
+    accessories.into_iter().flat_map(|accessory| {
+        let aid = accessory.get_id();
+        accessory.get_services().into_iter().flat_map(move |service| {
+            service
+                .get_characteristics()
+                .into_iter()
+                .map(move |characteristic| (aid, characteristic.get_id(), characteristic))
+        })
+    })
+}
+
+#[async_trait]
 pub trait HapAccessorySetup {
     /// Sets a `hap::event::pointer::EventEmitter` on all Characteristics of the Accessory.
     fn set_event_emitter_on_characteristics(&mut self, event_emitter: Option<pointer::EventEmitter>);
+    /// Applies the updates queued by the link rules of the Characteristics of the Accessory. Every Characteristic is
+    /// updated at most once, so rules linking Characteristics to each other don't loop. A failing update doesn't stop
+    /// the others; the failures are returned as pairs of instance ID and error.
+    async fn apply_linked_updates(&mut self) -> Vec<(u64, Error)>;
+    /// Checks that the instance IDs of the Services and Characteristics of the Accessory are unique. Returns an
+    /// `Error::DuplicateInstanceIds` listing every colliding instance ID otherwise.
+    fn validate_instance_ids(&self) -> Result<()>;
 }
 
+#[async_trait]
 impl<H> HapAccessorySetup for H
 where
     H: HapAccessory,
 {
     fn set_event_emitter_on_characteristics(&mut self, event_emitter: Option<pointer::EventEmitter>) {
         for service in self.get_mut_services() {
+            service.set_event_emitter(event_emitter.clone());
             for characteristic in service.get_mut_characteristics() {
                 characteristic.set_event_emitter(event_emitter.clone());
             }
         }
     }
+
+    async fn apply_linked_updates(&mut self) -> Vec<(u64, Error)> {
+        let mut updated = Vec::new();
+        let mut failures = Vec::new();
+        loop {
+            let mut updates = Vec::new();
+            for service in self.get_mut_services() {
+                for characteristic in service.get_mut_characteristics() {
+                    let linked_updates = characteristic.take_linked_updates();
+                    if !linked_updates.is_empty() {
+                        updated.push(characteristic.get_id());
+                        updates.extend(linked_updates);
+                    }
+                }
+            }
+            updates.retain(|(iid, _)| !updated.contains(iid));
+            if updates.is_empty() {
+                return failures;
+            }
+
+            for (iid, value) in updates {
+                if updated.contains(&iid) {
+                    continue;
+                }
+                for service in self.get_mut_services() {
+                    for characteristic in service.get_mut_characteristics() {
+                        if characteristic.get_id() == iid {
+                            if let Err(e) = characteristic.set_linked_value(value.clone()).await {
+                                failures.push((iid, e));
+                            }
+                        }
+                    }
+                }
+                updated.push(iid);
+            }
+        }
+    }
+
+    fn validate_instance_ids(&self) -> Result<()> {
+        let mut iids = Vec::new();
+        for service in self.get_services() {
+            iids.push(service.get_id());
+            for characteristic in service.get_characteristics() {
+                iids.push(characteristic.get_id());
+            }
+        }
+        iids.sort_unstable();
+
+        let mut duplicates = iids.windows(2).filter(|w| w[0] == w[1]).map(|w| w[0]).collect::<Vec<u64>>();
+        duplicates.dedup();
+        if !duplicates.is_empty() {
+            return Err(Error::DuplicateInstanceIds(duplicates));
+        }
+
+        Ok(())
+    }
 }
 
 /// The `AccessoryInformationInformation` struct is used to store metadata about an `Accessory` and is converted to the
@@ -158,3 +243,94 @@ impl Default for AccessoryInformation {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        accessory::{lightbulb::LightbulbAccessory, temperature_sensor::TemperatureSensorAccessory},
+        characteristic::{
+            brightness::BrightnessCharacteristic,
+            hue::HueCharacteristic,
+            Characteristic,
+            CharacteristicCallbacks,
+        },
+    };
+
+    #[test]
+    fn test_apply_linked_updates() {
+        let mut lightbulb = LightbulbAccessory::new(1, AccessoryInformation::default()).unwrap();
+        let on_iid = lightbulb.lightbulb.on.get_id();
+        let mut brightness = BrightnessCharacteristic::new(100, 1);
+        brightness.link_rule(on_iid, |brightness: &i32| if *brightness > 0 { Some(json!(true)) } else { None });
+        lightbulb.lightbulb.brightness = Some(brightness);
+
+        let brightness = lightbulb.lightbulb.brightness.as_mut().unwrap();
+        executor::block_on(HapCharacteristic::set_value(brightness, json!(0))).unwrap();
+        assert!(executor::block_on(lightbulb.apply_linked_updates()).is_empty());
+        assert_eq!(executor::block_on(lightbulb.lightbulb.on.get_value()).unwrap(), json!(false));
+
+        let brightness = lightbulb.lightbulb.brightness.as_mut().unwrap();
+        executor::block_on(HapCharacteristic::set_value(brightness, json!(50))).unwrap();
+        assert!(executor::block_on(lightbulb.apply_linked_updates()).is_empty());
+        assert_eq!(executor::block_on(lightbulb.lightbulb.on.get_value()).unwrap(), json!(true));
+    }
+
+    #[test]
+    fn test_apply_linked_updates_failures() {
+        let mut lightbulb = LightbulbAccessory::new(1, AccessoryInformation::default()).unwrap();
+        let on_iid = lightbulb.lightbulb.on.get_id();
+        let mut brightness = BrightnessCharacteristic::new(100, 1);
+        brightness.link_rule(on_iid, |_: &i32| Some(json!(true)));
+        brightness.link_rule(101, |_: &i32| Some(json!("bright")));
+        lightbulb.lightbulb.brightness = Some(brightness);
+        lightbulb.lightbulb.hue = Some(HueCharacteristic::new(101, 1));
+        let on = lightbulb.lightbulb.on.as_any_mut().downcast_mut::<Characteristic<bool>>().unwrap();
+        on.set_writes_locked(true);
+
+        let brightness = lightbulb.lightbulb.brightness.as_mut().unwrap();
+        executor::block_on(HapCharacteristic::set_value(brightness, json!(50))).unwrap();
+        let failures = executor::block_on(lightbulb.apply_linked_updates());
+        assert!(matches!(failures.as_slice(), [(101, Error::InvalidValue(_))]));
+        assert_eq!(executor::block_on(lightbulb.lightbulb.on.get_value()).unwrap(), json!(true));
+    }
+
+    #[test]
+    fn test_validate_instance_ids() {
+        let mut lightbulb = LightbulbAccessory::new(1, AccessoryInformation::default()).unwrap();
+        assert!(lightbulb.validate_instance_ids().is_ok());
+
+        let on_iid = lightbulb.lightbulb.on.get_id();
+        lightbulb.lightbulb.brightness = Some(BrightnessCharacteristic::new(on_iid, 1));
+        assert!(matches!(
+            lightbulb.validate_instance_ids(),
+            Err(Error::DuplicateInstanceIds(iids)) if iids == vec![on_iid]
+        ));
+    }
+
+    #[test]
+    fn test_characteristics() {
+        let lightbulb = LightbulbAccessory::new(1, AccessoryInformation::default()).unwrap();
+        let sensor = TemperatureSensorAccessory::new(2, AccessoryInformation::default()).unwrap();
+        let accessories: [&dyn HapAccessory; 2] = [&lightbulb, &sensor];
+
+        let triples = characteristics(accessories.iter().copied()).collect::<Vec<_>>();
+        let expected = accessories
+            .iter()
+            .map(|a| a.get_services().iter().map(|s| s.get_characteristics().len()).sum::<usize>())
+            .sum::<usize>();
+        assert_eq!(triples.len(), expected);
+        assert!(triples.iter().all(|(aid, iid, c)| (*aid == 1 || *aid == 2) && c.get_id() == *iid));
+        assert_eq!(triples.first().map(|(aid, ..)| *aid), Some(1));
+        assert_eq!(triples.last().map(|(aid, ..)| *aid), Some(2));
+
+        let temperatures = triples
+            .iter()
+            .filter(|(_, _, c)| c.get_type() == HapType::CurrentTemperature)
+            .map(|(aid, iid, _)| (*aid, *iid))
+            .collect::<Vec<_>>();
+        assert_eq!(temperatures, vec![(2, sensor.temperature_sensor.current_temperature.get_id())]);
+    }
+}
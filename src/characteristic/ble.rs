@@ -0,0 +1,196 @@
+use std::fmt;
+
+use byteorder::{LittleEndian, WriteBytesExt};
+use serde::Serialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    characteristic::{Characteristic, Format, Perm, Unit},
+    tlv,
+};
+
+/// `Param` represents the HAP-over-BLE parameter types used in a characteristic signature.
+#[derive(Debug, Copy, Clone)]
+enum Param {
+    CharacteristicType = 0x04,
+    PropertiesDescriptor = 0x0A,
+    UserDescriptionDescriptor = 0x0B,
+    PresentationFormatDescriptor = 0x0C,
+    ValidRange = 0x0D,
+    StepValueDescriptor = 0x0E,
+    ValidValuesDescriptor = 0x11,
+    ValidValuesRangeDescriptor = 0x12,
+}
+
+impl<T: fmt::Debug + Default + Clone + Serialize + Send + Sync> Characteristic<T> {
+    /// Encodes the signature of a Characteristic to the TLV8 form used by HAP over BLE, i.e. its type, properties,
+    /// description, presentation format and constraints. Constraints are only encoded for numeric formats.
+    pub fn to_ble_tlv(&self) -> Vec<u8> {
+        let mut tlvs = vec![
+            (Param::CharacteristicType as u8, ble_uuid(&self.hap_type.to_uuid_string())),
+            (Param::PropertiesDescriptor as u8, ble_properties(&self.perms)),
+        ];
+        if let Some(ref description) = self.description {
+            tlvs.push((Param::UserDescriptionDescriptor as u8, description.clone().into_bytes()));
+        }
+        tlvs.push((
+            Param::PresentationFormatDescriptor as u8,
+            ble_presentation_format(self.format, self.unit),
+        ));
+        if let (Some(min_value), Some(max_value)) = (&self.min_value, &self.max_value) {
+            let mut range = self.ble_value(min_value);
+            range.extend(self.ble_value(max_value));
+            if !range.is_empty() {
+                tlvs.push((Param::ValidRange as u8, range));
+            }
+        }
+        if let Some(step_value) = self.advertised_step.as_ref().or(self.step_value.as_ref()) {
+            let step = self.ble_value(step_value);
+            if !step.is_empty() {
+                tlvs.push((Param::StepValueDescriptor as u8, step));
+            }
+        }
+        if let Some(ref valid_values) = self.valid_values {
+            let values = valid_values.iter().flat_map(|v| self.ble_value(v)).collect::<Vec<u8>>();
+            if !values.is_empty() {
+                tlvs.push((Param::ValidValuesDescriptor as u8, values));
+            }
+        }
+        if let Some([ref start, ref end]) = self.valid_values_range {
+            let mut range = self.ble_value(start);
+            range.extend(self.ble_value(end));
+            if !range.is_empty() {
+                tlvs.push((Param::ValidValuesRangeDescriptor as u8, range));
+            }
+        }
+
+        tlv::encode(tlvs)
+    }
+
+    /// Encodes a value as the little-endian representation of the `Format` of the Characteristic. Returns an empty
+    /// `Vec` for non-numeric formats.
+    fn ble_value(&self, value: &T) -> Vec<u8> {
+        let value = json!(value);
+        let mut bytes = Vec::new();
+        let res = match self.format {
+            Format::Bool => value.as_bool().map(|v| bytes.write_u8(v as u8)),
+            Format::UInt8 => value.as_u64().map(|v| bytes.write_u8(v as u8)),
+            Format::UInt16 => value.as_u64().map(|v| bytes.write_u16::<LittleEndian>(v as u16)),
+            Format::UInt32 => value.as_u64().map(|v| bytes.write_u32::<LittleEndian>(v as u32)),
+            Format::UInt64 => value.as_u64().map(|v| bytes.write_u64::<LittleEndian>(v)),
+            Format::Int32 => value.as_i64().map(|v| bytes.write_i32::<LittleEndian>(v as i32)),
+            Format::Float => value.as_f64().map(|v| bytes.write_f32::<LittleEndian>(v as f32)),
+            Format::String | Format::Tlv8 | Format::Data => None,
+        };
+        match res {
+            Some(Ok(())) => bytes,
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Converts a UUID string to its little-endian bytes.
+fn ble_uuid(uuid: &str) -> Vec<u8> {
+    match Uuid::parse_str(uuid) {
+        Ok(uuid) => uuid.as_bytes().iter().rev().cloned().collect(),
+        Err(_) => vec![0; 16],
+    }
+}
+
+/// Encodes `Perm`s as a HAP characteristic properties descriptor.
+fn ble_properties(perms: &[Perm]) -> Vec<u8> {
+    let properties = perms.iter().fold(0u16, |properties, perm| {
+        properties
+            | match perm {
+                Perm::PairedRead => 0x0010,
+                Perm::PairedWrite => 0x0020,
+                Perm::Events => 0x0080 | 0x0100,
+                Perm::AdditionalAuthorization => 0x0004,
+                Perm::TimedWrite => 0x0008,
+                Perm::Hidden => 0x0040,
+            }
+    });
+    properties.to_le_bytes().to_vec()
+}
+
+/// Encodes a `Format` and `Unit` as a GATT presentation format descriptor.
+fn ble_presentation_format(format: Format, unit: Option<Unit>) -> Vec<u8> {
+    let format: u8 = match format {
+        Format::Bool => 0x01,
+        Format::UInt8 => 0x04,
+        Format::UInt16 => 0x06,
+        Format::UInt32 => 0x08,
+        Format::UInt64 => 0x0A,
+        Format::Int32 => 0x10,
+        Format::Float => 0x14,
+        Format::String => 0x19,
+        Format::Tlv8 | Format::Data => 0x1B,
+    };
+    let unit: u16 = match unit {
+        Some(Unit::Percentage) => 0x27AD,
+        Some(Unit::ArcDegrees) => 0x2763,
+        Some(Unit::Celsius) => 0x272F,
+        Some(Unit::Lux) => 0x2731,
+        Some(Unit::Seconds) => 0x2703,
+        None => 0x2700,
+    };
+
+    let mut descriptor = vec![format, 0x00];
+    descriptor.extend(&unit.to_le_bytes());
+    // namespace (Bluetooth SIG) and description
+    descriptor.extend(&[0x01, 0x00, 0x00]);
+    descriptor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HapType;
+
+    #[test]
+    fn test_ble_tlv_numeric() {
+        let characteristic = Characteristic::<i32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::Brightness,
+            format: Format::Int32,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            unit: Some(Unit::Percentage),
+            max_value: Some(100),
+            min_value: Some(0),
+            step_value: Some(1),
+            ..Default::default()
+        };
+
+        assert_eq!(characteristic.to_ble_tlv(), vec![
+            0x04, 0x10, 0x91, 0x52, 0x76, 0xBB, 0x26, 0x00, 0x00, 0x80, 0x00, 0x10, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00,
+            0x0A, 0x02, 0xB0, 0x01, //
+            0x0C, 0x07, 0x10, 0x00, 0xAD, 0x27, 0x01, 0x00, 0x00, //
+            0x0D, 0x08, 0x00, 0x00, 0x00, 0x00, 0x64, 0x00, 0x00, 0x00, //
+            0x0E, 0x04, 0x01, 0x00, 0x00, 0x00,
+        ]);
+    }
+
+    #[test]
+    fn test_ble_tlv_valid_values() {
+        let characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::TargetHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            description: Some("Mode".into()),
+            valid_values: Some(vec![0, 1, 3]),
+            ..Default::default()
+        };
+
+        assert_eq!(characteristic.to_ble_tlv(), vec![
+            0x04, 0x10, 0x91, 0x52, 0x76, 0xBB, 0x26, 0x00, 0x00, 0x80, 0x00, 0x10, 0x00, 0x00, 0x33, 0x00, 0x00, 0x00,
+            0x0A, 0x02, 0x30, 0x00, //
+            0x0B, 0x04, b'M', b'o', b'd', b'e', //
+            0x0C, 0x07, 0x04, 0x00, 0x00, 0x27, 0x01, 0x00, 0x00, //
+            0x11, 0x03, 0x00, 0x01, 0x03,
+        ]);
+    }
+}
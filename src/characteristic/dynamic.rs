@@ -0,0 +1,432 @@
+use std::{any::Any, str::FromStr, time::Duration};
+
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    characteristic::{
+        AsyncCharacteristicCallbacks,
+        Characteristic,
+        CharacteristicCallbacks,
+        Format,
+        HapCharacteristic,
+        HapCharacteristicSetup,
+        LinkRuleFn,
+        OnCommittedFn,
+        OnReadFn,
+        OnReadFuture,
+        OnUpdateFn,
+        OnUpdateFuture,
+        Perm,
+        Unit,
+    },
+    pointer,
+    Error,
+    HapType,
+    Result,
+};
+
+/// A Characteristic holding its value as JSON, e.g. for accessories defined in a config file instead of code.
+#[derive(Debug, Default, Serialize)]
+pub struct DynCharacteristic(Characteristic<Value>);
+
+impl DynCharacteristic {
+    /// Creates a new Characteristic from a JSON template using the field names of the HAP accessory database, e.g.
+    /// `{"type": "8", "format": "int32", "perms": ["pr", "pw", "ev"], "minValue": 0, "maxValue": 100}`. The `type`,
+    /// `format` and `perms` fields are required. Without a `value`, the Characteristic starts out with the default
+    /// value of its format.
+    pub fn from_json(id: u64, accessory_id: u64, template: &Value) -> Result<Self> {
+        let template = template
+            .as_object()
+            .ok_or_else(|| invalid_template("the template isn't an object"))?;
+
+        let hap_type = match template.get("type").and_then(Value::as_str) {
+            Some(hap_type) => HapType::from_str(hap_type)?,
+            None => return Err(invalid_template("missing string field `type`")),
+        };
+        let format = match template.get("format").and_then(Value::as_str) {
+            Some(format) => Format::from_str(format)?,
+            None => return Err(invalid_template("missing string field `format`")),
+        };
+        let perms = match template.get("perms").and_then(Value::as_array) {
+            Some(perms) => perms
+                .iter()
+                .map(|perm| perm.as_str().and_then(perm_from_str))
+                .collect::<Option<Vec<Perm>>>()
+                .ok_or_else(|| invalid_template("invalid entry in `perms`"))?,
+            None => return Err(invalid_template("missing array field `perms`")),
+        };
+        let unit = match template.get("unit") {
+            Some(unit) => Some(
+                unit.as_str()
+                    .and_then(unit_from_str)
+                    .ok_or_else(|| invalid_template("invalid field `unit`"))?,
+            ),
+            None => None,
+        };
+        let description = match template.get("description") {
+            Some(description) => Some(
+                description
+                    .as_str()
+                    .ok_or_else(|| invalid_template("invalid field `description`"))?
+                    .to_string(),
+            ),
+            None => None,
+        };
+        let max_len = match template.get("maxLen") {
+            Some(max_len) => Some(
+                max_len
+                    .as_u64()
+                    .filter(|&l| l <= u16::MAX as u64)
+                    .ok_or_else(|| invalid_template("invalid field `maxLen`"))? as u16,
+            ),
+            None => None,
+        };
+        let max_data_len = match template.get("maxDataLen") {
+            Some(max_data_len) => Some(
+                max_data_len
+                    .as_u64()
+                    .filter(|&l| l <= u32::MAX as u64)
+                    .ok_or_else(|| invalid_template("invalid field `maxDataLen`"))? as u32,
+            ),
+            None => None,
+        };
+        let valid_values = match template.get("valid-values") {
+            Some(valid_values) => Some(
+                valid_values
+                    .as_array()
+                    .filter(|valid_values| valid_values.iter().all(|v| matches_format(format, v)))
+                    .ok_or_else(|| invalid_template("invalid field `valid-values`"))?
+                    .clone(),
+            ),
+            None => None,
+        };
+        let valid_values_range = match template.get("valid-values-range") {
+            Some(range) => match range.as_array().map(Vec::as_slice) {
+                Some([start, end]) if matches_format(format, start) && matches_format(format, end) => {
+                    Some([start.clone(), end.clone()])
+                },
+                _ => return Err(invalid_template("invalid field `valid-values-range`")),
+            },
+            None => None,
+        };
+
+        let characteristic = Characteristic::<Value> {
+            id,
+            accessory_id,
+            hap_type,
+            format,
+            perms,
+            description,
+            value: template.get("value").cloned().unwrap_or_else(|| default_value(format)),
+            unit,
+            max_value: format_field(template, "maxValue", format)?,
+            min_value: format_field(template, "minValue", format)?,
+            step_value: format_field(template, "minStep", format)?,
+            max_len,
+            max_data_len,
+            valid_values,
+            valid_values_range,
+            ..Default::default()
+        };
+        if !matches_format(format, &characteristic.value) {
+            return Err(Error::InvalidValue(format));
+        }
+        characteristic.validate()?;
+
+        Ok(Self(characteristic))
+    }
+
+    /// Returns a reference to the stored value of the Characteristic without invoking any read callbacks.
+    pub fn value_ref(&self) -> &Value { self.0.value_ref() }
+}
+
+impl From<DynCharacteristic> for Characteristic<Value> {
+    /// Returns the `Characteristic` configured from the template of the `DynCharacteristic`.
+    fn from(characteristic: DynCharacteristic) -> Self { characteristic.0 }
+}
+
+#[async_trait]
+impl HapCharacteristic for DynCharacteristic {
+    fn get_id(&self) -> u64 { self.0.get_id() }
+
+    fn get_type(&self) -> HapType { self.0.get_type() }
+
+    fn get_format(&self) -> Format { self.0.get_format() }
+
+    fn get_perms(&self) -> Vec<Perm> { self.0.get_perms() }
+
+    fn get_event_notifications(&self) -> Option<bool> { self.0.get_event_notifications() }
+
+    fn set_event_notifications(&mut self, event_notifications: Option<bool>) {
+        self.0.set_event_notifications(event_notifications)
+    }
+
+    fn is_reachable(&self) -> bool { self.0.is_reachable() }
+
+    fn set_reachable(&mut self, reachable: bool) { self.0.set_reachable(reachable) }
+
+    async fn handle_subscribe(&self) { self.0.handle_subscribe().await }
+
+    fn handle_unsubscribe(&self) { self.0.handle_unsubscribe() }
+
+    fn subscriber_count(&self) -> usize { self.0.subscriber_count() }
+
+    fn needs_refresh(&self, interval: Duration) -> bool { self.0.needs_refresh(interval) }
+
+    async fn tick(&mut self) -> Result<bool> { self.0.tick().await }
+
+    async fn get_value(&mut self) -> Result<Value> { self.0.get_json_value().await }
+
+    fn get_cached_value(&self) -> Value { self.0.get_cached_value() }
+
+    async fn set_value(&mut self, value: Value) -> Result<()> {
+        // unlike typed Characteristics, the value isn't checked by deserializing it
+        if !matches_format(self.0.get_format(), &value) {
+            return Err(Error::InvalidValue(self.0.get_format()));
+        }
+        self.0.set_json_value(value).await
+    }
+
+    fn get_unit(&self) -> Option<Unit> { self.0.get_unit() }
+
+    fn get_max_value(&self) -> Option<Value> { self.0.get_max_value() }
+
+    fn get_min_value(&self) -> Option<Value> { self.0.get_min_value() }
+
+    fn get_step_value(&self) -> Option<Value> { self.0.get_advertised_step() }
+
+    fn get_max_len(&self) -> Option<u16> { self.0.get_max_len() }
+
+    fn metadata_hash(&self) -> u64 { self.0.metadata_hash() }
+
+    fn detach(&mut self) { self.0.detach() }
+
+    fn take_linked_updates(&mut self) -> Vec<(u64, Value)> { self.0.take_linked_updates() }
+
+    async fn set_linked_value(&mut self, value: Value) -> Result<()> { self.0.set_linked_value(value).await }
+
+    fn as_any(&self) -> &dyn Any { &self.0 }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any { &mut self.0 }
+}
+
+impl HapCharacteristicSetup for DynCharacteristic {
+    fn set_event_emitter(&mut self, event_emitter: Option<pointer::EventEmitter>) {
+        self.0.set_event_emitter(event_emitter)
+    }
+}
+
+impl CharacteristicCallbacks<Value> for DynCharacteristic {
+    fn on_read(&mut self, f: Option<impl OnReadFn<Value>>) { self.0.on_read(f) }
+
+    fn on_update(&mut self, f: Option<impl OnUpdateFn<Value>>) { self.0.on_update(f) }
+
+    fn on_committed(&mut self, f: Option<impl OnCommittedFn<Value>>) { self.0.on_committed(f) }
+
+    fn link_rule(&mut self, other_iid: u64, f: impl LinkRuleFn<Value>) { self.0.link_rule(other_iid, f) }
+}
+
+impl AsyncCharacteristicCallbacks<Value> for DynCharacteristic {
+    fn on_read_async(&mut self, f: Option<impl OnReadFuture<Value>>) { self.0.on_read_async(f) }
+
+    fn on_update_async(&mut self, f: Option<impl OnUpdateFuture<Value>>) { self.0.on_update_async(f) }
+}
+
+fn invalid_template(reason: &str) -> Error { Error::InvalidCharacteristicTemplate(reason.to_string()) }
+
+/// Returns the field `name` of a template if it's set, checking that it matches `format`.
+fn format_field(template: &Map<String, Value>, name: &str, format: Format) -> Result<Option<Value>> {
+    match template.get(name) {
+        Some(value) if matches_format(format, value) => Ok(Some(value.clone())),
+        Some(_) => Err(invalid_template(&format!("invalid field `{}`", name))),
+        None => Ok(None),
+    }
+}
+
+/// Returns whether a JSON value has the data type of a `Format`, i.e. integer formats only accept integers within
+/// their bounds. Bool Characteristics also accept `0` and `1`, as some controllers write them as numbers.
+fn matches_format(format: Format, value: &Value) -> bool {
+    let unsigned = |max: u64| matches!(value.as_u64(), Some(v) if v <= max);
+    match format {
+        Format::Bool => value.is_boolean() || unsigned(1),
+        Format::String | Format::Tlv8 | Format::Data => value.is_string(),
+        Format::UInt8 => unsigned(u8::MAX as u64),
+        Format::UInt16 => unsigned(u16::MAX as u64),
+        Format::UInt32 => unsigned(u32::MAX as u64),
+        Format::UInt64 => value.is_u64(),
+        Format::Int32 => matches!(value.as_i64(), Some(v) if v >= i32::MIN as i64 && v <= i32::MAX as i64),
+        Format::Float => value.is_number(),
+    }
+}
+
+fn default_value(format: Format) -> Value {
+    match format {
+        Format::Bool => json!(false),
+        Format::Float => json!(0.0),
+        Format::String | Format::Tlv8 | Format::Data => json!(""),
+        _ => json!(0),
+    }
+}
+
+fn perm_from_str(perm: &str) -> Option<Perm> {
+    match perm {
+        "pr" => Some(Perm::PairedRead),
+        "pw" => Some(Perm::PairedWrite),
+        "ev" => Some(Perm::Events),
+        "aa" => Some(Perm::AdditionalAuthorization),
+        "tw" => Some(Perm::TimedWrite),
+        "hd" => Some(Perm::Hidden),
+        _ => None,
+    }
+}
+
+fn unit_from_str(unit: &str) -> Option<Unit> {
+    match unit {
+        "percentage" => Some(Unit::Percentage),
+        "arcdegrees" => Some(Unit::ArcDegrees),
+        "celsius" => Some(Unit::Celsius),
+        "lux" => Some(Unit::Lux),
+        "seconds" => Some(Unit::Seconds),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor;
+
+    use super::*;
+
+    #[test]
+    fn test_from_json() {
+        let template = json!({
+            "type": "8",
+            "format": "int32",
+            "perms": ["pr", "pw", "ev"],
+            "unit": "percentage",
+            "minValue": 0,
+            "maxValue": 100,
+            "minStep": 1,
+        });
+        let mut characteristic = DynCharacteristic::from_json(9, 1, &template).unwrap();
+
+        assert_eq!(characteristic.get_type(), HapType::Brightness);
+        assert_eq!(characteristic.get_format(), Format::Int32);
+        assert_eq!(characteristic.get_unit(), Some(Unit::Percentage));
+        assert_eq!(characteristic.get_max_value(), Some(json!(100)));
+        assert_eq!(characteristic.value_ref(), &json!(0));
+
+        executor::block_on(HapCharacteristic::set_value(&mut characteristic, json!(42))).unwrap();
+        assert_eq!(executor::block_on(HapCharacteristic::get_value(&mut characteristic)).unwrap(), json!(42));
+        assert!(matches!(
+            executor::block_on(HapCharacteristic::set_value(&mut characteristic, json!(101))),
+            Err(Error::ValueAboveMaxValue)
+        ));
+        assert!(matches!(
+            executor::block_on(HapCharacteristic::set_value(&mut characteristic, json!("42"))),
+            Err(Error::InvalidValue(Format::Int32))
+        ));
+
+        assert_eq!(
+            serde_json::to_string(&characteristic).unwrap(),
+            "{\"iid\":9,\"type\":\"8\",\"format\":\"int32\",\"perms\":[\"pr\",\"pw\",\"ev\"],\"value\":42,\"unit\":\"percentage\",\"maxValue\":100,\"minValue\":0,\"minStep\":1}"
+        );
+    }
+
+    #[test]
+    fn test_from_json_malformed() {
+        assert!(matches!(
+            DynCharacteristic::from_json(1, 1, &json!(["8"])),
+            Err(Error::InvalidCharacteristicTemplate(_))
+        ));
+        assert!(matches!(
+            DynCharacteristic::from_json(1, 1, &json!({"format": "bool", "perms": ["pr"]})),
+            Err(Error::InvalidCharacteristicTemplate(reason)) if reason.contains("`type`")
+        ));
+        assert!(matches!(
+            DynCharacteristic::from_json(1, 1, &json!({"type": "25", "format": "bool", "perms": ["xx"]})),
+            Err(Error::InvalidCharacteristicTemplate(reason)) if reason.contains("`perms`")
+        ));
+        assert!(matches!(
+            DynCharacteristic::from_json(1, 1, &json!({"type": "25", "format": "int64", "perms": ["pr"]})),
+            Err(Error::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            DynCharacteristic::from_json(
+                1,
+                1,
+                &json!({"type": "25", "format": "bool", "perms": ["pr"], "value": "on"})
+            ),
+            Err(Error::InvalidValue(Format::Bool))
+        ));
+        assert!(matches!(
+            DynCharacteristic::from_json(
+                1,
+                1,
+                &json!({"type": "8", "format": "int32", "perms": ["pr"], "valid-values-range": 100})
+            ),
+            Err(Error::InvalidCharacteristicTemplate(reason)) if reason.contains("`valid-values-range`")
+        ));
+        assert!(matches!(
+            DynCharacteristic::from_json(
+                1,
+                1,
+                &json!({"type": "8", "format": "int32", "perms": ["pr"], "valid-values-range": [0, "100"]})
+            ),
+            Err(Error::InvalidCharacteristicTemplate(reason)) if reason.contains("`valid-values-range`")
+        ));
+        assert!(matches!(
+            DynCharacteristic::from_json(
+                1,
+                1,
+                &json!({"type": "1", "format": "data", "perms": ["pr"], "maxDataLen": -1})
+            ),
+            Err(Error::InvalidCharacteristicTemplate(reason)) if reason.contains("`maxDataLen`")
+        ));
+        for (field, value) in [("maxValue", json!("100")), ("minValue", json!(-1)), ("minStep", json!(0.5))] {
+            let mut template = json!({"type": "8", "format": "uint8", "perms": ["pr"]});
+            template[field] = value;
+            assert!(matches!(
+                DynCharacteristic::from_json(1, 1, &template),
+                Err(Error::InvalidCharacteristicTemplate(reason)) if reason.contains(&format!("`{}`", field))
+            ));
+        }
+        assert!(matches!(
+            DynCharacteristic::from_json(
+                1,
+                1,
+                &json!({"type": "8", "format": "uint8", "perms": ["pr"], "valid-values": [0, "1"]})
+            ),
+            Err(Error::InvalidCharacteristicTemplate(reason)) if reason.contains("`valid-values`")
+        ));
+    }
+
+    #[test]
+    fn test_from_json_max_data_len() {
+        let template = json!({"type": "1", "format": "data", "perms": ["pr"], "maxDataLen": 2048});
+        let characteristic = DynCharacteristic::from_json(1, 1, &template).unwrap();
+
+        assert_eq!(characteristic.0.max_data_len, Some(2048));
+    }
+
+    #[test]
+    fn test_matches_format() {
+        assert!(matches_format(Format::UInt8, &json!(255)));
+        assert!(!matches_format(Format::UInt8, &json!(256)));
+        assert!(!matches_format(Format::UInt8, &json!(-1)));
+        assert!(!matches_format(Format::UInt16, &json!(1.5)));
+        assert!(matches_format(Format::UInt32, &json!(u32::MAX)));
+        assert!(!matches_format(Format::UInt32, &json!(u32::MAX as u64 + 1)));
+        assert!(matches_format(Format::UInt64, &json!(u64::MAX)));
+        assert!(!matches_format(Format::UInt64, &json!(-1)));
+        assert!(matches_format(Format::Int32, &json!(-42)));
+        assert!(!matches_format(Format::Int32, &json!(i32::MAX as i64 + 1)));
+        assert!(!matches_format(Format::Int32, &json!(3.0)));
+        assert!(matches_format(Format::Float, &json!(3)));
+        assert!(matches_format(Format::Bool, &json!(1)));
+        assert!(!matches_format(Format::Bool, &json!(2)));
+        assert!(!matches_format(Format::String, &json!(1)));
+    }
+}
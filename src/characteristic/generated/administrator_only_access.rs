@@ -1,5 +1,7 @@
 // this file is auto-generated by hap-codegen
 
+use std::{any::Any, time::Duration};
+
 use async_trait::async_trait;
 use serde::Serialize;
 use serde_json::json;
@@ -13,6 +15,8 @@ use crate::{
         HapCharacteristic,
         HapCharacteristicSetup,
         HapType,
+        LinkRuleFn,
+        OnCommittedFn,
         OnReadFn,
         OnReadFuture,
         OnUpdateFn,
@@ -21,7 +25,6 @@ use crate::{
         Unit,
     },
     pointer,
-    Error,
     Result,
 };
 
@@ -45,6 +48,28 @@ impl AdministratorOnlyAccessCharacteristic {
             ..Default::default()
         })
     }
+
+    /// Creates a new Administrator Only Access Characteristic with an initial value computed by `f`, e.g. from the
+    /// current time. `f` is called once and, unlike a subsequent `set_value`, neither invokes callbacks nor emits an
+    /// event.
+    pub fn with_initial_value(id: u64, accessory_id: u64, f: impl FnOnce() -> bool) -> Self {
+        let mut characteristic = Self::new(id, accessory_id);
+        characteristic.0.value = f();
+        characteristic
+    }
+
+    /// Returns a reference to the stored value of the Administrator Only Access Characteristic without invoking any
+    /// read callbacks.
+    pub fn value_ref(&self) -> &bool { self.0.value_ref() }
+
+    /// Inverts the value of the Administrator Only Access Characteristic and returns the new value.
+    pub async fn toggle(&mut self) -> Result<bool> { self.0.toggle().await }
+}
+
+impl From<AdministratorOnlyAccessCharacteristic> for Characteristic<bool> {
+    /// Returns the `Characteristic` configured with the format, permissions, unit and bounds of the
+    /// Administrator Only Access Characteristic.
+    fn from(characteristic: AdministratorOnlyAccessCharacteristic) -> Self { characteristic.0 }
 }
 
 #[async_trait]
@@ -63,28 +88,25 @@ impl HapCharacteristic for AdministratorOnlyAccessCharacteristic {
         self.0.set_event_notifications(event_notifications)
     }
 
-    async fn get_value(&mut self) -> Result<serde_json::Value> {
-        let value = self.0.get_value().await?;
-        Ok(json!(value))
-    }
+    fn is_reachable(&self) -> bool { self.0.is_reachable() }
 
-    async fn set_value(&mut self, value: serde_json::Value) -> Result<()> {
-        let v;
-        // for whatever reason, the controller is setting boolean values either as a boolean or as an integer
-        if self.0.format == Format::Bool && value.is_number() {
-            let num_v: u8 = serde_json::from_value(value)?;
-            if num_v == 0 {
-                v = serde_json::from_value(json!(false))?;
-            } else if num_v == 1 {
-                v = serde_json::from_value(json!(true))?;
-            } else {
-                return Err(Error::InvalidValue(self.get_format()));
-            }
-        } else {
-            v = serde_json::from_value(value).map_err(|_| Error::InvalidValue(self.get_format()))?;
-        }
-        self.0.set_value(v).await
-    }
+    fn set_reachable(&mut self, reachable: bool) { self.0.set_reachable(reachable) }
+
+    async fn handle_subscribe(&self) { self.0.handle_subscribe().await }
+
+    fn handle_unsubscribe(&self) { self.0.handle_unsubscribe() }
+
+    fn subscriber_count(&self) -> usize { self.0.subscriber_count() }
+
+    fn needs_refresh(&self, interval: Duration) -> bool { self.0.needs_refresh(interval) }
+
+    async fn tick(&mut self) -> Result<bool> { self.0.tick().await }
+
+    async fn get_value(&mut self) -> Result<serde_json::Value> { self.0.get_json_value().await }
+
+    fn get_cached_value(&self) -> serde_json::Value { self.0.get_cached_value() }
+
+    async fn set_value(&mut self, value: serde_json::Value) -> Result<()> { self.0.set_json_value(value).await }
 
     fn get_unit(&self) -> Option<Unit> { self.0.get_unit() }
 
@@ -92,9 +114,23 @@ impl HapCharacteristic for AdministratorOnlyAccessCharacteristic {
 
     fn get_min_value(&self) -> Option<serde_json::Value> { self.0.get_min_value().map(|v| json!(v)) }
 
-    fn get_step_value(&self) -> Option<serde_json::Value> { self.0.get_step_value().map(|v| json!(v)) }
+    fn get_step_value(&self) -> Option<serde_json::Value> { self.0.get_advertised_step().map(|v| json!(v)) }
 
     fn get_max_len(&self) -> Option<u16> { self.0.get_max_len() }
+
+    fn metadata_hash(&self) -> u64 { self.0.metadata_hash() }
+
+    fn detach(&mut self) { self.0.detach() }
+
+    fn take_linked_updates(&mut self) -> Vec<(u64, serde_json::Value)> { self.0.take_linked_updates() }
+
+    async fn set_linked_value(&mut self, value: serde_json::Value) -> Result<()> {
+        self.0.set_linked_value(value).await
+    }
+
+    fn as_any(&self) -> &dyn Any { &self.0 }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any { &mut self.0 }
 }
 
 impl HapCharacteristicSetup for AdministratorOnlyAccessCharacteristic {
@@ -107,6 +143,10 @@ impl CharacteristicCallbacks<bool> for AdministratorOnlyAccessCharacteristic {
     fn on_read(&mut self, f: Option<impl OnReadFn<bool>>) { self.0.on_read(f) }
 
     fn on_update(&mut self, f: Option<impl OnUpdateFn<bool>>) { self.0.on_update(f) }
+
+    fn on_committed(&mut self, f: Option<impl OnCommittedFn<bool>>) { self.0.on_committed(f) }
+
+    fn link_rule(&mut self, other_iid: u64, f: impl LinkRuleFn<bool>) { self.0.link_rule(other_iid, f) }
 }
 
 impl AsyncCharacteristicCallbacks<bool> for AdministratorOnlyAccessCharacteristic {
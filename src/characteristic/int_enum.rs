@@ -0,0 +1,103 @@
+use std::convert::TryFrom;
+
+use serde::{
+    de::{self, Deserializer, Unexpected},
+    ser::Serializer,
+    Deserialize,
+    Serialize,
+};
+
+/// Wrapper around an enum value of a Characteristic that is serialized as its integer value. HAP requires integers
+/// for enumerated Characteristics like Target Heating Cooling State, e.g. `1` instead of `"Heat"`, which the derived
+/// `Serialize` implementation of an enum doesn't produce. The enum is converted with its `From` and `TryFrom`
+/// implementations for `u8`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct IntEnum<E>(pub E);
+
+impl<E> Serialize for IntEnum<E>
+where
+    E: Copy + Into<u8>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.0.into())
+    }
+}
+
+impl<'de, E> Deserialize<'de> for IntEnum<E>
+where
+    E: TryFrom<u8>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = u8::deserialize(deserializer)?;
+        E::try_from(value)
+            .map(IntEnum)
+            .map_err(|_| de::Error::invalid_value(Unexpected::Unsigned(value as u64), &"a value of the enum"))
+    }
+}
+
+impl<E> From<E> for IntEnum<E> {
+    fn from(value: E) -> Self { IntEnum(value) }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        characteristic::{Characteristic, Format, Perm},
+        HapType,
+    };
+
+    #[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+    enum TargetState {
+        #[default]
+        Off = 0,
+        Heat = 1,
+        Cool = 2,
+    }
+
+    impl From<TargetState> for u8 {
+        fn from(state: TargetState) -> Self { state as u8 }
+    }
+
+    impl TryFrom<u8> for TargetState {
+        type Error = ();
+
+        fn try_from(value: u8) -> Result<Self, ()> {
+            match value {
+                0 => Ok(TargetState::Off),
+                1 => Ok(TargetState::Heat),
+                2 => Ok(TargetState::Cool),
+                _ => Err(()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_int_enum_serialization() {
+        assert_eq!(json!(TargetState::Heat), json!("Heat"));
+        assert_eq!(json!(IntEnum(TargetState::Heat)), json!(1));
+
+        let characteristic = Characteristic::<IntEnum<TargetState>> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::TargetHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            value: IntEnum(TargetState::Heat),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_string(&characteristic).unwrap(),
+            "{\"iid\":1,\"type\":\"33\",\"format\":\"uint8\",\"perms\":[\"pr\",\"pw\"],\"value\":1}"
+        );
+
+        assert_eq!(
+            serde_json::from_value::<IntEnum<TargetState>>(json!(2)).unwrap(),
+            IntEnum(TargetState::Cool)
+        );
+        assert!(serde_json::from_value::<IntEnum<TargetState>>(json!(3)).is_err());
+        assert!(serde_json::from_value::<IntEnum<TargetState>>(json!("Heat")).is_err());
+    }
+}
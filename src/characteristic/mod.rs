@@ -1,19 +1,52 @@
-use std::fmt;
+use std::{
+    any::Any,
+    borrow::Cow,
+    collections::VecDeque,
+    fmt,
+    io,
+    ops::RangeInclusive,
+    panic::{self, AssertUnwindSafe},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        RwLock,
+    },
+    time::{Duration, Instant},
+};
+#[cfg(feature = "metrics")]
+use std::sync::atomic::AtomicU64;
+#[cfg(test)]
+use std::sync::{Arc, Mutex as StdMutex};
 
+use aead::{Aead, NewAead};
 use async_trait::async_trait;
+use chacha20poly1305::ChaCha20Poly1305;
 use erased_serde::serialize_trait_object;
-use futures::future::BoxFuture;
+use futures::future::{BoxFuture, Future, FutureExt};
 use serde::{
+    de::DeserializeOwned,
     ser::{SerializeStruct, Serializer},
     Deserialize,
     Serialize,
 };
+use rand::{rngs::OsRng, RngCore};
 use serde_json::json;
+use sha2::{Digest, Sha512};
+#[cfg(feature = "tracing")]
+use tracing::Instrument;
 
-use crate::{event::Event, pointer, HapType, Result};
+use crate::{config::Config, event::Event, pointer, Error, HapType, Result};
 
+mod ble;
+mod dynamic;
 mod generated;
+mod int_enum;
+mod persistence;
+mod simulation;
 
+pub use dynamic::DynCharacteristic;
+pub use int_enum::IntEnum;
+pub use simulation::SimulationSource;
 pub use generated::*;
 
 /// A characteristic. A characteristic is a feature that represents data or an associated behavior of a service. The
@@ -24,49 +57,193 @@ pub struct Characteristic<T: fmt::Debug + Default + Clone + Serialize + Send + S
     id: u64,
     accessory_id: u64,
     hap_type: HapType,
+    type_format: TypeFormat,
+    id_format: IdFormat,
+    serialization_mode: SerializationMode,
     format: Format,
     perms: Vec<Perm>,
     description: Option<String>,
     event_notifications: Option<bool>,
+    emit_on_subscribe: bool,
+    subscribers: AtomicUsize,
+    event_verbosity: EventVerbosity,
+    error_events: bool,
+    linked_iids: Vec<u64>,
+    programmable: bool,
 
     value: T,
+    version: u64,
+    value_set: bool,
     unit: Option<Unit>,
 
     max_value: Option<T>,
     min_value: Option<T>,
     step_value: Option<T>,
+    advertised_step: Option<T>,
+    notify_threshold: Option<T>,
+    last_notified: Option<f64>,
+    min_notify_interval: Option<Duration>,
+    last_notified_at: Option<Instant>,
+    notify_pending: bool,
+    structure_change_pending: bool,
     max_len: Option<u16>,
     max_data_len: Option<u32>,
     valid_values: Option<Vec<T>>,
     valid_values_range: Option<[T; 2]>,
+    bool_valid_values: bool,
+    precision: Option<u8>,
+    sanitization: Sanitization,
+    idempotent_write_policy: IdempotentWritePolicy,
+    float_coercion: FloatCoercion,
+    bounds_mode: BoundsMode,
+    bool_encoding: BoolEncoding,
+    #[cfg(feature = "regex")]
+    pattern: Option<regex::Regex>,
+    writes_locked: bool,
+    unreachable: bool,
+    reject_hidden_writes: bool,
+    catch_panics: bool,
+    drop_closed_event_emitters: bool,
+    frozen: bool,
+    auto_reset: Option<Duration>,
+    reset_at: Option<Instant>,
+    write_coalescing: Option<Duration>,
+    write_dedup_window: Option<Duration>,
+    last_json_write: Option<(serde_json::Value, Instant)>,
+    read_cache_ttl: Option<Duration>,
+    last_read: Option<Instant>,
+    pending_update: Option<(T, Instant)>,
+    history_capacity: usize,
+    history: VecDeque<(Instant, T)>,
+    last_updated: Option<Instant>,
 
     on_read: Option<Box<dyn OnReadFn<T>>>,
     on_update: Option<Box<dyn OnUpdateFn<T>>>,
     on_read_async: Option<Box<dyn OnReadFuture<T>>>,
     on_update_async: Option<Box<dyn OnUpdateFuture<T>>>,
+    on_committed: Option<Box<dyn OnCommittedFn<T>>>,
+    link_rules: Vec<(u64, Box<dyn LinkRuleFn<T>>)>,
+    linked_updates: Vec<(u64, serde_json::Value)>,
+    write_action: Option<Box<dyn FnMut() + 'static + Send + Sync>>,
+    write_pipeline: Option<WritePipeline>,
+    data_source: Option<Box<dyn DataSource>>,
+
+    event_emitters: Vec<pointer::EventEmitter>,
+
+    batch: Option<Batch<T>>,
+    snapshot: Option<serde_json::Map<String, serde_json::Value>>,
+    clock: Option<Box<dyn Clock>>,
+    encryption: Option<Encryption>,
+
+    #[cfg(feature = "metrics")]
+    metrics: Metrics,
+}
+
+/// State of an open write batch on a `Characteristic`.
+struct Batch<T> {
+    value: T,
+    sealed: Option<Vec<u8>>,
+    version: u64,
+    value_set: bool,
+    reset_at: Option<Instant>,
+    last_json_write: Option<(serde_json::Value, Instant)>,
+    history: VecDeque<(Instant, T)>,
+    last_updated: Option<Instant>,
+    events: Vec<Event>,
+}
+
+/// Key and sealed value of a `Characteristic` whose value is encrypted at rest.
+struct Encryption {
+    cipher: ChaCha20Poly1305,
+    /// Nonce followed by the encrypted JSON form of the value.
+    sealed: Vec<u8>,
+}
+
+impl Encryption {
+    fn new(key: &[u8; 32]) -> Self {
+        Encryption {
+            cipher: ChaCha20Poly1305::new(key.into()),
+            sealed: Vec::new(),
+        }
+    }
+
+    /// Encrypts a value with a fresh random nonce and stores it.
+    fn seal<T: Serialize>(&mut self, value: &T) -> Result<()> {
+        let mut nonce = [0; 12];
+        OsRng.fill_bytes(&mut nonce);
+        let plaintext = serde_json::to_vec(value)?;
+        let ciphertext = self.cipher.encrypt((&nonce).into(), plaintext.as_slice())?;
+        self.sealed = [&nonce[..], &ciphertext].concat();
+        Ok(())
+    }
 
-    event_emitter: Option<pointer::EventEmitter>,
+    /// Decrypts the stored value. Returns `None` if no value has been stored yet.
+    fn open<T: DeserializeOwned>(&self) -> Result<Option<T>> {
+        if self.sealed.len() < 12 {
+            return Ok(None);
+        }
+        let (nonce_bytes, ciphertext) = self.sealed.split_at(12);
+        let mut nonce = [0; 12];
+        nonce.copy_from_slice(nonce_bytes);
+        let plaintext = self.cipher.decrypt((&nonce).into(), ciphertext)?;
+        Ok(Some(serde_json::from_slice(&plaintext)?))
+    }
 }
 
 impl<T: fmt::Debug + Default + Clone + Serialize + Send + Sync> fmt::Debug for Characteristic<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Characteristic")
-            .field("id", &self.id)
+        let mut d = f.debug_struct("Characteristic");
+        d.field("id", &self.id)
             .field("accessory_id", &self.accessory_id)
             .field("hap_type", &self.hap_type)
+            .field("type_format", &self.type_format)
+            .field("id_format", &self.id_format)
+            .field("serialization_mode", &self.serialization_mode)
             .field("format", &self.format)
             .field("perms", &self.perms)
             .field("description", &self.description)
             .field("event_notifications", &self.event_notifications)
+            .field("emit_on_subscribe", &self.emit_on_subscribe)
+            .field("subscribers", &self.subscribers)
+            .field("event_verbosity", &self.event_verbosity)
+            .field("error_events", &self.error_events)
+            .field("linked_iids", &self.linked_iids)
+            .field("programmable", &self.programmable)
             .field("value", &self.value)
+            .field("version", &self.version)
             .field("unit", &self.unit)
             .field("max_value", &self.max_value)
             .field("min_value", &self.min_value)
             .field("step_value", &self.step_value)
+            .field("advertised_step", &self.advertised_step)
+            .field("notify_threshold", &self.notify_threshold)
+            .field("min_notify_interval", &self.min_notify_interval)
             .field("max_len", &self.max_len)
             .field("max_data_len", &self.max_data_len)
             .field("valid_values", &self.valid_values)
             .field("valid_values_range", &self.valid_values_range)
+            .field("bool_valid_values", &self.bool_valid_values)
+            .field("precision", &self.precision)
+            .field("sanitization", &self.sanitization)
+            .field("idempotent_write_policy", &self.idempotent_write_policy)
+            .field("float_coercion", &self.float_coercion)
+            .field("bounds_mode", &self.bounds_mode)
+            .field("bool_encoding", &self.bool_encoding);
+        #[cfg(feature = "regex")]
+        d.field("pattern", &self.pattern);
+        d.field("writes_locked", &self.writes_locked)
+            .field("unreachable", &self.unreachable)
+            .field("reject_hidden_writes", &self.reject_hidden_writes)
+            .field("catch_panics", &self.catch_panics)
+            .field("drop_closed_event_emitters", &self.drop_closed_event_emitters)
+            .field("frozen", &self.frozen)
+            .field("auto_reset", &self.auto_reset)
+            .field("write_coalescing", &self.write_coalescing)
+            .field("write_dedup_window", &self.write_dedup_window)
+            .field("read_cache_ttl", &self.read_cache_ttl)
+            .field("history_capacity", &self.history_capacity)
+            .field("last_updated", &self.last_updated)
+            .field("encrypted", &self.encryption.is_some())
             .finish()
     }
 }
@@ -75,20 +252,96 @@ impl<T: fmt::Debug + Default + Clone + Serialize + Send + Sync> Characteristic<T
 where
     for<'de> T: Deserialize<'de>,
 {
+    /// Creates a new Characteristic with the default value of `T`. Numeric Characteristics get the standard unit of
+    /// their `HapType` as returned by `default_unit`. Returns an `Error::InvalidValue` if `T` doesn't have the data type
+    /// of the `Format`. See the `characteristic!` macro for a shorter way to call it.
+    pub fn new(id: u64, accessory_id: u64, hap_type: HapType, format: Format, perms: Vec<Perm>) -> Result<Self> {
+        let value = json!(T::default());
+        let valid = match format {
+            Format::Bool => value.is_boolean(),
+            Format::UInt8 | Format::UInt16 | Format::UInt32 | Format::UInt64 => value.is_u64(),
+            Format::Int32 => value.is_i64(),
+            Format::Float => value.is_number(),
+            Format::String => value.is_string(),
+            Format::Tlv8 | Format::Data => value.is_string() || value.is_array(),
+        };
+        if !valid {
+            return Err(Error::InvalidValue(format));
+        }
+
+        let characteristic = Characteristic {
+            id,
+            accessory_id,
+            hap_type,
+            format,
+            perms,
+            unit: default_unit(hap_type).filter(|_| format.is_numeric()),
+            ..Default::default()
+        };
+        characteristic.validate()?;
+
+        Ok(characteristic)
+    }
+
     /// Returns the ID of a Characteristic.
     pub fn get_id(&self) -> u64 { self.id }
 
     /// Returns the `HapType` of a Characteristic.
     pub fn get_type(&self) -> HapType { self.hap_type }
 
+    /// Returns how the `HapType` of a Characteristic is serialized.
+    pub fn get_type_format(&self) -> TypeFormat { self.type_format }
+
+    /// Sets how the `HapType` of a Characteristic is serialized. Defaults to the short form used by the HAP IP
+    /// transport.
+    pub fn set_type_format(&mut self, type_format: TypeFormat) -> Result<()> {
+        self.check_not_frozen()?;
+        self.type_format = type_format;
+        Ok(())
+    }
+
+    /// Returns how the instance and accessory IDs of a Characteristic are serialized.
+    pub fn get_id_format(&self) -> IdFormat { self.id_format }
+
+    /// Sets how the instance and accessory IDs of a Characteristic are serialized. Defaults to JSON numbers as used by
+    /// the HAP IP transport.
+    pub fn set_id_format(&mut self, id_format: IdFormat) -> Result<()> {
+        self.check_not_frozen()?;
+        self.id_format = id_format;
+        Ok(())
+    }
+
+    /// Returns the `SerializationMode` of a Characteristic.
+    pub fn get_serialization_mode(&self) -> SerializationMode { self.serialization_mode }
+
+    /// Sets the `SerializationMode` of a Characteristic. Defaults to `Modern`; `Legacy` omits metadata fields older
+    /// controllers don't know about.
+    pub fn set_serialization_mode(&mut self, serialization_mode: SerializationMode) -> Result<()> {
+        self.check_not_frozen()?;
+        self.serialization_mode = serialization_mode;
+        Ok(())
+    }
+
     /// Returns the `Format` of a Characteristic.
     pub fn get_format(&self) -> Format { self.format }
 
     /// Returns the `Perm`s of a Characteristic.
     pub fn get_perms(&self) -> Vec<Perm> { self.perms.clone() }
 
-    /// Sets the description of a Characteristic.
-    pub fn set_description(&mut self, description: Option<String>) { self.description = description; }
+    /// Sets the description of a Characteristic. Does nothing if the metadata of the Characteristic is frozen.
+    pub fn set_description(&mut self, description: Option<String>) {
+        if !self.frozen {
+            self.description = description;
+        }
+    }
+
+    /// Returns the description of a Characteristic if set, otherwise the name of its `HapType`, e.g. for logging.
+    pub fn effective_description(&self) -> String {
+        match self.description {
+            Some(ref description) => description.clone(),
+            None => self.hap_type.name().into(),
+        }
+    }
 
     /// Returns the event notifications value of a Characteristic.
     pub fn get_event_notifications(&self) -> Option<bool> { self.event_notifications }
@@ -98,325 +351,4735 @@ where
         self.event_notifications = event_notifications;
     }
 
+    /// Returns whether the accessory behind a Characteristic is reachable.
+    pub fn is_reachable(&self) -> bool { !self.unreachable }
+
+    /// Sets whether the accessory behind a Characteristic is reachable, e.g. a bridged device that dropped off the
+    /// network. While unreachable, reads and writes by controllers fail with `Error::Unreachable`, which is reported
+    /// to them as a communication failure, so the Home app shows the accessory as "No Response". The stored value can
+    /// still be read and written locally. Defaults to `true`.
+    pub fn set_reachable(&mut self, reachable: bool) { self.unreachable = !reachable; }
+
+    /// Returns the event notifications acknowledgment object of a Characteristic, i.e. its `aid`, `iid` and `ev`
+    /// without the rest of its fields.
+    pub fn event_notifications_to_json(&self) -> serde_json::Value {
+        json!({
+            "aid": self.accessory_id,
+            "iid": self.id,
+            "ev": self.event_notifications.unwrap_or(false),
+        })
+    }
+
+    /// Returns whether a Characteristic emits its current value when a controller subscribes to it.
+    pub fn get_emit_on_subscribe(&self) -> bool { self.emit_on_subscribe }
+
+    /// Sets whether a Characteristic emits its current value when a controller subscribes to it. Defaults to `false`.
+    pub fn set_emit_on_subscribe(&mut self, emit_on_subscribe: bool) { self.emit_on_subscribe = emit_on_subscribe; }
+
+    /// Returns the `EventVerbosity` of a Characteristic.
+    pub fn get_event_verbosity(&self) -> EventVerbosity { self.event_verbosity }
+
+    /// Sets the `EventVerbosity` of a Characteristic, i.e. whether its value change events include its unit and
+    /// format. Controllers only ever receive the value.
+    pub fn set_event_verbosity(&mut self, event_verbosity: EventVerbosity) { self.event_verbosity = event_verbosity; }
+
+    /// Returns whether a Characteristic emits error events.
+    pub fn get_error_events(&self) -> bool { self.error_events }
+
+    /// Sets whether a Characteristic emits a `CharacteristicError` event when reading or writing its value fails, e.g.
+    /// to count rejected writes on a dashboard. The event carries the name of the `Error` variant as its kind and is
+    /// never sent to controllers. Defaults to `false`.
+    pub fn set_error_events(&mut self, error_events: bool) { self.error_events = error_events; }
+
+    /// Emits the current value of a Characteristic without changing it.
+    pub async fn emit_current(&self) {
+        self.emit(&self.value_changed_event(false)).await;
+    }
+
+    /// Handles a new event subscription of a controller to a Characteristic.
+    pub async fn handle_subscribe(&self) {
+        self.subscribers.fetch_add(1, Ordering::Relaxed);
+        if self.emit_on_subscribe {
+            self.emit_current().await;
+        }
+    }
+
+    /// Handles the removal of the event subscription of a controller to a Characteristic.
+    pub fn handle_unsubscribe(&self) {
+        let _ = self
+            .subscribers
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+    }
+
+    /// Returns the number of controllers subscribed to events of a Characteristic, e.g. to skip expensive work while
+    /// nobody is listening.
+    pub fn subscriber_count(&self) -> usize { self.subscribers.load(Ordering::Relaxed) }
+
+    /// Returns the instance IDs of the Characteristics linked to a Characteristic.
+    pub fn get_linked_iids(&self) -> Vec<u64> { self.linked_iids.clone() }
+
+    /// Sets the instance IDs of the Characteristics linked to a Characteristic, e.g. the Remaining Duration linked to
+    /// the Set Duration of a valve.
+    pub fn set_linked_iids(&mut self, linked_iids: Vec<u64>) -> Result<()> {
+        self.check_not_frozen()?;
+        self.linked_iids = linked_iids;
+        Ok(())
+    }
+
+    /// Returns whether a Characteristic participates in programmable events.
+    pub fn get_programmable(&self) -> bool { self.programmable }
+
+    /// Sets whether a Characteristic participates in programmable events, e.g. the Programmable Switch Event of a
+    /// doorbell that can trigger automations. Serialized as `programmable` only when set. Defaults to `false`.
+    pub fn set_programmable(&mut self, programmable: bool) -> Result<()> {
+        self.check_not_frozen()?;
+        self.programmable = programmable;
+        Ok(())
+    }
+
     /// Returns the value of a Characteristic.
     pub async fn get_value(&mut self) -> Result<T> {
-        let mut val = None;
-        if let Some(ref on_read) = self.on_read {
-            val = on_read();
+        let fresh = self.read_value().await?;
+        Ok(fresh.unwrap_or_else(|| self.value.clone()))
+    }
+
+    /// Returns the value of a Characteristic without cloning it if possible. The value is borrowed if it was read from
+    /// memory and owned if a read callback or `DataSource` produced a fresh value, e.g. to inspect large string or
+    /// data values cheaply.
+    pub async fn get_value_cow(&mut self) -> Result<Cow<'_, T>> {
+        Ok(match self.read_value().await? {
+            Some(fresh) => Cow::Owned(fresh),
+            None => Cow::Borrowed(&self.value),
+        })
+    }
+
+    /// Reads the value of a Characteristic, returning a fresh value produced by its read callbacks or `DataSource` or
+    /// `None` if the stored value is current.
+    async fn read_value(&mut self) -> Result<Option<T>> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("get_value", aid = self.accessory_id, iid = self.id, hap_type = ?self.hap_type);
+        let read = self.read_value_inner();
+        #[cfg(feature = "tracing")]
+        let read = read.instrument(span);
+        let result = read.await;
+        self.emit_error(&result).await;
+        result
+    }
+
+    async fn read_value_inner(&mut self) -> Result<Option<T>> {
+        #[cfg(feature = "metrics")]
+        self.metrics.reads.fetch_add(1, Ordering::Relaxed);
+
+        // a failed reset doesn't make the current value unreadable
+        if let Err(e) = self.reset_if_expired().await {
+            self.emit_error::<()>(&Err(e)).await;
+        }
+        self.update_if_settled().await?;
+        self.notify_structure_if_pending().await;
+
+        // large values are fetched on demand and not kept in memory
+        if let Some(ref data_source) = self.data_source {
+            let data = data_source.fetch().await?;
+            return serde_json::from_value(json!(data))
+                .map(Some)
+                .map_err(|_| Error::InvalidValue(self.format));
         }
-        if let Some(ref on_read_async) = self.on_read_async {
-            val = on_read_async().await;
+
+        let now = self.now();
+        let cached = match (self.read_cache_ttl, self.last_read) {
+            (Some(ttl), Some(last_read)) => now.saturating_duration_since(last_read) < ttl,
+            _ => false,
+        };
+        let mut val = None;
+        if !cached {
+            if let Some(ref on_read) = self.on_read {
+                val = guard(self.catch_panics, on_read)?;
+                self.last_read = Some(now);
+            }
+            if let Some(ref on_read_async) = self.on_read_async {
+                val = guard_async(self.catch_panics, on_read_async()).await?;
+                self.last_read = Some(now);
+            }
         }
-        if let Some(v) = val {
-            self.set_value(v).await?;
+        match val {
+            Some(v) => {
+                // values read from the device are stored as they are, even if they exceed the constraints
+                self.set_value_inner(v, false).await?;
+                match self.encryption {
+                    Some(_) => Ok(Some(self.open_value()?)),
+                    None => Ok(Some(self.value.clone())),
+                }
+            },
+            None if self.encryption.is_some() => Ok(Some(self.open_value()?)),
+            None => Ok(None),
         }
+    }
+
+    /// Returns a reference to the stored value of a Characteristic without invoking any read callbacks.
+    pub fn value_ref(&self) -> &T { &self.value }
 
-        Ok(self.value.clone())
+    /// Returns the version of the value of a Characteristic, which is incremented by every stored write.
+    pub fn version(&self) -> u64 { self.version }
+
+    /// Sets the value of a Characteristic if its version still is `expected_version`, e.g. to avoid lost updates when
+    /// multiple writers race. Returns an `Error::VersionMismatch` without invoking any callbacks otherwise.
+    pub async fn compare_and_set(&mut self, expected_version: u64, val: T) -> Result<()> {
+        if self.version != expected_version {
+            return Err(Error::VersionMismatch {
+                expected: expected_version,
+                actual: self.version,
+            });
+        }
+        self.set_value(val).await
     }
 
     /// Sets the value of a Characteristic.
     pub async fn set_value(&mut self, val: T) -> Result<()> {
-        // TODO: check for min/max on types implementing PartialOrd
-        // if let Some(ref max) = self.inner.try_borrow()?.max_value {
-        //     if &val > max {
-        //         return Err(Error::from_str("value above max_value"));
-        //     }
-        // }
-        // if let Some(ref min) = self.inner.try_borrow()?.min_value {
-        //     if &val < min {
-        //         return Err(Error::from_str("value below min_value"));
-        //     }
-        // }
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("set_value", aid = self.accessory_id, iid = self.id, hap_type = ?self.hap_type);
+        let write = self.set_value_inner(val, true);
+        #[cfg(feature = "tracing")]
+        let write = write.instrument(span);
+        let result = write.await;
+        self.emit_error(&result).await;
+        result
+    }
 
-        let old_val = self.value.clone();
-        if let Some(ref on_update) = self.on_update {
-            on_update(&old_val, &val);
+    /// Emits a `CharacteristicError` event for a failed read or write if error events are enabled.
+    async fn emit_error<R>(&self, result: &Result<R>) {
+        if let (Err(e), true) = (result, self.error_events) {
+            self.emit(&Event::CharacteristicError {
+                aid: self.accessory_id,
+                iid: self.id,
+                kind: e.kind().to_string(),
+            })
+            .await;
         }
-        if let Some(ref on_update_async) = self.on_update_async {
-            on_update_async(old_val, val.clone()).await;
+    }
+
+    async fn set_value_inner(&mut self, val: T, checked: bool) -> Result<()> {
+        if self.encryption.is_none() {
+            return self.store_value(val, checked).await;
         }
 
-        if self.event_notifications == Some(true) {
-            if let Some(ref event_emitter) = self.event_emitter {
-                event_emitter
-                    .lock()
-                    .await
-                    .emit(&Event::CharacteristicValueChanged {
-                        aid: self.accessory_id,
-                        iid: self.id,
-                        value: json!(&val),
-                    })
-                    .await;
-            }
+        // the plaintext is only held while the write is processed
+        self.value = self.open_value()?;
+        let result = self.store_value(val, checked).await;
+        let value = std::mem::take(&mut self.value);
+        if let Some(ref mut encryption) = self.encryption {
+            encryption.seal(&value)?;
         }
+        result
+    }
 
-        self.value = val;
+    /// Decrypts the value of an encrypted Characteristic, falling back to the default value if none was stored yet.
+    fn open_value(&self) -> Result<T> {
+        match self.encryption {
+            Some(ref encryption) => Ok(encryption.open()?.unwrap_or_default()),
+            None => Ok(self.value.clone()),
+        }
+    }
 
+    /// Returns whether the value of a Characteristic is encrypted at rest.
+    pub fn is_encrypted(&self) -> bool { self.encryption.is_some() }
+
+    /// Sets the key the value of a Characteristic is encrypted with while it is stored in memory, e.g. for
+    /// configuration TLVs carrying credentials. The value is encrypted with ChaCha20-Poly1305 and only decrypted while
+    /// it is read or written, so `get_value` returns it unchanged while `value_ref` and `get_cached_value` return the
+    /// default value and the value is left out of the serialized Characteristic. Passing `None` decrypts the value and
+    /// stores it in plain again.
+    ///
+    /// This only shortens the time the plaintext is held in memory. The key itself, values passed to callbacks, held
+    /// in the history or emitted in events and freed buffers that held the plaintext remain readable from a memory
+    /// dump.
+    pub fn set_encryption_key(&mut self, key: Option<[u8; 32]>) -> Result<()> {
+        let value = match self.encryption {
+            Some(_) => self.open_value()?,
+            None => std::mem::take(&mut self.value),
+        };
+        match key {
+            Some(ref key) => {
+                let mut encryption = Encryption::new(key);
+                encryption.seal(&value)?;
+                self.encryption = Some(encryption);
+            },
+            None => {
+                self.encryption = None;
+                self.value = value;
+            },
+        }
         Ok(())
     }
 
-    /// Returns the `Unit` of a Characteristic.
-    pub fn get_unit(&self) -> Option<Unit> { self.unit }
+    /// Stores a value, checking it against the constraints of the Characteristic first if `checked` is set.
+    async fn store_value(&mut self, val: T, checked: bool) -> Result<()> {
+        let val = if checked { self.sanitize(val)? } else { val };
+        let bounds = (self.min_value.clone(), self.max_value.clone());
+        let expanded = self.expand_bounds(&val);
+        if let (true, Err(e)) = (checked, self.would_accept(&val)) {
+            // a rejected value doesn't widen the bounds
+            self.min_value = bounds.0;
+            self.max_value = bounds.1;
+            return Err(e);
+        }
+        // the widened bounds are announced once by the next tick or read instead of on every value
+        self.structure_change_pending |= expanded;
 
-    /// Returns the maximum value of a Characteristic.
-    pub fn get_max_value(&self) -> Option<T> { self.max_value.clone() }
+        #[cfg(feature = "metrics")]
+        self.metrics.writes.fetch_add(1, Ordering::Relaxed);
 
-    /// Sets the maximum value of a Characteristic.
-    pub fn set_max_value(&mut self, val: Option<T>) { self.max_value = val; }
+        // write-triggered actions don't have a meaningful value to store
+        if let Some(ref mut write_action) = self.write_action {
+            guard(self.catch_panics, write_action)?;
+            return Ok(());
+        }
 
-    /// Returns the minimum value of a Characteristic.
-    pub fn get_min_value(&self) -> Option<T> { self.min_value.clone() }
+        self.update_if_settled().await?;
 
-    /// Sets the minimum value of a Characteristic.
-    pub fn set_min_value(&mut self, val: Option<T>) { self.min_value = val; }
+        let unchanged = self.idempotent_write_policy != IdempotentWritePolicy::AlwaysRun
+            && json!(&val) == json!(&self.value);
+        if unchanged && self.idempotent_write_policy == IdempotentWritePolicy::SkipAll {
+            return Ok(());
+        }
 
-    /// Returns the step value of a Characteristic.
-    pub fn get_step_value(&self) -> Option<T> { self.step_value.clone() }
+        let old_val = self.value.clone();
+        if let (false, Some(write_coalescing)) = (unchanged, self.write_coalescing) {
+            // the update callbacks get the value from before the burst once it settles
+            let burst_old_val = match self.pending_update.take() {
+                Some((burst_old_val, _)) => burst_old_val,
+                None => old_val,
+            };
+            self.pending_update = Some((burst_old_val, self.now() + write_coalescing));
+        } else if !unchanged {
+            if let Some(ref on_update) = self.on_update {
+                guard(self.catch_panics, || on_update(&old_val, &val))?;
+            }
+            if let Some(ref on_update_async) = self.on_update_async {
+                guard_async(self.catch_panics, on_update_async(old_val, val.clone())).await?;
+            }
+        }
 
-    /// Returns the step value of a Characteristic.
-    pub fn set_step_value(&mut self, val: Option<T>) { self.step_value = val; }
+        if let Some(auto_reset) = self.auto_reset {
+            self.reset_at = if json!(&val) != json!(T::default()) {
+                Some(self.now() + auto_reset)
+            } else {
+                None
+            };
+        }
 
-    /// Returns the maximum length of a Characteristic.
-    pub fn get_max_len(&self) -> Option<u16> { self.max_len }
+        self.value = val;
+        self.version += 1;
+        let first = !self.value_set;
+        self.value_set = true;
+        self.last_updated = Some(self.now());
+        if self.history_capacity > 0 {
+            if self.history.len() == self.history_capacity {
+                self.history.pop_front();
+            }
+            self.history.push_back((self.now(), self.value.clone()));
+        }
 
-    /// Sets a callback function on a characteristic that is called every time a controller attempts to read its value.
-    /// Returning a `Some(T)` from this function changes the value of the `Characteristic` before the Controller reads
-    /// it so the Controller reads the new value.
-    pub fn on_read(&mut self, f: Option<impl OnReadFn<T>>) {
-        self.on_read = f.map(|f| Box::new(f) as Box<dyn OnReadFn<T>>);
+        if self.event_notifications == Some(true) && self.should_notify() {
+            self.notify_value_changed(first).await;
+        }
+
+        if let Some(ref on_committed) = self.on_committed {
+            guard(self.catch_panics, || on_committed(&self.value))?;
+        }
+
+        Ok(())
     }
 
-    /// Sets a callback function on a characteristic that is called every time a controller attempts to update its
-    /// value. The first argument is a reference to the current value of the characteristic and the second argument is a
-    /// reference to the value the controller attempts to change the characteristic's to.
-    pub fn on_update(&mut self, f: Option<impl OnUpdateFn<T>>) {
-        self.on_update = f.map(|f| Box::new(f) as Box<dyn OnUpdateFn<T>>);
+    /// Emits a value change event for the stored value of a Characteristic, holding it back while a batch is open.
+    async fn notify_value_changed(&mut self, first: bool) {
+        let event = self.value_changed_event(first);
+        match self.batch {
+            Some(ref mut batch) => batch.events.push(event),
+            None => {
+                self.emit(&event).await;
+                self.prune_event_emitters();
+            },
+        }
     }
 
-    /// Sets an async callback function on a characteristic that is driven to completion by the async runtime driving
-    /// the HAP server every time a controller attempts to read its value. Returning a `Some(T)` from this function
-    /// changes the value of the characteristic before the controller reads it so the controller reads the new value.
-    pub fn on_read_async(&mut self, f: Option<impl OnReadFuture<T>>) {
-        self.on_read_async = f.map(|f| Box::new(f) as Box<dyn OnReadFuture<T>>);
+    /// Returns whether a value change event is due for the stored value of a Characteristic, i.e. its minimum notify
+    /// interval has passed since the last event and the value exceeds its notify threshold, and records it as notified
+    /// if so. A value held back by the interval is marked as pending for `notify_if_pending`.
+    fn should_notify(&mut self) -> bool {
+        let now = self.now();
+        if let (Some(interval), Some(last_notified_at)) = (self.min_notify_interval, self.last_notified_at) {
+            if now < last_notified_at + interval {
+                self.notify_pending = true;
+                return false;
+            }
+        }
+        self.notify_pending = false;
+        if !self.exceeds_notify_threshold() {
+            return false;
+        }
+        self.last_notified_at = Some(now);
+        true
     }
 
-    /// Sets an async callback function on a characteristic that is driven to completion by the async runtime driving
-    /// the HAP server every time a controller attempts to update its value. The first argument is a reference to the
-    /// current value of the characteristic and the second argument is a reference to the value the controller attempts
-    /// to change the characteristic's to.
-    pub fn on_update_async(&mut self, f: Option<impl OnUpdateFuture<T>>) {
-        self.on_update_async = f.map(|f| Box::new(f) as Box<dyn OnUpdateFuture<T>>);
+    /// Emits a value change event for the latest value of a Characteristic held back by its minimum notify interval
+    /// once the interval has passed. Returns whether the event was emitted.
+    async fn notify_if_pending(&mut self) -> bool {
+        if !self.notify_pending || self.event_notifications != Some(true) {
+            return false;
+        }
+        if self.should_notify() {
+            self.notify_value_changed(false).await;
+            return true;
+        }
+        false
     }
 
-    /// Sets a `hap::event::pointer::EventEmitter` on the Characteristic.
-    pub(crate) fn set_event_emitter(&mut self, event_emitter: Option<pointer::EventEmitter>) {
-        self.event_emitter = event_emitter;
+    /// Returns whether the stored value of a Characteristic differs from the last notified one by at least its notify
+    /// threshold and records it as notified if so. Always `true` without a threshold.
+    fn exceeds_notify_threshold(&mut self) -> bool {
+        let (threshold, v) = match (
+            self.notify_threshold.as_ref().and_then(|t| json!(t).as_f64()),
+            json!(&self.value).as_f64(),
+        ) {
+            (Some(threshold), Some(v)) => (threshold, v),
+            _ => return true,
+        };
+        if let Some(last_notified) = self.last_notified {
+            if (v - last_notified).abs() < threshold {
+                return false;
+            }
+        }
+        self.last_notified = Some(v);
+        true
     }
-}
 
-impl<T: fmt::Debug + Default + Clone + Serialize + Send + Sync> Serialize for Characteristic<T> {
-    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
-        let mut state = serializer.serialize_struct("Characteristic", 15)?;
-        state.serialize_field("iid", &self.id)?;
-        state.serialize_field("type", &self.hap_type)?;
-        state.serialize_field("format", &self.format)?;
-        state.serialize_field("perms", &self.perms)?;
-        if let Some(ref description) = self.description {
-            state.serialize_field("description", description)?;
+    /// Returns the time the value of a Characteristic was last set, as measured by its `Clock`.
+    pub fn get_last_updated(&self) -> Option<Instant> { self.last_updated }
+
+    /// Returns whether a Characteristic is readable and its value wasn't updated within `interval`, e.g. to decide
+    /// whether a bridge needs to poll the underlying device. Characteristics that were never updated need a refresh.
+    pub fn needs_refresh(&self, interval: Duration) -> bool {
+        if !self.perms.contains(&Perm::PairedRead) {
+            return false;
         }
-        if let Some(ref event_notifications) = self.event_notifications {
-            state.serialize_field("ev", event_notifications)?;
+        match self.last_updated {
+            Some(last_updated) => self.now().saturating_duration_since(last_updated) >= interval,
+            None => true,
         }
+    }
+
+    /// Returns how control characters in string values of a Characteristic are handled.
+    pub fn get_sanitization(&self) -> Sanitization { self.sanitization }
+
+    /// Sets how control characters in string values of a Characteristic are handled.
+    pub fn set_sanitization(&mut self, sanitization: Sanitization) { self.sanitization = sanitization; }
+
+    /// Returns the `IdempotentWritePolicy` of a Characteristic.
+    pub fn get_idempotent_write_policy(&self) -> IdempotentWritePolicy { self.idempotent_write_policy }
 
-        if self.perms.contains(&Perm::PairedRead) {
-            state.serialize_field("value", &self.value)?;
+    /// Sets how `set_value` handles writes of the value a Characteristic already holds. Values are compared by their
+    /// JSON representation. Defaults to `AlwaysRun`.
+    pub fn set_idempotent_write_policy(&mut self, policy: IdempotentWritePolicy) {
+        self.idempotent_write_policy = policy;
+    }
+
+    /// Returns how float values written by controllers to an integer Characteristic are handled.
+    pub fn get_float_coercion(&self) -> FloatCoercion { self.float_coercion }
+
+    /// Sets how float values written by controllers to an integer Characteristic are handled. Whole-number floats like
+    /// `3.0` are always accepted.
+    pub fn set_float_coercion(&mut self, float_coercion: FloatCoercion) { self.float_coercion = float_coercion; }
+
+    /// Returns how the values of a bool Characteristic are sent to controllers.
+    pub fn get_bool_encoding(&self) -> BoolEncoding { self.bool_encoding }
+
+    /// Sets how the values of a bool Characteristic are sent to controllers, e.g. as `0` and `1` for legacy
+    /// controllers that don't handle JSON booleans. Applies to reads, events and the serialized Characteristic.
+    /// Written values are accepted in either form regardless.
+    pub fn set_bool_encoding(&mut self, bool_encoding: BoolEncoding) { self.bool_encoding = bool_encoding; }
+
+    /// Returns how values outside the bounds of a Characteristic are handled.
+    pub fn get_bounds_mode(&self) -> BoundsMode { self.bounds_mode }
+
+    /// Sets how values outside the minimum and maximum value of a Characteristic are handled, e.g. to let the bounds of
+    /// a sensor whose range isn't known upfront follow its readings. Bounds of frozen Characteristics are never
+    /// expanded.
+    pub fn set_bounds_mode(&mut self, bounds_mode: BoundsMode) -> Result<()> {
+        self.check_not_frozen()?;
+        self.bounds_mode = bounds_mode;
+        Ok(())
+    }
+
+    /// Widens the minimum or maximum value of a Characteristic to include `val` if its `BoundsMode` is `AutoExpand`.
+    /// The bound is moved past `val` by the width of the current range, so a drifting sensor only widens the bounds
+    /// a few times. Returns whether a bound was changed.
+    fn expand_bounds(&mut self, val: &T) -> bool {
+        if self.bounds_mode != BoundsMode::AutoExpand || self.frozen {
+            return false;
         }
-        if let Some(ref unit) = self.unit {
-            state.serialize_field("unit", unit)?;
+        let v = match json!(val).as_f64() {
+            Some(v) => v,
+            None => return false,
+        };
+        let max = self.max_value.as_ref().and_then(|max| json!(max).as_f64());
+        let min = self.min_value.as_ref().and_then(|min| json!(min).as_f64());
+        let width = match (min, max) {
+            (Some(min), Some(max)) => max - min,
+            _ => 0.0,
+        };
+
+        let mut expanded = false;
+        if max.is_some_and(|max| v > max) {
+            self.max_value = Some(self.widened_bound(v + width, val));
+            expanded = true;
         }
-        if let Some(ref max_value) = self.max_value {
-            state.serialize_field("maxValue", max_value)?;
+        if min.is_some_and(|min| v < min) {
+            self.min_value = Some(self.widened_bound(v - width, val));
+            expanded = true;
         }
-        if let Some(ref min_value) = self.min_value {
-            state.serialize_field("minValue", min_value)?;
+        expanded
+    }
+
+    /// Converts a widened bound to the type of the Characteristic, clamped to the limits of its `Format` and rounded
+    /// outwards for integer formats. Falls back to `val` if the bound can't be represented.
+    fn widened_bound(&self, bound: f64, val: &T) -> T {
+        let bound = match self.format.limits() {
+            Some((min, max)) => bound.clamp(min.as_f64().unwrap_or(f64::MIN), max.as_f64().unwrap_or(f64::MAX)),
+            None => bound,
+        };
+        let bound = match (self.format.is_integer(), bound < 0.0) {
+            (true, true) => json!(bound.floor() as i64),
+            (true, false) => json!(bound.ceil() as u64),
+            (false, _) => json!(bound),
+        };
+        serde_json::from_value(bound).unwrap_or_else(|_| val.clone())
+    }
+
+    /// Emits a single `CharacteristicStructureChanged` event for the bounds widened since the last one. Returns
+    /// whether an event was emitted.
+    async fn notify_structure_if_pending(&mut self) -> bool {
+        if !self.structure_change_pending {
+            return false;
         }
-        if let Some(ref step_value) = self.step_value {
-            state.serialize_field("minStep", step_value)?;
+        self.structure_change_pending = false;
+        self.emit(&Event::CharacteristicStructureChanged {
+            aid: self.accessory_id,
+            iid: self.id,
+        })
+        .await;
+        true
+    }
+
+    /// Returns the pattern string values written to a Characteristic have to match.
+    #[cfg(feature = "regex")]
+    pub fn get_pattern(&self) -> Option<&regex::Regex> { self.pattern.as_ref() }
+
+    /// Sets the pattern string values written to a Characteristic have to match, e.g. to only accept IP addresses.
+    /// Values that don't match are rejected by `set_value`. Defaults to `None`.
+    #[cfg(feature = "regex")]
+    pub fn set_pattern(&mut self, pattern: Option<regex::Regex>) { self.pattern = pattern; }
+
+    /// Applies the `Sanitization` of the Characteristic to a string value. Other values are returned unchanged.
+    fn sanitize(&self, val: T) -> Result<T> {
+        if let serde_json::Value::String(ref s) = json!(&val) {
+            if s.chars().any(char::is_control) {
+                match self.sanitization {
+                    Sanitization::Allow => {},
+                    Sanitization::Strip => {
+                        let stripped = s.chars().filter(|c| !c.is_control()).collect::<String>();
+                        return Ok(serde_json::from_value(json!(stripped))?);
+                    },
+                    Sanitization::Reject => return Err(Error::ValueContainsControlCharacters),
+                }
+            }
         }
-        if let Some(ref max_len) = self.max_len {
-            state.serialize_field("maxLen", max_len)?;
+
+        Ok(val)
+    }
+
+    /// Checks whether a value would be accepted by `set_value`, i.e. whether it satisfies the `max_value`, `min_value`,
+    /// `step_value`, `valid_values` and `valid_values_range` constraints of the Characteristic. Doesn't change the
+    /// Characteristic or invoke any callbacks.
+    pub fn would_accept(&self, val: &T) -> Result<()> {
+        let val = json!(val);
+
+        // non-finite floats like `NaN` and `Infinity` can't be represented in JSON and are converted to `null`
+        if self.format == Format::Float && !val.is_number() {
+            return Err(Error::InvalidValue(self.format));
         }
-        if let Some(ref max_data_len) = self.max_data_len {
-            state.serialize_field("maxDataLen", max_data_len)?;
+
+        if let Some(v) = val.as_f64() {
+            let max_value = self.max_value.as_ref().and_then(|max| json!(max).as_f64());
+            let min_value = self.min_value.as_ref().and_then(|min| json!(min).as_f64());
+            let step_value = self.step_value.as_ref().and_then(|step| json!(step).as_f64());
+
+            if let Some(max) = max_value {
+                if v > max {
+                    return Err(Error::ValueAboveMaxValue);
+                }
+            }
+            if let Some(min) = min_value {
+                if v < min {
+                    return Err(Error::ValueBelowMinValue);
+                }
+            }
+            if let Some([ref start, ref end]) = self.valid_values_range {
+                if json!(start).as_f64().is_some_and(|start| v < start) {
+                    return Err(Error::ValueBelowMinValue);
+                }
+                if json!(end).as_f64().is_some_and(|end| v > end) {
+                    return Err(Error::ValueAboveMaxValue);
+                }
+            }
+            if let Some(step) = step_value {
+                if step > 0.0 {
+                    // steps are counted from the minimum value, allowing for the rounding error of single-precision
+                    // float values
+                    let steps = (v - min_value.unwrap_or(0.0)) / step;
+                    if (steps - steps.round()).abs() > f64::from(f32::EPSILON) * steps.abs().max(1.0) * 4.0 {
+                        return Err(Error::ValueNotOnStep);
+                    }
+                }
+            }
         }
+
         if let Some(ref valid_values) = self.valid_values {
-            state.serialize_field("valid-values", valid_values)?;
+            if !valid_values.iter().any(|valid_value| json!(valid_value) == val) {
+                return Err(Error::ValueNotInValidValues);
+            }
         }
-        if let Some(ref valid_values_range) = self.valid_values_range {
-            state.serialize_field("valid-values-range", valid_values_range)?;
+
+        #[cfg(feature = "regex")]
+        {
+            if let (Some(pattern), Some(s)) = (&self.pattern, val.as_str()) {
+                if !pattern.is_match(s) {
+                    return Err(Error::ValueDoesNotMatchPattern);
+                }
+            }
         }
-        state.end()
+
+        Ok(())
     }
-}
 
-/// Permission of a `Characteristic`.
-#[derive(Debug, Copy, Clone, Serialize, PartialEq)]
-pub enum Perm {
-    #[serde(rename = "pr")]
-    PairedRead,
-    #[serde(rename = "pw")]
-    PairedWrite,
-    #[serde(rename = "ev")]
-    Events,
-    #[serde(rename = "aa")]
-    AdditionalAuthorization,
-    #[serde(rename = "tw")]
-    TimedWrite,
-    #[serde(rename = "hd")]
-    Hidden,
-}
+    /// Checks whether the metadata of a Characteristic is consistent. HAP treats `valid_values` and
+    /// `valid_values_range` as mutually exclusive, so a Characteristic with both set is rejected.
+    pub fn validate(&self) -> Result<()> {
+        if self.valid_values.is_some() && self.valid_values_range.is_some() {
+            return Err(Error::ConflictingValidValues);
+        }
 
-/// Unit of a `Characteristic`.
-#[derive(Debug, Copy, Clone, Serialize)]
-pub enum Unit {
-    #[serde(rename = "percentage")]
-    Percentage,
-    #[serde(rename = "arcdegrees")]
-    ArcDegrees,
-    #[serde(rename = "celsius")]
-    Celsius,
-    #[serde(rename = "lux")]
-    Lux,
-    #[serde(rename = "seconds")]
-    Seconds,
-}
+        Ok(())
+    }
 
-/// Format (data type) of a `Characteristic`.
-#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
-pub enum Format {
-    #[serde(rename = "string")]
-    String,
-    #[serde(rename = "bool")]
-    Bool,
-    #[serde(rename = "float")]
-    Float,
-    #[serde(rename = "uint8")]
-    UInt8,
-    #[serde(rename = "uint16")]
-    UInt16,
-    #[serde(rename = "uint32")]
-    UInt32,
-    #[serde(rename = "uint64")]
-    UInt64,
-    #[serde(rename = "int32")]
-    Int32,
-    #[serde(rename = "tlv8")]
-    Tlv8,
-    #[serde(rename = "data")]
-    Data,
-}
+    /// Returns the value of a Characteristic as JSON for a controller. Returns an `Error::Unreachable` if the
+    /// Characteristic is unreachable.
+    pub async fn get_json_value(&mut self) -> Result<serde_json::Value> {
+        if self.unreachable {
+            return Err(Error::Unreachable);
+        }
+        let value = self.get_value().await?;
+        Ok(self.value_to_json(&value))
+    }
 
-impl Default for Format {
-    fn default() -> Format { Format::String }
-}
+    /// Sets the value of a Characteristic from a JSON value written by a controller.
+    pub async fn set_json_value(&mut self, mut value: serde_json::Value) -> Result<()> {
+        intercept_write(self.accessory_id, self.id, &value);
+
+        if self.unreachable {
+            return Err(Error::Unreachable);
+        }
+        if self.writes_locked {
+            return Err(Error::ResourceBusy);
+        }
+        if self.reject_hidden_writes && self.perms.contains(&Perm::Hidden) {
+            return Err(Error::HiddenCharacteristic);
+        }
+        if let Some(ref write_pipeline) = self.write_pipeline {
+            write_pipeline.run(&mut value)?;
+        }
+
+        let now = self.now();
+        if let (Some(window), Some((ref last_value, last_at))) = (self.write_dedup_window, &self.last_json_write) {
+            if *last_value == value && now < *last_at + window {
+                return Ok(());
+            }
+        }
+        let written = self.write_dedup_window.map(|_| value.clone());
+
+        let v = self.value_from_json(value)?;
+        let v = self.snap_to_advertised_step(v)?;
+        self.set_value(v).await?;
+        self.last_json_write = written.map(|written| (written, now));
+        self.queue_linked_updates();
+
+        Ok(())
+    }
+
+    /// Sets the value of a Characteristic to an update queued by the link rule of another Characteristic. Unlike
+    /// `set_json_value`, this isn't treated as a write by a controller, so it skips the write interceptor, the write
+    /// pipeline and the checks for unreachable, locked and hidden Characteristics.
+    pub async fn set_linked_value(&mut self, value: serde_json::Value) -> Result<()> {
+        let v = self.value_from_json(value)?;
+        self.set_value(v).await?;
+        self.queue_linked_updates();
+
+        Ok(())
+    }
+
+    fn value_from_json(&mut self, value: serde_json::Value) -> Result<T> {
+        let v;
+        // for whatever reason, the controller is setting boolean values either as a boolean or as an integer
+        if self.format == Format::Bool && value.is_number() {
+            let num_v: u8 = serde_json::from_value(value)?;
+            if num_v == 0 {
+                v = serde_json::from_value(json!(false))?;
+            } else if num_v == 1 {
+                v = serde_json::from_value(json!(true))?;
+            } else {
+                return Err(Error::InvalidValue(self.format));
+            }
+        } else if self.format.is_integer() && value.is_f64() {
+            // some controllers send integer values as floats, e.g. `3.0`
+            let float_v = value.as_f64().unwrap_or_default();
+            let int_v = match self.float_coercion {
+                FloatCoercion::Exact if float_v.fract() == 0.0 => float_v,
+                FloatCoercion::Exact => return Err(Error::InvalidValue(self.format)),
+                FloatCoercion::Round => {
+                    #[cfg(feature = "metrics")]
+                    if float_v.fract() != 0.0 {
+                        self.metrics.adjusted.fetch_add(1, Ordering::Relaxed);
+                    }
+                    float_v.round()
+                },
+            };
+            v = serde_json::from_value(json!(int_v as i64)).map_err(|_| Error::InvalidValue(self.format))?;
+        } else {
+            v = serde_json::from_value(value).map_err(|_| Error::InvalidValue(self.format))?;
+        }
+
+        Ok(v)
+    }
+
+    fn queue_linked_updates(&mut self) {
+        for (iid, rule) in &self.link_rules {
+            if let Some(value) = rule(&self.value) {
+                self.linked_updates.push((*iid, value));
+            }
+        }
+    }
+
+    /// Returns and clears the updates of linked Characteristics queued by the link rules of a Characteristic as pairs
+    /// of instance ID and value.
+    pub fn take_linked_updates(&mut self) -> Vec<(u64, serde_json::Value)> { std::mem::take(&mut self.linked_updates) }
+
+    /// Returns whether panics of the callbacks of a Characteristic are caught.
+    pub fn get_catch_panics(&self) -> bool { self.catch_panics }
+
+    /// Sets whether panics of the callbacks of a Characteristic are caught and returned as
+    /// `Error::CallbackPanicked` instead of unwinding through `get_value` and `set_value`, so a faulty callback
+    /// doesn't take down the server. Callbacks are treated as unwind safe, so any state they share with the rest of
+    /// the program may be left inconsistent by a caught panic. Defaults to `false`.
+    pub fn set_catch_panics(&mut self, catch_panics: bool) { self.catch_panics = catch_panics; }
+
+    /// Returns whether writes by controllers to a Characteristic are rejected.
+    pub fn get_writes_locked(&self) -> bool { self.writes_locked }
+
+    /// Sets whether writes by controllers to a Characteristic are rejected, e.g. during a firmware update. While
+    /// locked, writes by controllers fail with a resource busy status without invoking any callbacks. Reads and calls
+    /// to `set_value` on the Characteristic itself are unaffected.
+    pub fn set_writes_locked(&mut self, writes_locked: bool) { self.writes_locked = writes_locked; }
+
+    /// Returns whether writes by controllers to a hidden Characteristic are rejected.
+    pub fn get_reject_hidden_writes(&self) -> bool { self.reject_hidden_writes }
+
+    /// Sets whether writes by controllers to a Characteristic with `Perm::Hidden` are rejected, e.g. for diagnostic
+    /// Characteristics that are only updated by the accessory itself. Rejected writes fail with a read-only status
+    /// without invoking any callbacks. Calls to `set_value` on the Characteristic itself are unaffected. Defaults to
+    /// `false`.
+    pub fn set_reject_hidden_writes(&mut self, reject_hidden_writes: bool) -> Result<()> {
+        self.check_not_frozen()?;
+        self.reject_hidden_writes = reject_hidden_writes;
+        Ok(())
+    }
+
+    /// Returns the duration after which the value of a Characteristic is reset to its default.
+    pub fn get_auto_reset(&self) -> Option<Duration> { self.auto_reset }
+
+    /// Sets a duration after which the value of a Characteristic is reset to its default, e.g. to clear a Motion
+    /// Detected Characteristic when no new motion arrives. Every `set_value` to a non-default value restarts the
+    /// timer. The reset is applied by the next `tick`, which the server runs periodically if its timer is enabled with
+    /// `IpServer::set_timer_interval`, or the next read, and emits the change like any other value update.
+    pub fn set_auto_reset(&mut self, auto_reset: Option<Duration>) {
+        self.auto_reset = auto_reset;
+        if auto_reset.is_none() {
+            self.reset_at = None;
+        }
+    }
+
+    /// Returns the window within which writes to a Characteristic are coalesced.
+    pub fn get_write_coalescing(&self) -> Option<Duration> { self.write_coalescing }
+
+    /// Sets a window within which writes to a Characteristic are coalesced, e.g. to drive slow hardware only with the
+    /// final value of a burst of writes while a controller drags a slider. Every write is stored and notified right
+    /// away, but the update callbacks are deferred until no write arrived for the window. They are then invoked once
+    /// with the value from before the burst and the final value by the next `tick`, which the server runs
+    /// periodically if its timer is enabled with `IpServer::set_timer_interval`, or the next read or write. Defaults
+    /// to `None`.
+    pub fn set_write_coalescing(&mut self, write_coalescing: Option<Duration>) {
+        self.write_coalescing = write_coalescing;
+    }
+
+    /// Returns the window within which identical writes by controllers to a Characteristic are deduplicated.
+    pub fn get_write_dedup_window(&self) -> Option<Duration> { self.write_dedup_window }
+
+    /// Sets a window within which a write by a controller of the same value as the previous one is acknowledged
+    /// without being processed again, e.g. when two controllers send the same command at once. Only the first write
+    /// invokes the update callbacks and emits an event. Defaults to `None`.
+    pub fn set_write_dedup_window(&mut self, write_dedup_window: Option<Duration>) {
+        self.write_dedup_window = write_dedup_window;
+    }
+
+    /// Returns the time for which the value of a Characteristic produced by its read callbacks is served from cache.
+    pub fn get_read_cache_ttl(&self) -> Option<Duration> { self.read_cache_ttl }
+
+    /// Sets the time for which the value of a Characteristic produced by its read callbacks is served from cache, e.g.
+    /// to avoid polling slow hardware on every read by a controller. Reads within the TTL of the last invocation of the
+    /// read callbacks return the stored value without invoking them. Defaults to `None`, invoking them on every read.
+    pub fn set_read_cache_ttl(&mut self, read_cache_ttl: Option<Duration>) { self.read_cache_ttl = read_cache_ttl; }
+
+    /// Returns the number of values of a Characteristic that are recorded in its history.
+    pub fn get_history_capacity(&self) -> usize { self.history_capacity }
+
+    /// Sets the number of values of a Characteristic that are recorded in its history, e.g. to debug the behavior of
+    /// a sensor. Once the history is full, every new value replaces the oldest one. Defaults to `0`, which disables
+    /// the history.
+    pub fn set_history_capacity(&mut self, history_capacity: usize) {
+        self.history_capacity = history_capacity;
+        while self.history.len() > history_capacity {
+            self.history.pop_front();
+        }
+    }
+
+    /// Invokes the update callbacks of a Characteristic for a burst of coalesced writes if no write arrived for the
+    /// coalescing window. Returns whether the callbacks were invoked.
+    pub async fn update_if_settled(&mut self) -> Result<bool> {
+        let now = self.now();
+        let old_val = match self.pending_update.take() {
+            Some((old_val, settle_at)) if settle_at <= now => old_val,
+            pending_update => {
+                self.pending_update = pending_update;
+                return Ok(false);
+            },
+        };
+        if let Some(ref on_update) = self.on_update {
+            guard(self.catch_panics, || on_update(&old_val, &self.value))?;
+        }
+        if let Some(ref on_update_async) = self.on_update_async {
+            guard_async(self.catch_panics, on_update_async(old_val, self.value.clone())).await?;
+        }
+        Ok(true)
+    }
+
+    /// Resets the value of a Characteristic to its default if its auto reset duration has expired. Returns whether the
+    /// value was reset. The default value is stored without running the constraint checks and update callbacks, as
+    /// it's the resting state of the Characteristic rather than a write.
+    pub async fn reset_if_expired(&mut self) -> Result<bool> {
+        match self.reset_at {
+            Some(reset_at) if reset_at <= self.now() => self.reset_at = None,
+            _ => return Ok(false),
+        }
+
+        let value = T::default();
+        match self.encryption {
+            Some(ref mut encryption) => encryption.seal(&value)?,
+            None => self.value = value.clone(),
+        }
+        self.version += 1;
+        self.last_updated = Some(self.now());
+        if self.history_capacity > 0 {
+            if self.history.len() == self.history_capacity {
+                self.history.pop_front();
+            }
+            self.history.push_back((self.now(), value));
+        }
+        if self.event_notifications == Some(true) {
+            self.notify_value_changed(false).await;
+        }
+        // the next value after a reset is a first value again
+        self.value_set = false;
+
+        Ok(true)
+    }
+
+    /// Applies the time-dependent updates of a Characteristic that are due without waiting for a read or write, i.e.
+    /// invokes the update callbacks for a settled burst of coalesced writes, resets an expired auto reset value and
+    /// notifies the latest value held back by the minimum notify interval. The server calls this periodically for
+    /// every Characteristic if its timer is enabled with `IpServer::set_timer_interval`. Returns whether anything was
+    /// applied.
+    pub async fn tick(&mut self) -> Result<bool> {
+        let settled = self.update_if_settled().await?;
+        let reset = self.reset_if_expired().await?;
+        let notified = self.notify_if_pending().await;
+        let structure_changed = self.notify_structure_if_pending().await;
+        Ok(settled || reset || notified || structure_changed)
+    }
+
+    /// Stores the current serialized state of a Characteristic as the baseline for `delta_since_snapshot`.
+    pub fn snapshot(&mut self) -> Result<()> {
+        self.snapshot = match serde_json::to_value(&*self)? {
+            serde_json::Value::Object(fields) => Some(fields),
+            _ => None,
+        };
+        Ok(())
+    }
+
+    /// Returns a JSON object with the `aid` and `iid` of a Characteristic and all serialized fields that changed since
+    /// the last `snapshot`. Fields that are no longer present are set to `null`. Without a snapshot, all fields are
+    /// included.
+    pub fn delta_since_snapshot(&self) -> Result<serde_json::Value> {
+        let mut delta = serde_json::Map::new();
+        delta.insert("aid".into(), json!(self.accessory_id));
+        delta.insert("iid".into(), json!(self.id));
+
+        let current = match serde_json::to_value(self)? {
+            serde_json::Value::Object(fields) => fields,
+            _ => serde_json::Map::new(),
+        };
+        let empty = serde_json::Map::new();
+        let snapshot = self.snapshot.as_ref().unwrap_or(&empty);
+        for (key, value) in &current {
+            if snapshot.get(key) != Some(value) {
+                delta.insert(key.clone(), value.clone());
+            }
+        }
+        for key in snapshot.keys() {
+            if !current.contains_key(key) {
+                delta.insert(key.clone(), serde_json::Value::Null);
+            }
+        }
+
+        Ok(serde_json::Value::Object(delta))
+    }
+
+    /// Sets the `WritePipeline` applied to every value written to a Characteristic by a controller before it's
+    /// deserialized and validated.
+    pub fn set_write_pipeline(&mut self, write_pipeline: Option<WritePipeline>) { self.write_pipeline = write_pipeline; }
+
+    /// Sets the `Clock` used by time-dependent features of a Characteristic. Defaults to the system clock.
+    pub fn set_clock(&mut self, clock: Option<impl Clock>) {
+        self.clock = clock.map(|c| Box::new(c) as Box<dyn Clock>);
+    }
+
+    /// Opens a write batch on a Characteristic. Events caused by value changes while the batch is open are held back
+    /// until the batch is committed and discarded if it is rolled back.
+    pub fn begin_batch(&mut self) {
+        if self.batch.is_none() {
+            self.batch = Some(Batch {
+                value: self.value.clone(),
+                sealed: self.encryption.as_ref().map(|e| e.sealed.clone()),
+                version: self.version,
+                value_set: self.value_set,
+                reset_at: self.reset_at,
+                last_json_write: self.last_json_write.clone(),
+                history: self.history.clone(),
+                last_updated: self.last_updated,
+                events: Vec::new(),
+            });
+        }
+    }
+
+    /// Commits the open write batch of a Characteristic and emits the events held back during it.
+    pub async fn commit_batch(&mut self) {
+        if let Some(batch) = self.batch.take() {
+            for event in batch.events {
+                self.emit(&event).await;
+            }
+            self.prune_event_emitters();
+        }
+    }
+
+    /// Rolls back the open write batch of a Characteristic, restoring the value, version and update history it had
+    /// when the batch was opened and discarding the events held back during it.
+    pub fn rollback_batch(&mut self) {
+        if let Some(batch) = self.batch.take() {
+            self.value = batch.value;
+            if let (Some(ref mut encryption), Some(sealed)) = (&mut self.encryption, batch.sealed) {
+                encryption.sealed = sealed;
+            }
+            self.version = batch.version;
+            self.value_set = batch.value_set;
+            self.reset_at = batch.reset_at;
+            self.last_json_write = batch.last_json_write;
+            self.history = batch.history;
+            self.last_updated = batch.last_updated;
+        }
+    }
 
-/// `HapCharacteristic` is implemented by every `Characteristic`.
-#[async_trait]
-pub trait HapCharacteristic: HapCharacteristicSetup + erased_serde::Serialize + Send + Sync {
-    /// Returns the ID of a Characteristic.
-    fn get_id(&self) -> u64;
-    /// Returns the `HapType` of a Characteristic.
-    fn get_type(&self) -> HapType;
-    /// Returns the `Format` of a Characteristic.
-    fn get_format(&self) -> Format;
-    /// Returns the `Perm`s of a Characteristic.
-    fn get_perms(&self) -> Vec<Perm>;
-    /// Returns the event notifications value of a Characteristic.
-    fn get_event_notifications(&self) -> Option<bool>;
-    /// Sets the event notifications value of a Characteristic.
-    fn set_event_notifications(&mut self, event_notifications: Option<bool>);
-    /// Returns the value of a Characteristic.
-    async fn get_value(&mut self) -> Result<serde_json::Value>;
-    /// Sets the value of a Characteristic.
-    async fn set_value(&mut self, value: serde_json::Value) -> Result<()>;
     /// Returns the `Unit` of a Characteristic.
-    fn get_unit(&self) -> Option<Unit>;
+    pub fn get_unit(&self) -> Option<Unit> { self.unit }
+
     /// Returns the maximum value of a Characteristic.
-    fn get_max_value(&self) -> Option<serde_json::Value>;
+    pub fn get_max_value(&self) -> Option<T> { self.max_value.clone() }
+
+    /// Sets the maximum value of a Characteristic. Does nothing if the metadata of the Characteristic is frozen.
+    pub fn set_max_value(&mut self, val: Option<T>) {
+        if !self.frozen {
+            self.max_value = val;
+        }
+    }
+
     /// Returns the minimum value of a Characteristic.
-    fn get_min_value(&self) -> Option<serde_json::Value>;
+    pub fn get_min_value(&self) -> Option<T> { self.min_value.clone() }
+
+    /// Sets the minimum value of a Characteristic. Does nothing if the metadata of the Characteristic is frozen.
+    pub fn set_min_value(&mut self, val: Option<T>) {
+        if !self.frozen {
+            self.min_value = val;
+        }
+    }
+
+    /// Returns the minimum value of a Characteristic, falling back to the smallest value of its numeric `Format`.
+    /// Returns `None` for non-numeric formats without a minimum value.
+    pub fn effective_min(&self) -> Option<T> {
+        self.min_value
+            .clone()
+            .or_else(|| self.format.limits().and_then(|(min, _)| serde_json::from_value(min).ok()))
+    }
+
+    /// Returns the maximum value of a Characteristic, falling back to the largest value of its numeric `Format`.
+    /// Returns `None` for non-numeric formats without a maximum value.
+    pub fn effective_max(&self) -> Option<T> {
+        self.max_value
+            .clone()
+            .or_else(|| self.format.limits().and_then(|(_, max)| serde_json::from_value(max).ok()))
+    }
+
+    /// Returns the range between the minimum and maximum value of a Characteristic if both are set.
+    pub fn get_range(&self) -> Option<RangeInclusive<T>> {
+        match (&self.min_value, &self.max_value) {
+            (Some(min), Some(max)) => Some(min.clone()..=max.clone()),
+            _ => None,
+        }
+    }
+
+    /// Sets the minimum and maximum value of a Characteristic at once. Ranges whose start is above their end are
+    /// rejected.
+    pub fn set_range(&mut self, range: RangeInclusive<T>) -> Result<()> {
+        self.check_not_frozen()?;
+        let (min, max) = range.into_inner();
+        if let (Some(min), Some(max)) = (json!(&min).as_f64(), json!(&max).as_f64()) {
+            if min > max {
+                return Err(Error::InvertedRange);
+            }
+        }
+        self.min_value = Some(min);
+        self.max_value = Some(max);
+        Ok(())
+    }
+
+    /// Sets the minimum, maximum and step value, unit and valid values of a numeric Characteristic at once, replacing
+    /// the previous ones. Constraints set to `None` are cleared. The combination is rejected as a whole if the minimum
+    /// is above the maximum, the step value isn't positive or a valid value is out of range.
+    pub fn set_numeric_constraints(&mut self, constraints: NumericConstraints<T>) -> Result<()> {
+        self.check_not_frozen()?;
+        let min = constraints.min_value.as_ref().and_then(|min| json!(min).as_f64());
+        let max = constraints.max_value.as_ref().and_then(|max| json!(max).as_f64());
+        if let (Some(min), Some(max)) = (min, max) {
+            if min > max {
+                return Err(Error::InvertedRange);
+            }
+        }
+        if let Some(step) = constraints.step_value.as_ref().and_then(|step| json!(step).as_f64()) {
+            if step <= 0.0 {
+                return Err(Error::InvalidStepValue);
+            }
+        }
+        for valid_value in constraints.valid_values.iter().flatten() {
+            if let Some(valid_value) = json!(valid_value).as_f64() {
+                if min.is_some_and(|min| valid_value < min) || max.is_some_and(|max| valid_value > max) {
+                    return Err(Error::ValidValuesOutOfRange);
+                }
+            }
+        }
+
+        self.min_value = constraints.min_value;
+        self.max_value = constraints.max_value;
+        self.step_value = constraints.step_value;
+        self.unit = constraints.unit;
+        self.valid_values = constraints.valid_values;
+        Ok(())
+    }
+
     /// Returns the step value of a Characteristic.
-    fn get_step_value(&self) -> Option<serde_json::Value>;
+    pub fn get_step_value(&self) -> Option<T> { self.step_value.clone() }
+
+    /// Sets the step value of a Characteristic. Does nothing if the metadata of the Characteristic is frozen.
+    pub fn set_step_value(&mut self, val: Option<T>) {
+        if !self.frozen {
+            self.step_value = val;
+        }
+    }
+
+    /// Returns the step value of a Characteristic advertised to controllers. Defaults to the step value.
+    pub fn get_advertised_step(&self) -> Option<T> { self.advertised_step.clone().or_else(|| self.step_value.clone()) }
+
+    /// Sets the step value of a Characteristic advertised to controllers, e.g. to hint a controller to snap a dimmer to
+    /// 5% increments. Values written by controllers are snapped to the advertised step, while values set on the
+    /// Characteristic itself are stored exactly.
+    pub fn set_advertised_step(&mut self, val: Option<T>) -> Result<()> {
+        self.check_not_frozen()?;
+        self.advertised_step = val;
+        Ok(())
+    }
+
+    /// Returns whether a bool Characteristic advertises its implicit valid values.
+    pub fn get_bool_valid_values(&self) -> bool { self.bool_valid_values }
+
+    /// Sets whether a bool Characteristic advertises its implicit valid values as `valid-values` `[0, 1]`, which some
+    /// controllers handle better. Has no effect on other formats or if valid values are set. Defaults to `false`.
+    pub fn set_bool_valid_values(&mut self, bool_valid_values: bool) -> Result<()> {
+        self.check_not_frozen()?;
+        self.bool_valid_values = bool_valid_values;
+        Ok(())
+    }
+
+    /// Returns the minimum change of the value of a numeric Characteristic that is notified to controllers.
+    pub fn get_notify_threshold(&self) -> Option<T> { self.notify_threshold.clone() }
+
+    /// Sets the minimum change of the value of a numeric Characteristic that is notified to controllers, e.g. `0.5` to
+    /// only notify temperature changes of at least half a degree. Every value is stored, but value change events are
+    /// only emitted once the value differs from the last notified one by at least the threshold. Defaults to `None`.
+    pub fn set_notify_threshold(&mut self, val: Option<T>) -> Result<()> {
+        self.check_not_frozen()?;
+        self.notify_threshold = val;
+        self.last_notified = None;
+        Ok(())
+    }
+
+    /// Returns the minimum interval between two value change events of a Characteristic.
+    pub fn get_min_notify_interval(&self) -> Option<Duration> { self.min_notify_interval }
+
+    /// Sets the minimum interval between two value change events of a Characteristic, e.g. to the suggestion of
+    /// `default_min_notify_interval` for its `HapType`. Value changes within the interval are stored right away, and
+    /// the latest one is notified by the next `tick` once the interval has passed, which the server runs periodically
+    /// if its timer is enabled with `IpServer::set_timer_interval`, or the next write. Defaults to `None`, notifying
+    /// every change.
+    pub fn set_min_notify_interval(&mut self, min_notify_interval: Option<Duration>) {
+        self.min_notify_interval = min_notify_interval;
+    }
+
+    /// Snaps a numeric value written by a controller to the nearest multiple of the advertised step, counted from the
+    /// minimum value. Other values are returned unchanged.
+    fn snap_to_advertised_step(&self, val: T) -> Result<T> {
+        let step = match self.advertised_step.as_ref().and_then(|step| json!(step).as_f64()) {
+            Some(step) if step > 0.0 => step,
+            _ => return Ok(val),
+        };
+        let json_val = json!(&val);
+        let v = match json_val.as_f64() {
+            Some(v) => v,
+            None => return Ok(val),
+        };
+        let base = self.min_value.as_ref().and_then(|min| json!(min).as_f64()).unwrap_or(0.0);
+        let snapped = base + ((v - base) / step).round() * step;
+        #[cfg(feature = "metrics")]
+        if snapped != v {
+            self.metrics.adjusted.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let snapped = if json_val.is_f64() {
+            json!(snapped)
+        } else {
+            json!(snapped.round() as i64)
+        };
+        serde_json::from_value(snapped).map_err(|_| Error::InvalidValue(self.format))
+    }
+
     /// Returns the maximum length of a Characteristic.
-    fn get_max_len(&self) -> Option<u16>;
-}
+    pub fn get_max_len(&self) -> Option<u16> { self.max_len }
+
+    /// Returns whether the metadata of a Characteristic is frozen.
+    pub fn is_frozen(&self) -> bool { self.frozen }
+
+    /// Freezes the metadata of a Characteristic, e.g. after its Accessory was published to controllers. While frozen,
+    /// metadata setters leave the Characteristic unchanged. Those returning a `Result` return an
+    /// `Error::MetadataFrozen`, while `set_description`, `set_max_value`, `set_min_value` and `set_step_value` do
+    /// nothing. Values can still be set.
+    pub fn freeze(&mut self) { self.frozen = true; }
+
+    /// Unfreezes the metadata of a Characteristic. Since controllers cache the metadata of published Accessories, this
+    /// bumps the configuration number of `config`, which has to be saved afterwards to make controllers refetch it.
+    pub fn unfreeze(&mut self, config: &mut Config) {
+        self.frozen = false;
+        config.configuration_number += 1;
+    }
+
+    fn check_not_frozen(&self) -> Result<()> {
+        if self.frozen {
+            return Err(Error::MetadataFrozen);
+        }
+
+        Ok(())
+    }
+
+    /// Applies all metadata fields that are set on `metadata` to a Characteristic, e.g. the metadata of a
+    /// characteristic discovered on a bridged device, and emits a single structure change event if any were set.
+    pub async fn apply_metadata(&mut self, metadata: CharacteristicMetadata<T>) -> Result<()> {
+        self.check_not_frozen()?;
+
+        let mut changed = false;
+        if let Some(description) = metadata.description {
+            self.description = Some(description);
+            changed = true;
+        }
+        if let Some(unit) = metadata.unit {
+            self.unit = Some(unit);
+            changed = true;
+        }
+        if let Some(max_value) = metadata.max_value {
+            self.max_value = Some(max_value);
+            changed = true;
+        }
+        if let Some(min_value) = metadata.min_value {
+            self.min_value = Some(min_value);
+            changed = true;
+        }
+        if let Some(step_value) = metadata.step_value {
+            self.step_value = Some(step_value);
+            changed = true;
+        }
+        if let Some(max_len) = metadata.max_len {
+            self.max_len = Some(max_len);
+            changed = true;
+        }
+        if let Some(max_data_len) = metadata.max_data_len {
+            self.max_data_len = Some(max_data_len);
+            changed = true;
+        }
+        if let Some(valid_values) = metadata.valid_values {
+            self.valid_values = Some(valid_values);
+            changed = true;
+        }
+        if let Some(valid_values_range) = metadata.valid_values_range {
+            self.valid_values_range = Some(valid_values_range);
+            changed = true;
+        }
+
+        if changed {
+            self.emit(&Event::CharacteristicStructureChanged {
+                aid: self.accessory_id,
+                iid: self.id,
+            })
+            .await;
+        }
+
+        Ok(())
+    }
+
+    /// Sets a callback function on a characteristic that is called every time a controller attempts to read its value.
+    /// Returning a `Some(T)` from this function changes the value of the `Characteristic` before the Controller reads
+    /// it so the Controller reads the new value. The returned value is stored without checking it against the
+    /// constraints of the Characteristic, e.g. a sensor reading below its minimum value.
+    pub fn on_read(&mut self, f: Option<impl OnReadFn<T>>) {
+        self.on_read = f.map(|f| Box::new(f) as Box<dyn OnReadFn<T>>);
+    }
+
+    /// Sets a `SimulationSource` on a Characteristic, replacing its read callback. While set, reads return the value
+    /// scripted for the current point of its timeline, e.g. to test controllers against an accessory without hardware.
+    pub fn set_simulation_source(&mut self, source: Option<SimulationSource<T>>)
+    where
+        T: 'static,
+    {
+        self.on_read(source.map(|source| move || source.current()));
+    }
+
+    /// Sets a callback function on a characteristic that is called every time a controller attempts to update its
+    /// value. The first argument is a reference to the current value of the characteristic and the second argument is a
+    /// reference to the value the controller attempts to change the characteristic's to.
+    pub fn on_update(&mut self, f: Option<impl OnUpdateFn<T>>) {
+        self.on_update = f.map(|f| Box::new(f) as Box<dyn OnUpdateFn<T>>);
+    }
+
+    /// Sets an async callback function on a characteristic that is driven to completion by the async runtime driving
+    /// the HAP server every time a controller attempts to read its value. Returning a `Some(T)` from this function
+    /// changes the value of the characteristic before the controller reads it so the controller reads the new value.
+    pub fn on_read_async(&mut self, f: Option<impl OnReadFuture<T>>) {
+        self.on_read_async = f.map(|f| Box::new(f) as Box<dyn OnReadFuture<T>>);
+    }
+
+    /// Sets an async callback function on a characteristic that is driven to completion by the async runtime driving
+    /// the HAP server every time a controller attempts to update its value. The first argument is a reference to the
+    /// current value of the characteristic and the second argument is a reference to the value the controller attempts
+    /// to change the characteristic's to.
+    pub fn on_update_async(&mut self, f: Option<impl OnUpdateFuture<T>>) {
+        self.on_update_async = f.map(|f| Box::new(f) as Box<dyn OnUpdateFuture<T>>);
+    }
+
+    /// Sets a callback function on a characteristic that is called at the very end of every successful `set_value`,
+    /// after the new value is stored and its event is emitted, e.g. to acknowledge the change to the hardware. Unlike
+    /// `on_update`, it can't influence the write. The argument is a reference to the new value.
+    pub fn on_committed(&mut self, f: Option<impl OnCommittedFn<T>>) {
+        self.on_committed = f.map(|f| Box::new(f) as Box<dyn OnCommittedFn<T>>);
+    }
+
+    /// Registers a rule on a Characteristic that updates the Characteristic with the instance ID `other_iid` of the
+    /// same accessory after every write by a controller, e.g. to turn a light on when its brightness is raised. The
+    /// argument is a reference to the new value, returning `None` leaves the other Characteristic unchanged.
+    pub fn link_rule(&mut self, other_iid: u64, f: impl LinkRuleFn<T>) {
+        self.link_rules.push((other_iid, Box::new(f)));
+    }
+
+    /// Sets a `hap::event::pointer::EventEmitter` on the Characteristic.
+    pub(crate) fn set_event_emitter(&mut self, event_emitter: Option<pointer::EventEmitter>) {
+        self.event_emitters = event_emitter.into_iter().collect();
+    }
+
+    /// Adds a `hap::event::pointer::EventEmitter` to the Characteristic. Value changes are emitted on all of its
+    /// `EventEmitter`s.
+    pub fn add_event_emitter(&mut self, event_emitter: pointer::EventEmitter) {
+        self.event_emitters.push(event_emitter);
+    }
+
+    /// Returns whether closed `EventEmitter`s are removed from a Characteristic.
+    pub fn get_drop_closed_event_emitters(&self) -> bool { self.drop_closed_event_emitters }
+
+    /// Sets whether `EventEmitter`s are removed from a Characteristic once they are closed, e.g. because the server
+    /// they belong to shut down. Events aren't emitted on closed `EventEmitter`s either way and writes still store the
+    /// value.
+    pub fn set_drop_closed_event_emitters(&mut self, drop_closed_event_emitters: bool) {
+        self.drop_closed_event_emitters = drop_closed_event_emitters;
+    }
+
+    /// Removes closed `EventEmitter`s from a Characteristic if enabled. `EventEmitter`s that are currently locked
+    /// elsewhere are kept.
+    fn prune_event_emitters(&mut self) {
+        if self.drop_closed_event_emitters {
+            self.event_emitters
+                .retain(|event_emitter| !event_emitter.try_lock().is_some_and(|e| e.is_closed()));
+        }
+    }
+
+    /// Removes all callbacks and `EventEmitter`s of a Characteristic, e.g. when tearing down an accessory. Afterwards,
+    /// reads return the stored value and writes only store the new value.
+    pub fn detach(&mut self) {
+        self.on_read = None;
+        self.on_update = None;
+        self.on_read_async = None;
+        self.on_update_async = None;
+        self.on_committed = None;
+        self.link_rules.clear();
+        self.write_action = None;
+        self.event_emitters.clear();
+    }
+
+    /// Converts a Characteristic into one with a different value type, e.g. from `u8` to `u16` after a capability
+    /// change. Type, format, permissions, unit and all other metadata are carried over, the value and callbacks of the
+    /// new Characteristic are left at their defaults. Metadata values that don't convert to the new type are dropped,
+    /// which is signaled by the returned flag being `true`.
+    pub fn into_typed<U>(self) -> (Characteristic<U>, bool)
+    where
+        U: fmt::Debug + Default + Clone + Serialize + Send + Sync,
+        for<'de> U: Deserialize<'de>,
+    {
+        let mut dropped = false;
+        let mut convert = |val: Option<&T>| -> Option<U> {
+            let val = val?;
+            let converted = serde_json::from_value(json!(val)).ok();
+            dropped |= converted.is_none();
+            converted
+        };
+
+        let max_value = convert(self.max_value.as_ref());
+        let min_value = convert(self.min_value.as_ref());
+        let step_value = convert(self.step_value.as_ref());
+        let advertised_step = convert(self.advertised_step.as_ref());
+        let notify_threshold = convert(self.notify_threshold.as_ref());
+        let valid_values = self
+            .valid_values
+            .as_ref()
+            .and_then(|values| values.iter().map(|v| convert(Some(v))).collect::<Option<Vec<U>>>());
+        let valid_values_range = match self.valid_values_range {
+            Some([ref start, ref end]) => match (convert(Some(start)), convert(Some(end))) {
+                (Some(start), Some(end)) => Some([start, end]),
+                _ => None,
+            },
+            None => None,
+        };
+
+        let characteristic = Characteristic {
+            id: self.id,
+            accessory_id: self.accessory_id,
+            hap_type: self.hap_type,
+            type_format: self.type_format,
+            id_format: self.id_format,
+            serialization_mode: self.serialization_mode,
+            format: self.format,
+            perms: self.perms,
+            description: self.description,
+            event_notifications: self.event_notifications,
+            emit_on_subscribe: self.emit_on_subscribe,
+            linked_iids: self.linked_iids,
+            programmable: self.programmable,
+            unit: self.unit,
+            max_value,
+            min_value,
+            step_value,
+            advertised_step,
+            notify_threshold,
+            min_notify_interval: self.min_notify_interval,
+            max_len: self.max_len,
+            max_data_len: self.max_data_len,
+            valid_values,
+            valid_values_range,
+            bool_valid_values: self.bool_valid_values,
+            precision: self.precision,
+            sanitization: self.sanitization,
+            idempotent_write_policy: self.idempotent_write_policy,
+            float_coercion: self.float_coercion,
+            bounds_mode: self.bounds_mode,
+            bool_encoding: self.bool_encoding,
+            #[cfg(feature = "regex")]
+            pattern: self.pattern,
+            writes_locked: self.writes_locked,
+            reject_hidden_writes: self.reject_hidden_writes,
+            catch_panics: self.catch_panics,
+            drop_closed_event_emitters: self.drop_closed_event_emitters,
+            auto_reset: self.auto_reset,
+            write_coalescing: self.write_coalescing,
+            write_dedup_window: self.write_dedup_window,
+            read_cache_ttl: self.read_cache_ttl,
+            history_capacity: self.history_capacity,
+            event_emitters: self.event_emitters,
+            clock: self.clock,
+            ..Default::default()
+        };
+
+        (characteristic, dropped)
+    }
+}
+
+impl<T: fmt::Debug + Default + Clone + Serialize + Send + Sync> Characteristic<T> {
+    /// Returns the stored value of a Characteristic as JSON without invoking any read callbacks, e.g. to build the
+    /// accessory database without hitting the hardware.
+    pub fn get_cached_value(&self) -> serde_json::Value { self.value_to_json(&self.value) }
+
+    /// Returns a wrapper serializing a Characteristic together with the ID of its accessory.
+    pub fn with_aid(&self) -> WithAid<'_, T> { WithAid(self) }
+
+    /// Returns the constraints of a Characteristic as a JSON object with the field names of the HAP accessory
+    /// database, i.e. its unit, min, max and step values, maximum lengths and valid values. Only constraints that are
+    /// serialized as part of the Characteristic are included.
+    pub fn constraints_json(&self) -> serde_json::Value {
+        const CONSTRAINTS: [&str; 8] = [
+            "unit",
+            "maxValue",
+            "minValue",
+            "minStep",
+            "maxLen",
+            "maxDataLen",
+            "valid-values",
+            "valid-values-range",
+        ];
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(mut fields)) => {
+                fields.retain(|key, _| CONSTRAINTS.contains(&key.as_str()));
+                serde_json::Value::Object(fields)
+            },
+            _ => json!({}),
+        }
+    }
+
+    /// Returns a one-line summary of a Characteristic for logging, i.e. its `HapType`, `Format`, `Perm`s, current value
+    /// and attached callbacks, e.g. `Brightness (int32, ["pr","pw","ev"]) = 42, callbacks: [on_update]`.
+    pub fn debug_summary(&self) -> String {
+        let callbacks = [
+            ("on_read", self.on_read.is_some()),
+            ("on_update", self.on_update.is_some()),
+            ("on_read_async", self.on_read_async.is_some()),
+            ("on_update_async", self.on_update_async.is_some()),
+            ("on_committed", self.on_committed.is_some()),
+            ("link_rule", !self.link_rules.is_empty()),
+            ("write_action", self.write_action.is_some()),
+            ("data_source", self.data_source.is_some()),
+        ]
+        .iter()
+        .filter(|(_, attached)| *attached)
+        .map(|(name, _)| *name)
+        .collect::<Vec<&str>>();
+
+        format!(
+            "{:?} ({}, {}) = {}, callbacks: [{}]",
+            self.hap_type,
+            json!(self.format).as_str().unwrap_or_default(),
+            json!(self.perms),
+            self.get_cached_value(),
+            callbacks.join(", "),
+        )
+    }
+
+    /// Returns the history of a Characteristic as CSV with a `timestamp,value` header. Timestamps are the seconds since
+    /// the oldest recorded value, as measured by its `Clock`, and values are serialized as JSON.
+    pub fn history_csv(&self) -> String {
+        let mut csv = String::from("timestamp,value\n");
+        let start = match self.history.front() {
+            Some((start, _)) => *start,
+            None => return csv,
+        };
+        for (at, value) in &self.history {
+            let value = serde_json::to_string(value).unwrap_or_default();
+            let value = if value.contains(&[',', '"', '\n'][..]) {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            } else {
+                value
+            };
+            csv.push_str(&format!("{:.3},{}\n", at.duration_since(start).as_secs_f64(), value));
+        }
+        csv
+    }
+
+    /// Returns a JSON Schema (draft-07) describing the values a Characteristic accepts, derived from its `Format`,
+    /// bounds, step and valid values, e.g. to validate values before writing them. Integer formats without bounds are
+    /// limited to the range of the format. The step is only included as `multipleOf` if it counts from `0`.
+    pub fn json_schema(&self) -> serde_json::Value {
+        let mut schema = json!({ "$schema": "http://json-schema.org/draft-07/schema#" });
+        if let Some(ref description) = self.description {
+            schema["description"] = json!(description);
+        }
+        match self.format {
+            Format::Bool => schema["type"] = json!("boolean"),
+            Format::String => {
+                schema["type"] = json!("string");
+                if let Some(max_len) = self.max_len {
+                    schema["maxLength"] = json!(max_len);
+                }
+            },
+            Format::Tlv8 | Format::Data => {
+                schema["type"] = json!("string");
+                schema["contentEncoding"] = json!("base64");
+            },
+            format => {
+                schema["type"] = json!(if format.is_integer() { "integer" } else { "number" });
+                let (min, max) = match self.valid_values_range {
+                    Some([ref start, ref end]) => (Some(json!(start)), Some(json!(end))),
+                    None => (
+                        self.min_value.as_ref().map(|v| json!(v)),
+                        self.max_value.as_ref().map(|v| json!(v)),
+                    ),
+                };
+                let limits = format.limits().filter(|_| format.is_integer());
+                if let Some(min) = min.or_else(|| limits.as_ref().map(|(min, _)| min.clone())) {
+                    schema["minimum"] = min;
+                }
+                if let Some(max) = max.or_else(|| limits.map(|(_, max)| max)) {
+                    schema["maximum"] = max;
+                }
+                let step = self.advertised_step.as_ref().or(self.step_value.as_ref()).map(|v| json!(v));
+                let from_zero = match self.min_value {
+                    Some(ref min) => json!(min).as_f64() == Some(0.0),
+                    None => true,
+                };
+                if let (Some(step), true) = (step, from_zero) {
+                    schema["multipleOf"] = step;
+                }
+            },
+        }
+        if let Some(ref valid_values) = self.valid_values {
+            schema["enum"] = json!(valid_values);
+        } else if self.advertises_bool_valid_values() {
+            schema["enum"] = json!([false, true]);
+        }
+        schema
+    }
+
+    /// Serializes a Characteristic to JSON with the fields of every object sorted alphabetically by name, e.g. to get
+    /// stable diffs of persisted snapshots. Unlike the `Serialize` implementation, the order doesn't depend on the
+    /// order in which the fields are written.
+    pub fn to_canonical_json(&self) -> Result<String> {
+        let value = serde_json::to_value(self)?;
+        Ok(serde_json::to_string(&Canonical(&value))?)
+    }
+
+    /// Returns the signature of a Characteristic, i.e. all of its metadata without its value.
+    pub fn signature(&self) -> CharacteristicSignature<T> {
+        CharacteristicSignature {
+            id: self.id,
+            hap_type: self.hap_type,
+            format: self.format,
+            perms: self.perms.clone(),
+            description: self.description.clone(),
+            linked_iids: self.linked_iids.clone(),
+            unit: self.unit,
+            max_value: self.max_value.clone(),
+            min_value: self.min_value.clone(),
+            step_value: self.advertised_step.clone().or_else(|| self.step_value.clone()),
+            max_len: self.max_len,
+            max_data_len: self.max_data_len,
+            valid_values: self.valid_values.clone(),
+            valid_values_range: self.valid_values_range.clone(),
+        }
+    }
+
+    /// Serializes a Characteristic to JSON directly into `writer`, e.g. a reused buffer, avoiding an intermediate
+    /// `String` allocation.
+    pub fn serialize_into<W: io::Write>(&self, writer: W) -> Result<()> {
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+
+    /// Returns a stable hash of the metadata of a Characteristic, i.e. its type, format, permissions, unit and
+    /// constraints. The value and callbacks of the Characteristic don't affect the hash.
+    pub fn metadata_hash(&self) -> u64 {
+        let metadata = json!([
+            self.hap_type,
+            self.format,
+            self.perms,
+            self.unit,
+            self.max_value,
+            self.min_value,
+            self.advertised_step.as_ref().or(self.step_value.as_ref()),
+            self.max_len,
+            self.max_data_len,
+            self.valid_values,
+            self.valid_values_range,
+        ]);
+        let digest = Sha512::digest(metadata.to_string().as_bytes());
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        u64::from_le_bytes(bytes)
+    }
+
+    /// Converts a value to JSON, rounding it to the configured precision if one is set. Non-finite float values are
+    /// replaced by the default value, as controllers can't handle them.
+    fn value_to_json(&self, value: &T) -> serde_json::Value {
+        let value = json!(value);
+        match (self.precision, value.as_f64()) {
+            (Some(precision), Some(v)) => {
+                let factor = 10_f64.powi(precision as i32);
+                json!((v * factor).round() / factor)
+            },
+            (_, None) if self.format == Format::Float => json!(T::default()),
+            _ => self.encode_bool(value),
+        }
+    }
+
+    /// Converts a bool value to `0` or `1` if the `BoolEncoding` of the Characteristic is `Integer`.
+    fn encode_bool(&self, value: serde_json::Value) -> serde_json::Value {
+        match (self.bool_encoding, value.as_bool()) {
+            (BoolEncoding::Integer, Some(v)) => json!(v as u8),
+            _ => value,
+        }
+    }
+
+    /// Returns the `CharacteristicValueChanged` event for the current value of the Characteristic, enriched with its
+    /// unit and format if its `EventVerbosity` is `Verbose`.
+    fn value_changed_event(&self, first: bool) -> Event {
+        let (unit, format) = match self.event_verbosity {
+            EventVerbosity::Minimal => (None, None),
+            EventVerbosity::Verbose => (self.unit, Some(self.format)),
+        };
+        Event::CharacteristicValueChanged {
+            aid: self.accessory_id,
+            iid: self.id,
+            value: self.value_to_json(&self.value),
+            unit,
+            format,
+            first,
+        }
+    }
+
+    /// Returns the current time of the `Clock` of the Characteristic.
+    fn now(&self) -> Instant {
+        match self.clock {
+            Some(ref clock) => clock.now(),
+            None => Instant::now(),
+        }
+    }
+
+    /// Returns a snapshot of the number of reads, writes and events a Characteristic has processed.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> CharacteristicMetrics {
+        CharacteristicMetrics {
+            reads: self.metrics.reads.load(Ordering::Relaxed),
+            writes: self.metrics.writes.load(Ordering::Relaxed),
+            events: self.metrics.events.load(Ordering::Relaxed),
+            adjusted: self.metrics.adjusted.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Emits an event on all `EventEmitter`s of the Characteristic.
+    async fn emit(&self, event: &Event) {
+        #[cfg(feature = "metrics")]
+        self.metrics.events.fetch_add(1, Ordering::Relaxed);
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("emit", aid = self.accessory_id, iid = self.id, hap_type = ?self.hap_type);
+        let emit = async {
+            for event_emitter in &self.event_emitters {
+                let event_emitter = event_emitter.lock().await;
+                if event_emitter.is_closed() {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!("skipping closed event emitter");
+                    continue;
+                }
+                event_emitter.emit(event).await;
+            }
+        };
+        #[cfg(feature = "tracing")]
+        let emit = emit.instrument(span);
+        emit.await
+    }
+}
+
+impl Characteristic<f32> {
+    /// Returns the number of decimals the value of a float Characteristic is rounded to when serialized.
+    pub fn get_precision(&self) -> Option<u8> { self.precision }
+
+    /// Sets the number of decimals the value of a float Characteristic is rounded to when serialized. This avoids
+    /// floating point noise like `21.100000381469727` showing up on the controller.
+    pub fn set_precision(&mut self, precision: Option<u8>) { self.precision = precision; }
+
+    /// Returns the value of a float Characteristic converted from its HAP `Unit` to the given `UnitSystem`, e.g. to
+    /// present a Celsius temperature in Fahrenheit. Values without a convertible `Unit` are returned unchanged.
+    pub fn to_display(&self, unit_system: UnitSystem) -> f32 {
+        match (self.unit, unit_system) {
+            (Some(Unit::Celsius), UnitSystem::Imperial) => self.value * 9.0 / 5.0 + 32.0,
+            _ => self.value,
+        }
+    }
+
+    /// Converts a value in the given `UnitSystem` to the HAP `Unit` of a float Characteristic, e.g. a Fahrenheit
+    /// temperature to Celsius. Values without a convertible `Unit` are returned unchanged.
+    pub fn from_display(&self, value: f32, unit_system: UnitSystem) -> f32 {
+        match (self.unit, unit_system) {
+            (Some(Unit::Celsius), UnitSystem::Imperial) => (value - 32.0) * 5.0 / 9.0,
+            _ => value,
+        }
+    }
+}
+
+impl<X: fmt::Debug + Clone + Serialize + Send + Sync> Characteristic<Vec<X>>
+where
+    for<'de> X: Deserialize<'de>,
+{
+    /// Appends an element to the list value of a Characteristic, emitting a single change event with the resulting
+    /// list.
+    pub async fn append_element(&mut self, element: X) -> Result<()> {
+        let mut value = self.value.clone();
+        value.push(element);
+        self.set_value(value).await
+    }
+
+    /// Removes the element at `index` from the list value of a Characteristic and returns it, emitting a single change
+    /// event with the resulting list.
+    pub async fn remove_element(&mut self, index: usize) -> Result<X> {
+        if index >= self.value.len() {
+            return Err(Error::ElementIndexOutOfBounds(index));
+        }
+        let mut value = self.value.clone();
+        let element = value.remove(index);
+        self.set_value(value).await?;
+        Ok(element)
+    }
+
+    /// Replaces the element at `index` in the list value of a Characteristic, emitting a single change event with the
+    /// resulting list.
+    pub async fn replace_element(&mut self, index: usize, element: X) -> Result<()> {
+        if index >= self.value.len() {
+            return Err(Error::ElementIndexOutOfBounds(index));
+        }
+        let mut value = self.value.clone();
+        value[index] = element;
+        self.set_value(value).await
+    }
+}
+
+impl Characteristic<Vec<u8>> {
+    /// Sets a `DataSource` on a data Characteristic. While set, `get_value` fetches the value from the source on
+    /// every read instead of returning a value held in memory, e.g. for camera snapshots or large configuration blobs.
+    pub fn set_data_source(&mut self, data_source: Option<impl DataSource + 'static>) {
+        self.data_source = data_source.map(|s| Box::new(s) as Box<dyn DataSource>);
+    }
+}
+
+impl Characteristic<bool> {
+    /// Creates a new Identify Characteristic. The Identify Characteristic is write-only and doesn't store a value;
+    /// every write to it invokes `action`, which should make the accessory identify itself, e.g. by blinking an LED.
+    pub fn identify(id: u64, accessory_id: u64, action: impl FnMut() + 'static + Send + Sync) -> Self {
+        Self {
+            id,
+            accessory_id,
+            hap_type: HapType::Identify,
+            format: Format::Bool,
+            perms: vec![Perm::PairedWrite],
+            write_action: Some(Box::new(action)),
+            ..Default::default()
+        }
+    }
+
+    /// Inverts the current value of a bool Characteristic, as returned by `get_value`, through `set_value` and returns
+    /// the new value.
+    pub async fn toggle(&mut self) -> Result<bool> {
+        let val = !self.get_value().await?;
+        self.set_value(val).await?;
+        Ok(val)
+    }
+}
+
+impl<T: fmt::Debug + Default + Clone + Serialize + Send + Sync> Serialize for Characteristic<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.serialize_fields(serializer, false)
+    }
+}
+
+/// Wrapper serializing a `Characteristic` with an `aid` field alongside its `iid`, matching the event body format,
+/// e.g. for flat listings of the Characteristics of several accessories.
+pub struct WithAid<'a, T: fmt::Debug + Default + Clone + Serialize + Send + Sync>(&'a Characteristic<T>);
+
+impl<T: fmt::Debug + Default + Clone + Serialize + Send + Sync> Serialize for WithAid<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.0.serialize_fields(serializer, true)
+    }
+}
+
+impl<T: fmt::Debug + Default + Clone + Serialize + Send + Sync> Characteristic<T> {
+    /// Returns whether the value of a Characteristic is serialized. Values of unreadable Characteristics, of the
+    /// pairing Characteristics, whose values are only exchanged by the pairing protocol, and encrypted values are
+    /// omitted.
+    fn serializes_value(&self) -> bool {
+        let pairing = matches!(
+            self.hap_type,
+            HapType::PairSetup | HapType::PairVerify | HapType::PairingPairings
+        );
+        self.perms.contains(&Perm::PairedRead) && !pairing && self.encryption.is_none()
+    }
+
+    /// Returns whether the implicit valid values of a bool Characteristic are serialized.
+    fn advertises_bool_valid_values(&self) -> bool {
+        self.bool_valid_values && self.format == Format::Bool && self.valid_values.is_none()
+    }
+
+    /// Returns the number of fields `serialize_fields` writes for a Characteristic.
+    fn serialized_field_count(&self, aid: bool) -> usize {
+        let modern = self.serialization_mode == SerializationMode::Modern;
+        // `iid`, `type`, `format` and `perms` are always present
+        4 + [
+            aid,
+            self.description.is_some(),
+            self.event_notifications.is_some(),
+            !self.linked_iids.is_empty(),
+            self.programmable,
+            self.serializes_value(),
+            self.unit.is_some(),
+            self.max_value.is_some(),
+            self.min_value.is_some(),
+            self.advertised_step.is_some() || self.step_value.is_some(),
+            self.max_len.is_some(),
+            self.max_data_len.is_some() && modern,
+            self.valid_values.is_some() || self.advertises_bool_valid_values(),
+            self.valid_values_range.is_some() && self.valid_values.is_none() && modern,
+        ]
+        .iter()
+        .filter(|&&present| present)
+        .count()
+    }
+
+    fn serialize_fields<S: Serializer>(&self, serializer: S, aid: bool) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Characteristic", self.serialized_field_count(aid))?;
+        match self.id_format {
+            IdFormat::Number => {
+                if aid {
+                    state.serialize_field("aid", &self.accessory_id)?;
+                }
+                state.serialize_field("iid", &self.id)?;
+            },
+            IdFormat::String => {
+                if aid {
+                    state.serialize_field("aid", &self.accessory_id.to_string())?;
+                }
+                state.serialize_field("iid", &self.id.to_string())?;
+            },
+        }
+        match self.type_format {
+            TypeFormat::Short => state.serialize_field("type", &self.hap_type)?,
+            TypeFormat::Uuid => state.serialize_field("type", &self.hap_type.to_uuid_string())?,
+        }
+        state.serialize_field("format", &self.format)?;
+        state.serialize_field("perms", &self.perms)?;
+        if let Some(ref description) = self.description {
+            state.serialize_field("description", description)?;
+        }
+        if let Some(ref event_notifications) = self.event_notifications {
+            state.serialize_field("ev", event_notifications)?;
+        }
+        if !self.linked_iids.is_empty() {
+            state.serialize_field("linked", &self.linked_iids)?;
+        }
+        if self.programmable {
+            state.serialize_field("programmable", &self.programmable)?;
+        }
+
+        if self.serializes_value() {
+            let non_finite = self.format == Format::Float && !json!(&self.value).is_number();
+            let bool_as_integer = self.format == Format::Bool && self.bool_encoding == BoolEncoding::Integer;
+            if self.precision.is_some() || non_finite || bool_as_integer {
+                state.serialize_field("value", &self.value_to_json(&self.value))?;
+            } else {
+                state.serialize_field("value", &self.value)?;
+            }
+        }
+        if let Some(ref unit) = self.unit {
+            state.serialize_field("unit", unit)?;
+        }
+        if let Some(ref max_value) = self.max_value {
+            state.serialize_field("maxValue", max_value)?;
+        }
+        if let Some(ref min_value) = self.min_value {
+            state.serialize_field("minValue", min_value)?;
+        }
+        if let Some(step_value) = self.advertised_step.as_ref().or(self.step_value.as_ref()) {
+            state.serialize_field("minStep", step_value)?;
+        }
+        if let Some(ref max_len) = self.max_len {
+            state.serialize_field("maxLen", max_len)?;
+        }
+        let modern = self.serialization_mode == SerializationMode::Modern;
+        if let Some(ref max_data_len) = self.max_data_len {
+            if modern {
+                state.serialize_field("maxDataLen", max_data_len)?;
+            }
+        }
+        if let Some(ref valid_values) = self.valid_values {
+            state.serialize_field("valid-values", valid_values)?;
+        } else if self.advertises_bool_valid_values() {
+            state.serialize_field("valid-values", &[0, 1])?;
+        }
+        // `valid-values` and `valid-values-range` are mutually exclusive, so the former takes precedence
+        if let Some(ref valid_values_range) = self.valid_values_range {
+            if self.valid_values.is_none() && modern {
+                state.serialize_field("valid-values-range", valid_values_range)?;
+            }
+        }
+        state.end()
+    }
+}
+
+/// Wrapper serializing a JSON value with the fields of every object sorted alphabetically by name.
+struct Canonical<'a>(&'a serde_json::Value);
+
+impl<'a> Serialize for Canonical<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self.0 {
+            serde_json::Value::Object(fields) => {
+                let mut fields = fields.iter().collect::<Vec<_>>();
+                fields.sort_by(|a, b| a.0.cmp(b.0));
+                serializer.collect_map(fields.into_iter().map(|(key, value)| (key, Canonical(value))))
+            },
+            serde_json::Value::Array(values) => serializer.collect_seq(values.iter().map(Canonical)),
+            value => value.serialize(serializer),
+        }
+    }
+}
+
+/// Metadata of a `Characteristic` to be applied with `Characteristic::apply_metadata`. Fields set to `None` are left
+/// unchanged.
+#[derive(Debug, Default, Clone)]
+pub struct CharacteristicMetadata<T> {
+    pub description: Option<String>,
+    pub unit: Option<Unit>,
+    pub max_value: Option<T>,
+    pub min_value: Option<T>,
+    pub step_value: Option<T>,
+    pub max_len: Option<u16>,
+    pub max_data_len: Option<u32>,
+    pub valid_values: Option<Vec<T>>,
+    pub valid_values_range: Option<[T; 2]>,
+}
+
+/// Numeric constraints of a `Characteristic` to be set together with `Characteristic::set_numeric_constraints`.
+#[derive(Debug, Default, Clone)]
+pub struct NumericConstraints<T> {
+    pub min_value: Option<T>,
+    pub max_value: Option<T>,
+    pub step_value: Option<T>,
+    pub unit: Option<Unit>,
+    pub valid_values: Option<Vec<T>>,
+}
+
+/// Signature of a `Characteristic`, i.e. its type, format, permissions and constraints without its value, e.g. for
+/// discovery caching. Serializes to the same field names as the `Characteristic` itself.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CharacteristicSignature<T: Serialize> {
+    #[serde(rename = "iid")]
+    pub id: u64,
+    #[serde(rename = "type")]
+    pub hap_type: HapType,
+    pub format: Format,
+    pub perms: Vec<Perm>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "linked", skip_serializing_if = "Vec::is_empty")]
+    pub linked_iids: Vec<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<Unit>,
+    #[serde(rename = "maxValue", skip_serializing_if = "Option::is_none")]
+    pub max_value: Option<T>,
+    #[serde(rename = "minValue", skip_serializing_if = "Option::is_none")]
+    pub min_value: Option<T>,
+    #[serde(rename = "minStep", skip_serializing_if = "Option::is_none")]
+    pub step_value: Option<T>,
+    #[serde(rename = "maxLen", skip_serializing_if = "Option::is_none")]
+    pub max_len: Option<u16>,
+    #[serde(rename = "maxDataLen", skip_serializing_if = "Option::is_none")]
+    pub max_data_len: Option<u32>,
+    #[serde(rename = "valid-values", skip_serializing_if = "Option::is_none")]
+    pub valid_values: Option<Vec<T>>,
+    #[serde(rename = "valid-values-range", skip_serializing_if = "Option::is_none")]
+    pub valid_values_range: Option<[T; 2]>,
+}
+
+/// Permission of a `Characteristic`.
+#[derive(Debug, Copy, Clone, Serialize, PartialEq)]
+pub enum Perm {
+    #[serde(rename = "pr")]
+    PairedRead,
+    #[serde(rename = "pw")]
+    PairedWrite,
+    #[serde(rename = "ev")]
+    Events,
+    #[serde(rename = "aa")]
+    AdditionalAuthorization,
+    #[serde(rename = "tw")]
+    TimedWrite,
+    #[serde(rename = "hd")]
+    Hidden,
+}
+
+/// Unit of a `Characteristic`.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub enum Unit {
+    #[serde(rename = "percentage")]
+    Percentage,
+    #[serde(rename = "arcdegrees")]
+    ArcDegrees,
+    #[serde(rename = "celsius")]
+    Celsius,
+    #[serde(rename = "lux")]
+    Lux,
+    #[serde(rename = "seconds")]
+    Seconds,
+}
+
+/// Format (data type) of a `Characteristic`.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Format {
+    #[serde(rename = "string")]
+    String,
+    #[serde(rename = "bool")]
+    Bool,
+    #[serde(rename = "float")]
+    Float,
+    #[serde(rename = "uint8")]
+    UInt8,
+    #[serde(rename = "uint16")]
+    UInt16,
+    #[serde(rename = "uint32")]
+    UInt32,
+    #[serde(rename = "uint64")]
+    UInt64,
+    #[serde(rename = "int32")]
+    Int32,
+    #[serde(rename = "tlv8")]
+    Tlv8,
+    #[serde(rename = "data")]
+    Data,
+}
+
+impl Default for Format {
+    fn default() -> Format { Format::String }
+}
+
+impl Format {
+    /// Returns whether a `Format` is one of the integer formats or `Float`.
+    pub fn is_numeric(self) -> bool { self.is_integer() || self == Format::Float }
+
+    /// Returns whether a `Format` is one of the integer formats.
+    pub fn is_integer(self) -> bool {
+        matches!(self, Format::UInt8 | Format::UInt16 | Format::UInt32 | Format::UInt64 | Format::Int32)
+    }
+
+    /// Returns the smallest and largest value of a numeric `Format`.
+    pub fn limits(self) -> Option<(serde_json::Value, serde_json::Value)> {
+        match self {
+            Format::UInt8 => Some((json!(u8::MIN), json!(u8::MAX))),
+            Format::UInt16 => Some((json!(u16::MIN), json!(u16::MAX))),
+            Format::UInt32 => Some((json!(u32::MIN), json!(u32::MAX))),
+            Format::UInt64 => Some((json!(u64::MIN), json!(u64::MAX))),
+            Format::Int32 => Some((json!(i32::MIN), json!(i32::MAX))),
+            Format::Float => Some((json!(f32::MIN), json!(f32::MAX))),
+            Format::Bool | Format::String | Format::Tlv8 | Format::Data => None,
+        }
+    }
+
+    /// Parses a `Format` from its HAP name like `from_str`, but falls back to `Format::Data` for unknown names, e.g.
+    /// formats introduced by newer HAP versions.
+    pub fn from_str_lenient(s: &str) -> Format { Format::from_str(s).unwrap_or(Format::Data) }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Format> {
+        match s {
+            "string" => Ok(Format::String),
+            "bool" => Ok(Format::Bool),
+            "float" => Ok(Format::Float),
+            "uint8" => Ok(Format::UInt8),
+            "uint16" => Ok(Format::UInt16),
+            "uint32" => Ok(Format::UInt32),
+            "uint64" => Ok(Format::UInt64),
+            "int32" => Ok(Format::Int32),
+            "tlv8" => Ok(Format::Tlv8),
+            "data" => Ok(Format::Data),
+            _ => Err(Error::InvalidFormat(s.to_string())),
+        }
+    }
+}
+
+/// Unit system the values of a `Characteristic` are displayed in. HAP itself always uses metric units.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum UnitSystem {
+    /// Metric units, e.g. degrees Celsius.
+    #[default]
+    Metric,
+    /// Imperial units, e.g. degrees Fahrenheit.
+    Imperial,
+}
+
+/// Detail of the `CharacteristicValueChanged` events emitted by a `Characteristic`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum EventVerbosity {
+    /// Only the value, matching the HAP wire format.
+    #[default]
+    Minimal,
+    /// The value along with the unit and format of the `Characteristic`.
+    Verbose,
+}
+
+/// Serialization form of the `HapType` of a `Characteristic`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum TypeFormat {
+    /// The short form used by the HAP IP transport, e.g. `8`.
+    #[default]
+    Short,
+    /// The full UUID, e.g. `00000008-0000-1000-8000-0026BB765291`.
+    Uuid,
+}
+
+/// Serialization form of the instance and accessory IDs of a `Characteristic`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum IdFormat {
+    /// JSON numbers as used by the HAP IP transport, e.g. `9`.
+    #[default]
+    Number,
+    /// JSON strings for tooling expecting string IDs, e.g. `"9"`.
+    String,
+}
+
+/// Set of metadata fields included when serializing a `Characteristic`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum SerializationMode {
+    /// All fields, including `maxDataLen` and `valid-values-range`.
+    #[default]
+    Modern,
+    /// Only the fields understood by older iOS versions, omitting `maxDataLen` and `valid-values-range`.
+    Legacy,
+}
+
+/// Handling of control characters like null bytes or newlines in string values of a `Characteristic`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum Sanitization {
+    /// Control characters are stored as they are.
+    #[default]
+    Allow,
+    /// Control characters are removed from the value.
+    Strip,
+    /// Values containing control characters are rejected.
+    Reject,
+}
+
+/// Handling of writes of the value a `Characteristic` already holds.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum IdempotentWritePolicy {
+    /// The write is processed like any other.
+    #[default]
+    AlwaysRun,
+    /// The update callbacks aren't called, but the value is stored and its event is emitted.
+    SkipCallback,
+    /// The write is skipped entirely, so neither callbacks are called nor events emitted.
+    SkipAll,
+}
+
+/// Handling of float values written by controllers to a `Characteristic` with an integer `Format`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum FloatCoercion {
+    /// Only whole-number floats like `3.0` are accepted, others are rejected.
+    #[default]
+    Exact,
+    /// Floats are rounded to the nearest integer, e.g. `3.5` to `4`.
+    Round,
+}
+
+/// Encoding of the values of a bool `Characteristic` sent to controllers.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum BoolEncoding {
+    /// JSON booleans as mandated by the HAP specification, e.g. `true`.
+    #[default]
+    Bool,
+    /// The integers `0` and `1`.
+    Integer,
+}
+
+/// Handling of values outside the minimum and maximum value of a `Characteristic`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum BoundsMode {
+    /// Values outside the bounds are rejected.
+    #[default]
+    Reject,
+    /// The bounds are widened past the value, emitting a single `CharacteristicStructureChanged` event by the next
+    /// `tick` or read of the `Characteristic`.
+    AutoExpand,
+}
+
+/// `HapCharacteristic` is implemented by every `Characteristic`.
+#[async_trait]
+pub trait HapCharacteristic: HapCharacteristicSetup + erased_serde::Serialize + Send + Sync {
+    /// Returns the ID of a Characteristic.
+    fn get_id(&self) -> u64;
+    /// Returns the `HapType` of a Characteristic.
+    fn get_type(&self) -> HapType;
+    /// Returns the `Format` of a Characteristic.
+    fn get_format(&self) -> Format;
+    /// Returns the `Perm`s of a Characteristic.
+    fn get_perms(&self) -> Vec<Perm>;
+    /// Returns the `HapType`, `Format` and `Perm`s of a Characteristic, e.g. to compare it against the HAP
+    /// specification.
+    fn descriptor(&self) -> (HapType, Format, Vec<Perm>) { (self.get_type(), self.get_format(), self.get_perms()) }
+    /// Returns the event notifications value of a Characteristic.
+    fn get_event_notifications(&self) -> Option<bool>;
+    /// Sets the event notifications value of a Characteristic.
+    fn set_event_notifications(&mut self, event_notifications: Option<bool>);
+    /// Returns whether the accessory behind a Characteristic is reachable.
+    fn is_reachable(&self) -> bool;
+    /// Sets whether the accessory behind a Characteristic is reachable.
+    fn set_reachable(&mut self, reachable: bool);
+    /// Handles a new event subscription of a controller to a Characteristic.
+    async fn handle_subscribe(&self);
+    /// Handles the removal of the event subscription of a controller to a Characteristic.
+    fn handle_unsubscribe(&self);
+    /// Returns the number of controllers subscribed to events of a Characteristic.
+    fn subscriber_count(&self) -> usize;
+    /// Returns whether a Characteristic is readable and its value wasn't updated within `interval`.
+    fn needs_refresh(&self, interval: Duration) -> bool;
+    /// Applies the time-dependent updates of a Characteristic that are due, e.g. auto resets. Called
+    /// periodically by the server if its timer is enabled. Returns whether anything was applied.
+    async fn tick(&mut self) -> Result<bool> { Ok(false) }
+    /// Returns the value of a Characteristic.
+    async fn get_value(&mut self) -> Result<serde_json::Value>;
+    /// Returns the stored value of a Characteristic without invoking any read callbacks.
+    fn get_cached_value(&self) -> serde_json::Value;
+    /// Sets the value of a Characteristic.
+    async fn set_value(&mut self, value: serde_json::Value) -> Result<()>;
+    /// Returns the `Unit` of a Characteristic.
+    fn get_unit(&self) -> Option<Unit>;
+    /// Returns the maximum value of a Characteristic.
+    fn get_max_value(&self) -> Option<serde_json::Value>;
+    /// Returns the minimum value of a Characteristic.
+    fn get_min_value(&self) -> Option<serde_json::Value>;
+    /// Returns the step value of a Characteristic.
+    fn get_step_value(&self) -> Option<serde_json::Value>;
+    /// Returns the maximum length of a Characteristic.
+    fn get_max_len(&self) -> Option<u16>;
+    /// Returns a stable hash of the metadata of a Characteristic.
+    fn metadata_hash(&self) -> u64;
+    /// Removes all callbacks and event emitters of a Characteristic.
+    fn detach(&mut self);
+    /// Returns and clears the updates of linked Characteristics queued by the link rules of a Characteristic.
+    fn take_linked_updates(&mut self) -> Vec<(u64, serde_json::Value)>;
+    /// Sets the value of a Characteristic to an update queued by the link rule of another Characteristic, skipping
+    /// the checks applied to writes by controllers.
+    async fn set_linked_value(&mut self, value: serde_json::Value) -> Result<()>;
+    /// Returns the underlying `Characteristic<T>` as `Any`, e.g. to downcast it to `Characteristic<u8>` and access its
+    /// typed value.
+    fn as_any(&self) -> &dyn Any;
+    /// Returns the underlying `Characteristic<T>` as mutable `Any`.
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+serialize_trait_object!(HapCharacteristic);
+
+/// Gets the value of a type-erased Characteristic and deserializes it into `U`, e.g. to read a `u8` from a
+/// `Box<dyn HapCharacteristic>` without downcasting it.
+pub async fn get_typed<U: DeserializeOwned>(characteristic: &mut dyn HapCharacteristic) -> Result<U> {
+    let value = characteristic.get_value().await?;
+    Ok(serde_json::from_value(value)?)
+}
+
+pub trait HapCharacteristicSetup {
+    /// Sets a `hap::event::pointer::EventEmitter` on the characteristic.
+    fn set_event_emitter(&mut self, event_emitter: Option<pointer::EventEmitter>);
+}
+
+pub trait OnReadFn<T: Default + Clone + Serialize + Send + Sync>: Fn() -> Option<T> + 'static + Send + Sync {}
+impl<F, T: Default + Clone + Serialize + Send + Sync> OnReadFn<T> for F where
+    F: Fn() -> Option<T> + 'static + Send + Sync
+{
+}
+
+pub trait OnUpdateFn<T: Default + Clone + Serialize + Send + Sync>: Fn(&T, &T) + 'static + Send + Sync {}
+impl<F, T: Default + Clone + Serialize + Send + Sync> OnUpdateFn<T> for F where F: Fn(&T, &T) + 'static + Send + Sync {}
+
+pub trait OnCommittedFn<T: Default + Clone + Serialize + Send + Sync>: Fn(&T) + 'static + Send + Sync {}
+impl<F, T: Default + Clone + Serialize + Send + Sync> OnCommittedFn<T> for F where F: Fn(&T) + 'static + Send + Sync {}
+
+pub trait LinkRuleFn<T: Default + Clone + Serialize + Send + Sync>:
+    Fn(&T) -> Option<serde_json::Value> + 'static + Send + Sync
+{
+}
+impl<F, T: Default + Clone + Serialize + Send + Sync> LinkRuleFn<T> for F where
+    F: Fn(&T) -> Option<serde_json::Value> + 'static + Send + Sync
+{
+}
+
+pub trait OnReadFuture<T: Default + Clone + Serialize + Send + Sync>:
+    Fn() -> BoxFuture<'static, Option<T>> + 'static + Send + Sync
+{
+}
+impl<F, T: Default + Clone + Serialize + Send + Sync> OnReadFuture<T> for F where
+    F: Fn() -> BoxFuture<'static, Option<T>> + 'static + Send + Sync
+{
+}
+
+pub trait OnUpdateFuture<T: Default + Clone + Serialize + Send + Sync>:
+    Fn(T, T) -> BoxFuture<'static, ()> + 'static + Send + Sync
+{
+}
+impl<F, T: Default + Clone + Serialize + Send + Sync> OnUpdateFuture<T> for F where
+    F: Fn(T, T) -> BoxFuture<'static, ()> + 'static + Send + Sync
+{
+}
+
+// Fn() -> impl Future<Output = Option<T>>
+// Fn(&T, &T) -> Future<Output = ()>
+
+pub trait CharacteristicCallbacks<T: fmt::Debug + Default + Clone + Serialize + Send + Sync> {
+    /// Sets a callback function on a characteristic that is called every time a controller attempts to read its value.
+    /// Returning a `Some(T)` from this function changes the value of the `Characteristic` before the Controller reads
+    /// it so the Controller reads the new value.
+    fn on_read(&mut self, f: Option<impl OnReadFn<T>>);
+    /// Sets a callback function on a characteristic that is called every time a controller attempts to update its
+    /// value. The first argument is a reference to the current value of the characteristic and the second argument is a
+    /// reference to the value the controller attempts to change the characteristic's to.
+    fn on_update(&mut self, f: Option<impl OnUpdateFn<T>>);
+    /// Sets a callback function on a characteristic that is called after every successful update of its value, once
+    /// the new value is stored and its event is emitted. The argument is a reference to the new value.
+    fn on_committed(&mut self, f: Option<impl OnCommittedFn<T>>);
+    /// Registers a rule on a characteristic that updates the characteristic with the instance ID `other_iid` of the
+    /// same accessory after every write by a controller. Returning `None` from the rule leaves the other
+    /// characteristic unchanged.
+    fn link_rule(&mut self, other_iid: u64, f: impl LinkRuleFn<T>);
+}
+
+pub trait AsyncCharacteristicCallbacks<T: fmt::Debug + Default + Clone + Serialize + Send + Sync> {
+    /// Sets an async callback function on a characteristic that is driven to completion by the async runtime driving
+    /// the HAP server every time a controller attempts to read its value. Returning a `Some(T)` from this function
+    /// changes the value of the characteristic before the controller reads it so the controller reads the new value.
+    fn on_read_async(&mut self, f: Option<impl OnReadFuture<T>>);
+    /// Sets an async callback function on a characteristic that is driven to completion by the async runtime driving
+    /// the HAP server every time a controller attempts to update its value. The first argument is a reference to the
+    /// current value of the characteristic and the second argument is a reference to the value the controller attempts
+    /// to change the characteristic's to.
+    fn on_update_async(&mut self, f: Option<impl OnUpdateFuture<T>>);
+}
+
+/// Creates a `Characteristic` with `Characteristic::new`, deriving its value type from its `Format` and its `Perm`s
+/// from their HAP short names (`pr`, `pw`, `ev`, `aa`, `tw`, `hd`). The IDs default to `0` if omitted.
+///
+/// # Examples
+///
+/// ```
+/// use hap::characteristic;
+///
+/// let on = characteristic! { id: 9, accessory_id: 1, type: On, format: Bool, perms: [pr, pw, ev] }.unwrap();
+/// let brightness = characteristic! { type: Brightness, format: Int32, perms: [pr, pw, ev] }.unwrap();
+/// ```
+#[macro_export]
+macro_rules! characteristic {
+    (id: $id:expr, accessory_id: $aid:expr, type: $hap_type:ident, format: $format:ident, perms: [$($perm:ident),* $(,)?] $(,)?) => {
+        $crate::characteristic::Characteristic::<$crate::__characteristic_value_type!($format)>::new(
+            $id,
+            $aid,
+            $crate::HapType::$hap_type,
+            $crate::characteristic::Format::$format,
+            vec![$($crate::__characteristic_perm!($perm)),*],
+        )
+    };
+    (type: $hap_type:ident, format: $format:ident, perms: [$($perm:ident),* $(,)?] $(,)?) => {
+        $crate::characteristic!(id: 0, accessory_id: 0, type: $hap_type, format: $format, perms: [$($perm),*])
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __characteristic_value_type {
+    (Bool) => { bool };
+    (UInt8) => { u8 };
+    (UInt16) => { u16 };
+    (UInt32) => { u32 };
+    (UInt64) => { u64 };
+    (Int32) => { i32 };
+    (Float) => { f32 };
+    (String) => { String };
+    (Tlv8) => { Vec<u8> };
+    (Data) => { Vec<u8> };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __characteristic_perm {
+    (pr) => { $crate::characteristic::Perm::PairedRead };
+    (pw) => { $crate::characteristic::Perm::PairedWrite };
+    (ev) => { $crate::characteristic::Perm::Events };
+    (aa) => { $crate::characteristic::Perm::AdditionalAuthorization };
+    (tw) => { $crate::characteristic::Perm::TimedWrite };
+    (hd) => { $crate::characteristic::Perm::Hidden };
+}
+
+pub trait WriteInterceptorFn: Fn(u64, u64, &serde_json::Value) + 'static + Send + Sync {}
+impl<F> WriteInterceptorFn for F where F: Fn(u64, u64, &serde_json::Value) + 'static + Send + Sync {}
+
+static WRITE_INTERCEPTOR_SET: AtomicBool = AtomicBool::new(false);
+static WRITE_INTERCEPTOR: RwLock<Option<Box<dyn WriteInterceptorFn>>> = RwLock::new(None);
+
+/// Sets a function that is called with the Accessory ID, the instance ID and the value of every write to a
+/// Characteristic through `HapCharacteristic::set_value` across all Accessories, e.g. to audit writes centrally. It's
+/// called before the write is processed, so it also sees writes that are rejected. Passing `None` removes it.
+pub fn set_write_interceptor(f: Option<impl WriteInterceptorFn>) {
+    let mut write_interceptor = WRITE_INTERCEPTOR.write().unwrap_or_else(|e| e.into_inner());
+    *write_interceptor = f.map(|f| Box::new(f) as Box<dyn WriteInterceptorFn>);
+    WRITE_INTERCEPTOR_SET.store(write_interceptor.is_some(), Ordering::Release);
+}
+
+/// Calls the write interceptor if one is set.
+fn intercept_write(aid: u64, iid: u64, value: &serde_json::Value) {
+    if !WRITE_INTERCEPTOR_SET.load(Ordering::Acquire) {
+        return;
+    }
+    if let Some(ref write_interceptor) = *WRITE_INTERCEPTOR.read().unwrap_or_else(|e| e.into_inner()) {
+        write_interceptor(aid, iid, value);
+    }
+}
+
+pub trait WriteStageFn: Fn(&mut serde_json::Value) -> Result<()> + 'static + Send + Sync {}
+impl<F> WriteStageFn for F where F: Fn(&mut serde_json::Value) -> Result<()> + 'static + Send + Sync {}
+
+/// Ordered stages transforming the JSON values written to a `Characteristic` by controllers, e.g. to convert units or
+/// clamp values, before they're deserialized and validated. The first stage returning an error rejects the write.
+#[derive(Default)]
+pub struct WritePipeline {
+    stages: Vec<Box<dyn WriteStageFn>>,
+}
+
+impl WritePipeline {
+    /// Creates a new `WritePipeline` without any stages.
+    pub fn new() -> Self { Self::default() }
+
+    /// Appends a stage to the `WritePipeline`.
+    pub fn stage(mut self, f: impl WriteStageFn) -> Self {
+        self.stages.push(Box::new(f));
+        self
+    }
+
+    /// Runs all stages of the `WritePipeline` on a value in order.
+    fn run(&self, value: &mut serde_json::Value) -> Result<()> {
+        for stage in &self.stages {
+            stage(value)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for WritePipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WritePipeline").field("stages", &self.stages.len()).finish()
+    }
+}
+
+/// Source of the value of a data `Characteristic` that is fetched lazily on every read instead of being held in memory.
+#[async_trait]
+pub trait DataSource: Send + Sync {
+    /// Fetches the current value.
+    async fn fetch(&self) -> Result<Vec<u8>>;
+}
+
+/// Source of the current time for time-dependent features of a `Characteristic`, e.g. auto reset. Can be replaced to
+/// control time in tests.
+pub trait Clock: 'static + Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+}
+
+/// Returns a suggested minimum interval between two value change events of Characteristics of a `HapType`, to be
+/// applied with `Characteristic::set_min_notify_interval`. Sensor readings that may change continuously are suggested
+/// to be notified at most once per second.
+///
+/// | `HapType`                                              | Interval |
+/// |--------------------------------------------------------|----------|
+/// | `CurrentTemperature`, `CurrentRelativeHumidity`        | 1 s      |
+/// | `CurrentAmbientLightLevel`                             | 1 s      |
+/// | `CarbonDioxideLevel`, `CarbonMonoxideLevel`            | 1 s      |
+/// | `AirParticulateDensity`, `Pm2_5Density`, `Pm10Density` | 1 s      |
+/// | `NitrogenDioxideDensity`, `OzoneDensity`               | 1 s      |
+/// | `SulphurDioxideDensity`, `VocDensity`                  | 1 s      |
+/// | all others                                             | none     |
+pub fn default_min_notify_interval(hap_type: HapType) -> Option<Duration> {
+    match hap_type {
+        HapType::CurrentTemperature
+        | HapType::CurrentRelativeHumidity
+        | HapType::CurrentAmbientLightLevel
+        | HapType::CarbonDioxideLevel
+        | HapType::CarbonMonoxideLevel
+        | HapType::AirParticulateDensity
+        | HapType::Pm2_5Density
+        | HapType::Pm10Density
+        | HapType::NitrogenDioxideDensity
+        | HapType::OzoneDensity
+        | HapType::SulphurDioxideDensity
+        | HapType::VocDensity => Some(Duration::from_secs(1)),
+        _ => None,
+    }
+}
+
+/// Returns the standard unit of Characteristics of a `HapType`, or `None` if the type has no unit.
+///
+/// | `HapType`                                                    | Unit         |
+/// |--------------------------------------------------------------|--------------|
+/// | `Brightness`, `Saturation`, `RotationSpeed`, `Volume`        | `Percentage` |
+/// | `BatteryLevel`, `WaterLevel`                                 | `Percentage` |
+/// | `CurrentPosition`, `TargetPosition`                          | `Percentage` |
+/// | `CurrentRelativeHumidity`, `TargetRelativeHumidity`          | `Percentage` |
+/// | `RelativeHumidity{Dehumidifier,Humidifier}Threshold`         | `Percentage` |
+/// | `CurrentTemperature`, `TargetTemperature`                    | `Celsius`    |
+/// | `CoolingThresholdTemperature`, `HeatingThresholdTemperature` | `Celsius`    |
+/// | `Hue`, `ImageRotation`                                       | `ArcDegrees` |
+/// | `{Current,Target}{,Horizontal,Vertical}TiltAngle`            | `ArcDegrees` |
+/// | `CurrentAmbientLightLevel`                                   | `Lux`        |
+/// | `LockManagementAutoSecurityTimeout`                          | `Seconds`    |
+/// | all others                                                   | none         |
+pub fn default_unit(hap_type: HapType) -> Option<Unit> {
+    match hap_type {
+        HapType::Brightness
+        | HapType::Saturation
+        | HapType::RotationSpeed
+        | HapType::Volume
+        | HapType::BatteryLevel
+        | HapType::WaterLevel
+        | HapType::CurrentPosition
+        | HapType::TargetPosition
+        | HapType::CurrentRelativeHumidity
+        | HapType::TargetRelativeHumidity
+        | HapType::RelativeHumidityDehumidifierThreshold
+        | HapType::RelativeHumidityHumidifierThreshold => Some(Unit::Percentage),
+        HapType::CurrentTemperature
+        | HapType::TargetTemperature
+        | HapType::CoolingThresholdTemperature
+        | HapType::HeatingThresholdTemperature => Some(Unit::Celsius),
+        HapType::Hue
+        | HapType::ImageRotation
+        | HapType::CurrentTiltAngle
+        | HapType::TargetTiltAngle
+        | HapType::CurrentHorizontalTiltAngle
+        | HapType::TargetHorizontalTiltAngle
+        | HapType::CurrentVerticalTiltAngle
+        | HapType::TargetVerticalTiltAngle => Some(Unit::ArcDegrees),
+        HapType::CurrentAmbientLightLevel => Some(Unit::Lux),
+        HapType::LockManagementAutoSecurityTimeout => Some(Unit::Seconds),
+        _ => None,
+    }
+}
+
+/// Calls a callback, catching a panic if `catch_panics` is set.
+fn guard<R>(catch_panics: bool, f: impl FnOnce() -> R) -> Result<R> {
+    if !catch_panics {
+        return Ok(f());
+    }
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|_| Error::CallbackPanicked)
+}
+
+/// Drives the future of an async callback to completion, catching a panic if `catch_panics` is set.
+async fn guard_async<R>(catch_panics: bool, f: impl Future<Output = R>) -> Result<R> {
+    if !catch_panics {
+        return Ok(f.await);
+    }
+    AssertUnwindSafe(f).catch_unwind().await.map_err(|_| Error::CallbackPanicked)
+}
+
+/// Counters of the reads, writes and events processed by a `Characteristic`.
+#[cfg(feature = "metrics")]
+#[derive(Default)]
+struct Metrics {
+    reads: AtomicU64,
+    writes: AtomicU64,
+    events: AtomicU64,
+    adjusted: AtomicU64,
+}
+
+/// Snapshot of the number of reads, writes and events processed by a `Characteristic`.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct CharacteristicMetrics {
+    /// Number of calls to `get_value`.
+    pub reads: u64,
+    /// Number of values accepted by `set_value`.
+    pub writes: u64,
+    /// Number of events emitted.
+    pub events: u64,
+    /// Number of values written by controllers that were rounded to the advertised step or to an integer.
+    pub adjusted: u64,
+}
+
+/// `Clock` reading the system's monotonic clock.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant { Instant::now() }
+}
+
+/// `Clock` that only advances when told to.
+#[cfg(test)]
+#[derive(Clone)]
+pub(crate) struct MockClock(Arc<StdMutex<Instant>>);
+
+#[cfg(test)]
+impl MockClock {
+    pub(crate) fn new() -> Self { MockClock(Arc::new(StdMutex::new(Instant::now()))) }
+
+    pub(crate) fn advance(&self, duration: Duration) { *self.0.lock().unwrap() += duration; }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now(&self) -> Instant { *self.0.lock().unwrap() }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex as StdMutex};
+
+    use futures::{channel::mpsc, executor, future::FutureExt, lock::Mutex, pin_mut, poll, stream::StreamExt};
+
+    use super::*;
+    use crate::event::EventEmitter;
+
+    /// Returns an `EventEmitter` and the list of values of the `CharacteristicValueChanged` events it emits.
+    fn recording_event_emitter() -> (pointer::EventEmitter, Arc<StdMutex<Vec<serde_json::Value>>>) {
+        let values = Arc::new(StdMutex::new(Vec::new()));
+        let mut event_emitter = EventEmitter::new();
+        let v = values.clone();
+        event_emitter.add_listener(Box::new(move |event| {
+            if let Event::CharacteristicValueChanged { ref value, .. } = event {
+                v.lock().unwrap().push(value.clone());
+            }
+            async {}.boxed()
+        }));
+
+        (Arc::new(Mutex::new(event_emitter)), values)
+    }
+
+    /// Returns an `EventEmitter` and the number of `CharacteristicStructureChanged` events it emits.
+    fn structure_event_emitter() -> (pointer::EventEmitter, Arc<StdMutex<usize>>) {
+        let count = Arc::new(StdMutex::new(0));
+        let mut event_emitter = EventEmitter::new();
+        let c = count.clone();
+        event_emitter.add_listener(Box::new(move |event| {
+            if let Event::CharacteristicStructureChanged { .. } = event {
+                *c.lock().unwrap() += 1;
+            }
+            async {}.boxed()
+        }));
+
+        (Arc::new(Mutex::new(event_emitter)), count)
+    }
+
+    #[test]
+    fn test_json_serialization() {
+        let characteristic = Characteristic::<u16> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentTiltAngle,
+            type_format: TypeFormat::Short,
+            id_format: IdFormat::Number,
+            serialization_mode: SerializationMode::Modern,
+            format: Format::UInt16,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            description: Some("Acme Tilt Angle".into()),
+            event_notifications: Some(true),
+            emit_on_subscribe: false,
+            subscribers: AtomicUsize::new(0),
+            event_verbosity: EventVerbosity::Minimal,
+            error_events: false,
+            linked_iids: Vec::new(),
+            programmable: false,
+
+            value: 123,
+            version: 0,
+            value_set: false,
+            unit: Some(Unit::ArcDegrees),
+
+            max_value: Some(360),
+            min_value: Some(0),
+            step_value: Some(1),
+            advertised_step: None,
+            notify_threshold: None,
+            last_notified: None,
+            min_notify_interval: None,
+            last_notified_at: None,
+            notify_pending: false,
+            structure_change_pending: false,
+            max_len: None,
+            max_data_len: None,
+            valid_values: None,
+            valid_values_range: Some([0, 360]),
+            bool_valid_values: false,
+            precision: None,
+            sanitization: Sanitization::Allow,
+            idempotent_write_policy: IdempotentWritePolicy::AlwaysRun,
+            float_coercion: FloatCoercion::Exact,
+            bounds_mode: BoundsMode::Reject,
+            bool_encoding: BoolEncoding::Bool,
+            #[cfg(feature = "regex")]
+            pattern: None,
+            writes_locked: false,
+            unreachable: false,
+            reject_hidden_writes: false,
+            catch_panics: false,
+            drop_closed_event_emitters: false,
+            frozen: false,
+            auto_reset: None,
+            reset_at: None,
+            write_coalescing: None,
+            write_dedup_window: None,
+            last_json_write: None,
+            read_cache_ttl: None,
+            last_read: None,
+            pending_update: None,
+            history_capacity: 0,
+            history: VecDeque::new(),
+            last_updated: None,
+
+            on_read: None,
+            on_update: None,
+            on_read_async: None,
+            on_update_async: None,
+            on_committed: None,
+            link_rules: Vec::new(),
+            linked_updates: Vec::new(),
+            write_action: None,
+            write_pipeline: None,
+            data_source: None,
+
+            event_emitters: Vec::new(),
+
+            batch: None,
+            snapshot: None,
+            clock: None,
+            encryption: None,
+
+            #[cfg(feature = "metrics")]
+            metrics: Default::default(),
+        };
+        let json = serde_json::to_string(&characteristic).unwrap();
+        assert_eq!(json, "{\"iid\":1,\"type\":\"C1\",\"format\":\"uint16\",\"perms\":[\"pr\",\"ev\"],\"description\":\"Acme Tilt Angle\",\"ev\":true,\"value\":123,\"unit\":\"arcdegrees\",\"maxValue\":360,\"minValue\":0,\"minStep\":1,\"valid-values-range\":[0,360]}".to_string());
+    }
+
+    #[test]
+    fn test_json_serialization_with_precision() {
+        let mut characteristic = Characteristic::<f32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            value: 21.123_4,
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&characteristic).unwrap();
+        assert_eq!(json, "{\"iid\":1,\"type\":\"11\",\"format\":\"float\",\"perms\":[\"pr\",\"ev\"],\"value\":21.1234}".to_string());
+
+        characteristic.set_precision(Some(2));
+        let json = serde_json::to_string(&characteristic).unwrap();
+        assert_eq!(json, "{\"iid\":1,\"type\":\"11\",\"format\":\"float\",\"perms\":[\"pr\",\"ev\"],\"value\":21.12}".to_string());
+        assert_eq!(executor::block_on(characteristic.get_json_value()).unwrap(), json!(21.12));
+
+        characteristic.set_precision(Some(0));
+        let json = serde_json::to_string(&characteristic).unwrap();
+        assert_eq!(json, "{\"iid\":1,\"type\":\"11\",\"format\":\"float\",\"perms\":[\"pr\",\"ev\"],\"value\":21.0}".to_string());
+    }
+
+    #[test]
+    fn test_value_ref() {
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            ..Default::default()
+        };
+        characteristic.on_read(Some(|| Some(2)));
+
+        assert_eq!(characteristic.value_ref(), &0);
+
+        executor::block_on(characteristic.set_value(1)).unwrap();
+        assert_eq!(characteristic.value_ref(), &1);
+    }
+
+    #[test]
+    fn test_batch() {
+        let (event_emitter, values) = recording_event_emitter();
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            event_notifications: Some(true),
+            event_emitters: vec![event_emitter],
+            ..Default::default()
+        };
+
+        let version = characteristic.version();
+        characteristic.begin_batch();
+        executor::block_on(characteristic.set_value(1)).unwrap();
+        executor::block_on(characteristic.set_value(2)).unwrap();
+        characteristic.rollback_batch();
+        assert_eq!(characteristic.value_ref(), &0);
+        assert_eq!(characteristic.version(), version);
+        assert!(characteristic.needs_refresh(Duration::from_secs(60)));
+        assert!(values.lock().unwrap().is_empty());
+
+        characteristic.begin_batch();
+        executor::block_on(characteristic.set_value(1)).unwrap();
+        assert!(values.lock().unwrap().is_empty());
+        executor::block_on(characteristic.commit_batch());
+        assert_eq!(characteristic.value_ref(), &1);
+        assert_eq!(*values.lock().unwrap(), vec![json!(1)]);
+    }
+
+    #[test]
+    fn test_auto_reset() {
+        let (event_emitter, values) = recording_event_emitter();
+        let clock = MockClock::new();
+        let mut characteristic = Characteristic::<bool> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::MotionDetected,
+            format: Format::Bool,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            event_notifications: Some(true),
+            event_emitters: vec![event_emitter],
+            ..Default::default()
+        };
+        characteristic.set_clock(Some(clock.clone()));
+        characteristic.set_auto_reset(Some(Duration::from_secs(10)));
+
+        executor::block_on(characteristic.set_value(true)).unwrap();
+        clock.advance(Duration::from_secs(6));
+        executor::block_on(characteristic.set_value(true)).unwrap();
+        clock.advance(Duration::from_secs(6));
+        assert!(executor::block_on(characteristic.get_value()).unwrap());
+
+        clock.advance(Duration::from_secs(4));
+        assert!(executor::block_on(characteristic.reset_if_expired()).unwrap());
+        assert_eq!(characteristic.value_ref(), &false);
+        assert_eq!(*values.lock().unwrap(), vec![json!(true), json!(true), json!(false)]);
+        assert!(!executor::block_on(characteristic.reset_if_expired()).unwrap());
+
+        // the timer resets the value and notifies subscribers without a read
+        executor::block_on(characteristic.set_value(true)).unwrap();
+        clock.advance(Duration::from_secs(9));
+        assert!(!executor::block_on(characteristic.tick()).unwrap());
+        clock.advance(Duration::from_secs(1));
+        assert!(executor::block_on(characteristic.tick()).unwrap());
+        assert_eq!(characteristic.value_ref(), &false);
+        assert_eq!(values.lock().unwrap().last(), Some(&json!(false)));
+        assert_eq!(values.lock().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_auto_reset_outside_bounds() {
+        let clock = MockClock::new();
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentPosition,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            min_value: Some(10),
+            max_value: Some(100),
+            value: 50,
+            ..Default::default()
+        };
+        characteristic.set_clock(Some(clock.clone()));
+        characteristic.set_auto_reset(Some(Duration::from_secs(10)));
+
+        characteristic.reset_at = Some(clock.now() + Duration::from_secs(10));
+        let version = characteristic.version();
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(executor::block_on(characteristic.get_value()).unwrap(), 0);
+        assert_eq!(characteristic.version(), version + 1);
+        assert!(!executor::block_on(characteristic.tick()).unwrap());
+    }
+
+    #[test]
+    fn test_would_accept() {
+        let characteristic = Characteristic::<f32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::TargetTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            max_value: Some(38.0),
+            min_value: Some(10.0),
+            step_value: Some(0.1),
+            ..Default::default()
+        };
+
+        assert!(characteristic.would_accept(&21.5).is_ok());
+        assert!(characteristic.would_accept(&10.0).is_ok());
+        assert!(characteristic.would_accept(&38.0).is_ok());
+        assert!(matches!(characteristic.would_accept(&38.1), Err(Error::ValueAboveMaxValue)));
+        assert!(matches!(characteristic.would_accept(&9.9), Err(Error::ValueBelowMinValue)));
+        assert!(matches!(characteristic.would_accept(&21.55), Err(Error::ValueNotOnStep)));
+
+        let characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::TargetHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            valid_values: Some(vec![0, 1, 3]),
+            ..Default::default()
+        };
+
+        assert!(characteristic.would_accept(&3).is_ok());
+        assert!(matches!(characteristic.would_accept(&2), Err(Error::ValueNotInValidValues)));
+
+        let characteristic = Characteristic::<u16> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentTiltAngle,
+            format: Format::UInt16,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            valid_values_range: Some([10, 20]),
+            ..Default::default()
+        };
+
+        assert!(characteristic.would_accept(&15).is_ok());
+        assert!(matches!(characteristic.would_accept(&5), Err(Error::ValueBelowMinValue)));
+        assert!(matches!(characteristic.would_accept(&25), Err(Error::ValueAboveMaxValue)));
+    }
+
+    #[test]
+    fn test_set_value_validation() {
+        let (event_emitter, values) = recording_event_emitter();
+        let mut characteristic = Characteristic::<i32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::Brightness,
+            format: Format::Int32,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            event_notifications: Some(true),
+            max_value: Some(100),
+            min_value: Some(0),
+            step_value: Some(5),
+            event_emitters: vec![event_emitter],
+            ..Default::default()
+        };
+        let updates = Arc::new(StdMutex::new(0));
+        let u = updates.clone();
+        characteristic.on_update(Some(move |_: &i32, _: &i32| *u.lock().unwrap() += 1));
+
+        assert!(matches!(
+            executor::block_on(characteristic.set_value(101)),
+            Err(Error::ValueAboveMaxValue)
+        ));
+        assert!(matches!(
+            executor::block_on(characteristic.set_value(-1)),
+            Err(Error::ValueBelowMinValue)
+        ));
+        assert!(matches!(
+            executor::block_on(characteristic.set_value(42)),
+            Err(Error::ValueNotOnStep)
+        ));
+        assert_eq!(characteristic.value_ref(), &0);
+        assert_eq!(*updates.lock().unwrap(), 0);
+        assert!(values.lock().unwrap().is_empty());
+
+        executor::block_on(characteristic.set_value(45)).unwrap();
+        assert_eq!(characteristic.value_ref(), &45);
+        assert_eq!(*updates.lock().unwrap(), 1);
+        assert_eq!(*values.lock().unwrap(), vec![json!(45)]);
+    }
+
+    #[test]
+    fn test_toggle() {
+        let (event_emitter, values) = recording_event_emitter();
+        let mut characteristic = Characteristic::<bool> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::On,
+            format: Format::Bool,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            event_notifications: Some(true),
+            event_emitters: vec![event_emitter],
+            ..Default::default()
+        };
+
+        assert!(executor::block_on(characteristic.toggle()).unwrap());
+        assert_eq!(characteristic.value_ref(), &true);
+        assert!(!executor::block_on(characteristic.toggle()).unwrap());
+        assert_eq!(characteristic.value_ref(), &false);
+        assert_eq!(*values.lock().unwrap(), vec![json!(true), json!(false)]);
+
+        characteristic.set_encryption_key(Some([7; 32])).unwrap();
+        assert!(executor::block_on(characteristic.toggle()).unwrap());
+        assert!(!executor::block_on(characteristic.toggle()).unwrap());
+        characteristic.set_encryption_key(None).unwrap();
+
+        characteristic.on_read(Some(|| Some(true)));
+        assert!(!executor::block_on(characteristic.toggle()).unwrap());
+        assert_eq!(characteristic.value_ref(), &false);
+    }
+
+    #[test]
+    fn test_multiple_event_emitters() {
+        let (event_emitter, values) = recording_event_emitter();
+        let (other_event_emitter, other_values) = recording_event_emitter();
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            event_notifications: Some(true),
+            ..Default::default()
+        };
+        characteristic.set_event_emitter(Some(event_emitter));
+        characteristic.add_event_emitter(other_event_emitter);
+
+        executor::block_on(characteristic.set_value(1)).unwrap();
+        assert_eq!(*values.lock().unwrap(), vec![json!(1)]);
+        assert_eq!(*other_values.lock().unwrap(), vec![json!(1)]);
+
+        characteristic.set_event_emitter(None);
+        executor::block_on(characteristic.set_value(2)).unwrap();
+        assert_eq!(*values.lock().unwrap(), vec![json!(1)]);
+        assert_eq!(*other_values.lock().unwrap(), vec![json!(1)]);
+    }
+
+    #[test]
+    fn test_last_updated() {
+        let clock = MockClock::new();
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            valid_values: Some(vec![0, 1, 2]),
+            ..Default::default()
+        };
+        characteristic.set_clock(Some(clock.clone()));
+        assert_eq!(characteristic.get_last_updated(), None);
+
+        executor::block_on(characteristic.set_value(1)).unwrap();
+        let first_update = characteristic.get_last_updated().unwrap();
+        assert_eq!(first_update, clock.now());
+
+        clock.advance(Duration::from_secs(5));
+        assert!(executor::block_on(characteristic.set_value(3)).is_err());
+        assert_eq!(characteristic.get_last_updated(), Some(first_update));
+
+        executor::block_on(characteristic.set_value(2)).unwrap();
+        assert_eq!(
+            characteristic.get_last_updated(),
+            Some(first_update + Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn test_delta_since_snapshot() {
+        let mut characteristic = Characteristic::<i32> {
+            id: 2,
+            accessory_id: 1,
+            hap_type: HapType::Brightness,
+            format: Format::Int32,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            unit: Some(Unit::Percentage),
+            max_value: Some(100),
+            min_value: Some(0),
+            ..Default::default()
+        };
+
+        characteristic.snapshot().unwrap();
+        assert_eq!(characteristic.delta_since_snapshot().unwrap(), json!({ "aid": 1, "iid": 2 }));
+
+        executor::block_on(characteristic.set_value(50)).unwrap();
+        assert_eq!(
+            characteristic.delta_since_snapshot().unwrap(),
+            json!({ "aid": 1, "iid": 2, "value": 50 })
+        );
+
+        characteristic.set_max_value(Some(80));
+        characteristic.set_min_value(None);
+        characteristic.set_description(Some("Dimmer".into()));
+        assert_eq!(
+            characteristic.delta_since_snapshot().unwrap(),
+            json!({ "aid": 1, "iid": 2, "value": 50, "maxValue": 80, "minValue": null, "description": "Dimmer" })
+        );
+
+        characteristic.snapshot().unwrap();
+        assert_eq!(characteristic.delta_since_snapshot().unwrap(), json!({ "aid": 1, "iid": 2 }));
+    }
+
+    #[test]
+    fn test_identify() {
+        let (event_emitter, values) = recording_event_emitter();
+        let identifications = Arc::new(StdMutex::new(0));
+        let i = identifications.clone();
+        let mut characteristic = Characteristic::identify(1, 1, move || *i.lock().unwrap() += 1);
+        characteristic.set_event_emitter(Some(event_emitter));
+        characteristic.set_event_notifications(Some(true));
+
+        executor::block_on(characteristic.set_value(true)).unwrap();
+        executor::block_on(characteristic.set_value(true)).unwrap();
+        assert_eq!(*identifications.lock().unwrap(), 2);
+        assert_eq!(characteristic.value_ref(), &false);
+        assert!(values.lock().unwrap().is_empty());
+
+        let json = serde_json::to_string(&characteristic).unwrap();
+        assert_eq!(
+            json,
+            "{\"iid\":1,\"type\":\"14\",\"format\":\"bool\",\"perms\":[\"pw\"],\"ev\":true}".to_string()
+        );
+    }
+
+    #[test]
+    fn test_json_serialization_with_linked_iids() {
+        let mut characteristic = Characteristic::<u32> {
+            id: 5,
+            accessory_id: 1,
+            hap_type: HapType::SetDuration,
+            format: Format::UInt32,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&characteristic).unwrap();
+        assert_eq!(
+            json,
+            "{\"iid\":5,\"type\":\"D3\",\"format\":\"uint32\",\"perms\":[\"pr\",\"pw\",\"ev\"],\"value\":0}".to_string()
+        );
+
+        characteristic.set_linked_iids(vec![6]).unwrap();
+        assert_eq!(characteristic.get_linked_iids(), vec![6]);
+        let json = serde_json::to_string(&characteristic).unwrap();
+        assert_eq!(
+            json,
+            "{\"iid\":5,\"type\":\"D3\",\"format\":\"uint32\",\"perms\":[\"pr\",\"pw\",\"ev\"],\"linked\":[6],\"value\":0}"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_sanitization() {
+        let mut characteristic = Characteristic::<String> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::Name,
+            format: Format::String,
+            perms: vec![Perm::PairedRead],
+            ..Default::default()
+        };
+
+        executor::block_on(characteristic.set_value("Acme\0Lamp\n".into())).unwrap();
+        assert_eq!(characteristic.value_ref(), "Acme\0Lamp\n");
+
+        characteristic.set_sanitization(Sanitization::Strip);
+        executor::block_on(characteristic.set_value("Acme\0 Lamp\n".into())).unwrap();
+        assert_eq!(characteristic.value_ref(), "Acme Lamp");
+
+        characteristic.set_sanitization(Sanitization::Reject);
+        assert!(matches!(
+            executor::block_on(characteristic.set_value("Acme\nLamp".into())),
+            Err(Error::ValueContainsControlCharacters)
+        ));
+        assert!(matches!(
+            executor::block_on(characteristic.set_value("Acme\0Lamp".into())),
+            Err(Error::ValueContainsControlCharacters)
+        ));
+        assert_eq!(characteristic.value_ref(), "Acme Lamp");
+        executor::block_on(characteristic.set_value("Acme Light".into())).unwrap();
+        assert_eq!(characteristic.value_ref(), "Acme Light");
+    }
+
+    #[test]
+    fn test_event_channel_backpressure() {
+        let (sender, mut receiver) = mpsc::channel(0);
+        let mut event_emitter = EventEmitter::new();
+        event_emitter.add_sender(sender);
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            event_notifications: Some(true),
+            event_emitters: vec![Arc::new(Mutex::new(event_emitter))],
+            ..Default::default()
+        };
+
+        executor::block_on(async {
+            characteristic.set_value(1).await.unwrap();
+
+            {
+                let write = characteristic.set_value(2);
+                pin_mut!(write);
+                assert!(poll!(write.as_mut()).is_pending());
+
+                let event = receiver.next().await.unwrap();
+                assert!(matches!(event, Event::CharacteristicValueChanged { ref value, .. } if value == &json!(1)));
+                assert!(poll!(write.as_mut()).is_ready());
+            }
+            assert_eq!(characteristic.value_ref(), &2);
+
+            drop(receiver);
+            characteristic.set_value(1).await.unwrap();
+            characteristic.set_value(2).await.unwrap();
+            assert_eq!(characteristic.value_ref(), &2);
+        });
+    }
+
+    #[test]
+    fn test_emit_on_subscribe() {
+        let (event_emitter, values) = recording_event_emitter();
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            value: 2,
+            event_emitters: vec![event_emitter],
+            ..Default::default()
+        };
+
+        executor::block_on(characteristic.handle_subscribe());
+        assert!(values.lock().unwrap().is_empty());
+
+        characteristic.set_emit_on_subscribe(true);
+        executor::block_on(characteristic.handle_subscribe());
+        assert_eq!(*values.lock().unwrap(), vec![json!(2)]);
+    }
+
+    #[test]
+    fn test_json_serialization_with_type_format() {
+        let mut characteristic = Characteristic::<i32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::Brightness,
+            format: Format::Int32,
+            perms: vec![Perm::PairedRead],
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&characteristic).unwrap();
+        assert_eq!(
+            json,
+            "{\"iid\":1,\"type\":\"8\",\"format\":\"int32\",\"perms\":[\"pr\"],\"value\":0}".to_string()
+        );
+
+        characteristic.set_type_format(TypeFormat::Uuid).unwrap();
+        let json = serde_json::to_string(&characteristic).unwrap();
+        assert_eq!(
+            json,
+            "{\"iid\":1,\"type\":\"00000008-0000-1000-8000-0026BB765291\",\"format\":\"int32\",\"perms\":[\"pr\"],\"value\":0}"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_apply_metadata() {
+        let (event_emitter, structure_changes) = structure_event_emitter();
+        let mut characteristic = Characteristic::<f32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            unit: Some(Unit::Celsius),
+            max_value: Some(100.0),
+            min_value: Some(0.0),
+            event_emitters: vec![event_emitter],
+            ..Default::default()
+        };
+
+        executor::block_on(characteristic.apply_metadata(CharacteristicMetadata::default())).unwrap();
+        assert_eq!(*structure_changes.lock().unwrap(), 0);
+
+        executor::block_on(characteristic.apply_metadata(CharacteristicMetadata {
+            min_value: Some(-40.0),
+            step_value: Some(0.5),
+            ..Default::default()
+        }))
+        .unwrap();
+        assert_eq!(*structure_changes.lock().unwrap(), 1);
+        assert_eq!(characteristic.get_min_value(), Some(-40.0));
+        assert_eq!(characteristic.get_step_value(), Some(0.5));
+        assert_eq!(characteristic.get_max_value(), Some(100.0));
+        assert!(matches!(characteristic.get_unit(), Some(Unit::Celsius)));
+    }
+
+    #[test]
+    fn test_writes_locked() {
+        let mut characteristic = Characteristic::<bool> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::On,
+            format: Format::Bool,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            ..Default::default()
+        };
+        let updates = Arc::new(StdMutex::new(0));
+        let u = updates.clone();
+        characteristic.on_update(Some(move |_: &bool, _: &bool| *u.lock().unwrap() += 1));
+
+        executor::block_on(characteristic.set_json_value(json!(1))).unwrap();
+        assert_eq!(characteristic.value_ref(), &true);
+
+        characteristic.set_writes_locked(true);
+        assert!(matches!(
+            executor::block_on(characteristic.set_json_value(json!(false))),
+            Err(Error::ResourceBusy)
+        ));
+        assert_eq!(*updates.lock().unwrap(), 1);
+        assert!(executor::block_on(characteristic.get_value()).unwrap());
+
+        executor::block_on(characteristic.set_value(false)).unwrap();
+        assert_eq!(characteristic.value_ref(), &false);
+        assert_eq!(*updates.lock().unwrap(), 2);
+
+        characteristic.set_writes_locked(false);
+        executor::block_on(characteristic.set_json_value(json!(true))).unwrap();
+        assert_eq!(characteristic.value_ref(), &true);
+    }
+
+    #[test]
+    fn test_event_notifications_to_json() {
+        let mut characteristic = Characteristic::<u8> {
+            id: 7,
+            accessory_id: 2,
+            hap_type: HapType::CurrentDoorState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_string(&characteristic.event_notifications_to_json()).unwrap(),
+            r#"{"aid":2,"ev":false,"iid":7}"#
+        );
+
+        characteristic.set_event_notifications(Some(true));
+        assert_eq!(
+            serde_json::to_string(&characteristic.event_notifications_to_json()).unwrap(),
+            r#"{"aid":2,"ev":true,"iid":7}"#
+        );
+    }
+
+    #[test]
+    fn test_advertised_step() {
+        let mut characteristic = Characteristic::<i32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::Brightness,
+            format: Format::Int32,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            max_value: Some(100),
+            min_value: Some(0),
+            step_value: Some(1),
+            ..Default::default()
+        };
+        characteristic.set_advertised_step(Some(5)).unwrap();
+        assert_eq!(characteristic.get_advertised_step(), Some(5));
+        assert_eq!(characteristic.get_step_value(), Some(1));
+        assert_eq!(json!(&characteristic)["minStep"], json!(5));
+
+        executor::block_on(characteristic.set_json_value(json!(37))).unwrap();
+        assert_eq!(characteristic.value_ref(), &35);
+        executor::block_on(characteristic.set_json_value(json!(98))).unwrap();
+        assert_eq!(characteristic.value_ref(), &100);
+
+        executor::block_on(characteristic.set_value(37)).unwrap();
+        assert_eq!(characteristic.value_ref(), &37);
+
+        characteristic.set_advertised_step(None).unwrap();
+        assert_eq!(characteristic.get_advertised_step(), Some(1));
+        executor::block_on(characteristic.set_json_value(json!(37))).unwrap();
+        assert_eq!(characteristic.value_ref(), &37);
+    }
+
+    #[test]
+    fn test_needs_refresh() {
+        let clock = MockClock::new();
+        let mut characteristic = Characteristic::<f32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            ..Default::default()
+        };
+        characteristic.set_clock(Some(clock.clone()));
+        assert!(characteristic.needs_refresh(Duration::from_secs(60)));
+
+        executor::block_on(characteristic.set_value(21.5)).unwrap();
+        assert!(!characteristic.needs_refresh(Duration::from_secs(60)));
+
+        clock.advance(Duration::from_secs(59));
+        assert!(!characteristic.needs_refresh(Duration::from_secs(60)));
+        clock.advance(Duration::from_secs(1));
+        assert!(characteristic.needs_refresh(Duration::from_secs(60)));
+
+        let write_only = Characteristic::<bool> {
+            id: 2,
+            accessory_id: 1,
+            hap_type: HapType::Identify,
+            format: Format::Bool,
+            perms: vec![Perm::PairedWrite],
+            ..Default::default()
+        };
+        assert!(!write_only.needs_refresh(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_display_conversion() {
+        let mut characteristic = Characteristic::<f32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            unit: Some(Unit::Celsius),
+            ..Default::default()
+        };
+
+        for (celsius, fahrenheit) in &[(0.0, 32.0), (100.0, 212.0), (-40.0, -40.0), (21.5, 70.7)] {
+            executor::block_on(characteristic.set_value(*celsius)).unwrap();
+            assert!((characteristic.to_display(UnitSystem::Imperial) - fahrenheit).abs() < 0.001);
+            assert_eq!(characteristic.to_display(UnitSystem::Metric), *celsius);
+            assert!((characteristic.from_display(*fahrenheit, UnitSystem::Imperial) - celsius).abs() < 0.001);
+            assert_eq!(characteristic.from_display(*celsius, UnitSystem::Metric), *celsius);
+        }
+        assert_eq!(json!(&characteristic)["value"], json!(21.5));
+
+        characteristic.unit = Some(Unit::Percentage);
+        assert_eq!(characteristic.to_display(UnitSystem::Imperial), 21.5);
+        assert_eq!(characteristic.from_display(50.0, UnitSystem::Imperial), 50.0);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_pattern() {
+        let mut characteristic = Characteristic::<String> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::ConfiguredName,
+            format: Format::String,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            ..Default::default()
+        };
+        characteristic.set_pattern(Some(regex::Regex::new(r"^\d{1,3}(\.\d{1,3}){3}$").unwrap()));
+
+        executor::block_on(characteristic.set_json_value(json!("192.168.1.10"))).unwrap();
+        assert_eq!(characteristic.value_ref(), "192.168.1.10");
+
+        assert!(matches!(
+            executor::block_on(characteristic.set_json_value(json!("not an ip"))),
+            Err(Error::ValueDoesNotMatchPattern)
+        ));
+        assert_eq!(characteristic.value_ref(), "192.168.1.10");
+
+        characteristic.set_pattern(None);
+        executor::block_on(characteristic.set_json_value(json!("not an ip"))).unwrap();
+        assert_eq!(characteristic.value_ref(), "not an ip");
+    }
+
+    #[test]
+    fn test_effective_description() {
+        let mut characteristic = Characteristic::<f32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            ..Default::default()
+        };
+        assert_eq!(characteristic.effective_description(), "Current Temperature");
+        assert!(json!(&characteristic).get("description").is_none());
+
+        characteristic.set_description(Some("Living Room".into()));
+        assert_eq!(characteristic.effective_description(), "Living Room");
+        assert_eq!(json!(&characteristic)["description"], json!("Living Room"));
+    }
+
+    #[test]
+    fn test_into_typed() {
+        let characteristic = Characteristic::<u8> {
+            id: 3,
+            accessory_id: 2,
+            hap_type: HapType::RotationSpeed,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            description: Some("Fan Speed".into()),
+            unit: Some(Unit::Percentage),
+            value: 42,
+            max_value: Some(100),
+            min_value: Some(0),
+            step_value: Some(10),
+            valid_values: Some(vec![0, 50, 100]),
+            ..Default::default()
+        };
+
+        let (characteristic, dropped) = characteristic.into_typed::<u16>();
+        assert!(!dropped);
+        assert_eq!(characteristic.get_id(), 3);
+        assert_eq!(characteristic.get_type(), HapType::RotationSpeed);
+        assert_eq!(characteristic.get_perms(), vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events]);
+        assert_eq!(characteristic.effective_description(), "Fan Speed");
+        assert!(matches!(characteristic.get_unit(), Some(Unit::Percentage)));
+        assert_eq!(characteristic.get_max_value(), Some(100u16));
+        assert_eq!(characteristic.get_min_value(), Some(0));
+        assert_eq!(characteristic.get_step_value(), Some(10));
+        assert_eq!(characteristic.valid_values, Some(vec![0, 50, 100]));
+        assert_eq!(characteristic.value_ref(), &0);
+
+        let characteristic = Characteristic::<u16> {
+            id: 3,
+            accessory_id: 2,
+            hap_type: HapType::RotationSpeed,
+            format: Format::UInt16,
+            perms: vec![Perm::PairedRead],
+            max_value: Some(1000),
+            min_value: Some(0),
+            ..Default::default()
+        };
+        let (characteristic, dropped) = characteristic.into_typed::<u8>();
+        assert!(dropped);
+        assert_eq!(characteristic.get_max_value(), None);
+        assert_eq!(characteristic.get_min_value(), Some(0));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_metrics() {
+        let (event_emitter, _) = recording_event_emitter();
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentDoorState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            event_notifications: Some(true),
+            max_value: Some(4),
+            ..Default::default()
+        };
+        characteristic.add_event_emitter(event_emitter);
+        assert_eq!(characteristic.metrics(), CharacteristicMetrics::default());
+
+        executor::block_on(characteristic.set_value(1)).unwrap();
+        executor::block_on(characteristic.set_value(2)).unwrap();
+        assert!(executor::block_on(characteristic.set_value(5)).is_err());
+        executor::block_on(characteristic.get_value()).unwrap();
+        assert_eq!(characteristic.metrics(), CharacteristicMetrics {
+            reads: 1,
+            writes: 2,
+            events: 2,
+            adjusted: 0,
+        });
+
+        characteristic.set_event_notifications(Some(false));
+        executor::block_on(characteristic.set_value(3)).unwrap();
+        assert_eq!(characteristic.metrics(), CharacteristicMetrics {
+            reads: 1,
+            writes: 3,
+            events: 2,
+            adjusted: 0,
+        });
+    }
+
+    #[test]
+    fn test_validate_valid_values() {
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::TargetHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            valid_values: Some(vec![0, 1, 3]),
+            ..Default::default()
+        };
+        assert!(characteristic.validate().is_ok());
+
+        characteristic.valid_values_range = Some([0, 3]);
+        assert!(matches!(characteristic.validate(), Err(Error::ConflictingValidValues)));
+
+        let json = json!(&characteristic);
+        assert_eq!(json["valid-values"], json!([0, 1, 3]));
+        assert!(json.get("valid-values-range").is_none());
+
+        characteristic.valid_values = None;
+        assert!(characteristic.validate().is_ok());
+        assert_eq!(json!(&characteristic)["valid-values-range"], json!([0, 3]));
+    }
+
+    #[test]
+    fn test_serialize_into() {
+        let characteristic = Characteristic::<String> {
+            id: 2,
+            accessory_id: 1,
+            hap_type: HapType::Name,
+            format: Format::String,
+            perms: vec![Perm::PairedRead],
+            value: "Lamp".into(),
+            ..Default::default()
+        };
+
+        let mut buffer = Vec::with_capacity(256);
+        characteristic.serialize_into(&mut buffer).unwrap();
+        assert_eq!(buffer, serde_json::to_string(&characteristic).unwrap().into_bytes());
+
+        buffer.clear();
+        characteristic.serialize_into(&mut buffer).unwrap();
+        characteristic.serialize_into(&mut buffer).unwrap();
+        assert_eq!(buffer, serde_json::to_string(&characteristic).unwrap().repeat(2).into_bytes());
+    }
+
+    #[test]
+    fn test_data_source() {
+        struct MockDataSource(Arc<StdMutex<usize>>);
+
+        #[async_trait]
+        impl DataSource for MockDataSource {
+            async fn fetch(&self) -> Result<Vec<u8>> {
+                let mut fetches = self.0.lock().unwrap();
+                *fetches += 1;
+                Ok(vec![*fetches as u8; 1024])
+            }
+        }
+
+        let mut characteristic = Characteristic::<Vec<u8>> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::Unknown,
+            format: Format::Data,
+            perms: vec![Perm::PairedRead],
+            ..Default::default()
+        };
+        let fetches = Arc::new(StdMutex::new(0));
+        characteristic.set_data_source(Some(MockDataSource(fetches.clone())));
+        assert_eq!(*fetches.lock().unwrap(), 0);
+
+        assert_eq!(executor::block_on(characteristic.get_value()).unwrap(), vec![1; 1024]);
+        assert_eq!(executor::block_on(characteristic.get_value()).unwrap(), vec![2; 1024]);
+        assert_eq!(*fetches.lock().unwrap(), 2);
+        assert!(characteristic.value_ref().is_empty());
+
+        characteristic.set_data_source(None::<MockDataSource>);
+        assert!(executor::block_on(characteristic.get_value()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_freeze() {
+        let (event_emitter, structure_changes) = structure_event_emitter();
+        let mut characteristic = Characteristic::<i32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::Brightness,
+            format: Format::Int32,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            max_value: Some(100),
+            min_value: Some(0),
+            ..Default::default()
+        };
+        characteristic.add_event_emitter(event_emitter);
+        characteristic.freeze();
+        assert!(characteristic.is_frozen());
+
+        characteristic.set_max_value(Some(50));
+        characteristic.set_min_value(Some(10));
+        characteristic.set_description(Some("Dimmer".into()));
+        characteristic.set_step_value(Some(5));
+        assert!(matches!(characteristic.set_notify_threshold(Some(5)), Err(Error::MetadataFrozen)));
+        assert!(matches!(characteristic.set_bounds_mode(BoundsMode::AutoExpand), Err(Error::MetadataFrozen)));
+        assert!(matches!(
+            executor::block_on(characteristic.apply_metadata(CharacteristicMetadata {
+                min_value: Some(10),
+                ..Default::default()
+            })),
+            Err(Error::MetadataFrozen)
+        ));
+        assert_eq!(characteristic.get_max_value(), Some(100));
+        assert_eq!(characteristic.get_min_value(), Some(0));
+        assert_eq!(characteristic.get_step_value(), None);
+        assert_eq!(characteristic.effective_description(), "Brightness");
+        assert_eq!(*structure_changes.lock().unwrap(), 0);
+
+        executor::block_on(characteristic.set_value(75)).unwrap();
+        assert_eq!(characteristic.value_ref(), &75);
+
+        let mut config = Config::default();
+        let configuration_number = config.configuration_number;
+        characteristic.unfreeze(&mut config);
+        assert!(!characteristic.is_frozen());
+        assert_eq!(config.configuration_number, configuration_number + 1);
+        characteristic.set_max_value(Some(50));
+        assert_eq!(characteristic.get_max_value(), Some(50));
+    }
+
+    #[test]
+    fn test_signature() {
+        let mut characteristic = Characteristic::<u8> {
+            id: 4,
+            accessory_id: 1,
+            hap_type: HapType::TargetHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            description: Some("Mode".into()),
+            event_notifications: Some(true),
+            value: 1,
+            max_value: Some(3),
+            min_value: Some(0),
+            step_value: Some(1),
+            valid_values: Some(vec![0, 1, 3]),
+            ..Default::default()
+        };
+
+        let signature = characteristic.signature();
+        assert_eq!(signature, CharacteristicSignature {
+            id: 4,
+            hap_type: HapType::TargetHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            description: Some("Mode".into()),
+            linked_iids: vec![],
+            unit: None,
+            max_value: Some(3),
+            min_value: Some(0),
+            step_value: Some(1),
+            max_len: None,
+            max_data_len: None,
+            valid_values: Some(vec![0, 1, 3]),
+            valid_values_range: None,
+        });
+        assert_eq!(
+            serde_json::to_string(&signature).unwrap(),
+            "{\"iid\":4,\"type\":\"33\",\"format\":\"uint8\",\"perms\":[\"pr\",\"pw\",\"ev\"],\"description\":\"Mode\",\"maxValue\":3,\"minValue\":0,\"minStep\":1,\"valid-values\":[0,1,3]}"
+        );
+
+        executor::block_on(characteristic.set_value(3)).unwrap();
+        assert_eq!(characteristic.signature(), signature);
+    }
+
+    #[test]
+    fn test_on_committed() {
+        let log = Arc::new(StdMutex::new(Vec::new()));
+
+        let mut event_emitter = EventEmitter::new();
+        let l = log.clone();
+        event_emitter.add_listener(Box::new(move |_| {
+            l.lock().unwrap().push("event".to_string());
+            async {}.boxed()
+        }));
+
+        let mut characteristic = Characteristic::<bool> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::On,
+            format: Format::Bool,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            event_notifications: Some(true),
+            ..Default::default()
+        };
+        characteristic.add_event_emitter(Arc::new(Mutex::new(event_emitter)));
+        let l = log.clone();
+        characteristic.on_update(Some(move |_: &bool, _: &bool| l.lock().unwrap().push("update".to_string())));
+        let l = log.clone();
+        characteristic.on_committed(Some(move |v: &bool| l.lock().unwrap().push(format!("committed {}", v))));
+
+        executor::block_on(characteristic.set_value(true)).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["update", "event", "committed true"]);
+        assert_eq!(characteristic.value_ref(), &true);
+
+        log.lock().unwrap().clear();
+        characteristic.set_writes_locked(true);
+        assert!(executor::block_on(characteristic.set_json_value(json!(false))).is_err());
+        assert!(log.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_non_finite_floats() {
+        let mut characteristic = Characteristic::<f32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            value: 21.5,
+            ..Default::default()
+        };
+
+        for v in &[f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert!(matches!(
+                executor::block_on(characteristic.set_value(*v)),
+                Err(Error::InvalidValue(Format::Float))
+            ));
+            assert_eq!(characteristic.value_ref(), &21.5);
+        }
+        assert!(executor::block_on(characteristic.set_json_value(json!(null))).is_err());
+        assert!(executor::block_on(characteristic.set_json_value(json!("NaN"))).is_err());
+
+        characteristic.value = f32::NAN;
+        assert_eq!(json!(&characteristic)["value"], json!(0.0));
+        assert_eq!(executor::block_on(characteristic.get_json_value()).unwrap(), json!(0.0));
+        characteristic.set_precision(Some(1));
+        assert_eq!(json!(&characteristic)["value"], json!(0.0));
+    }
+
+    #[test]
+    fn test_event_verbosity() {
+        let events = Arc::new(StdMutex::new(Vec::new()));
+        let mut event_emitter = EventEmitter::new();
+        let e = events.clone();
+        event_emitter.add_listener(Box::new(move |event| {
+            e.lock().unwrap().push(event.clone());
+            async {}.boxed()
+        }));
+
+        let mut characteristic = Characteristic::<f32> {
+            id: 2,
+            accessory_id: 1,
+            hap_type: HapType::CurrentTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            event_notifications: Some(true),
+            unit: Some(Unit::Celsius),
+            ..Default::default()
+        };
+        characteristic.add_event_emitter(Arc::new(Mutex::new(event_emitter)));
+
+        executor::block_on(characteristic.set_value(21.5)).unwrap();
+        characteristic.set_event_verbosity(EventVerbosity::Verbose);
+        executor::block_on(characteristic.set_value(22.0)).unwrap();
+
+        let events = events.lock().unwrap();
+        assert!(matches!(events[0], Event::CharacteristicValueChanged {
+            aid: 1,
+            iid: 2,
+            unit: None,
+            format: None,
+            ..
+        }));
+        assert!(matches!(events[1], Event::CharacteristicValueChanged {
+            aid: 1,
+            iid: 2,
+            unit: Some(Unit::Celsius),
+            format: Some(Format::Float),
+            ..
+        }));
+    }
+
+    #[test]
+    fn test_list_elements() {
+        let (event_emitter, values) = recording_event_emitter();
+        let mut characteristic = Characteristic::<Vec<String>> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::Unknown,
+            format: Format::Data,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            event_notifications: Some(true),
+            ..Default::default()
+        };
+        characteristic.add_event_emitter(event_emitter);
+
+        executor::block_on(characteristic.append_element("a".into())).unwrap();
+        executor::block_on(characteristic.append_element("b".into())).unwrap();
+        executor::block_on(characteristic.append_element("c".into())).unwrap();
+        assert_eq!(characteristic.value_ref(), &vec!["a", "b", "c"]);
+
+        assert_eq!(executor::block_on(characteristic.remove_element(1)).unwrap(), "b");
+        executor::block_on(characteristic.replace_element(1, "d".into())).unwrap();
+        assert_eq!(characteristic.value_ref(), &vec!["a", "d"]);
+
+        assert!(matches!(
+            executor::block_on(characteristic.remove_element(2)),
+            Err(Error::ElementIndexOutOfBounds(2))
+        ));
+        assert!(executor::block_on(characteristic.replace_element(5, "e".into())).is_err());
+
+        assert_eq!(*values.lock().unwrap(), vec![
+            json!(["a"]),
+            json!(["a", "b"]),
+            json!(["a", "b", "c"]),
+            json!(["a", "c"]),
+            json!(["a", "d"]),
+        ]);
+        assert_eq!(json!(&characteristic)["value"], json!(["a", "d"]));
+    }
+
+    #[test]
+    fn test_standard_characteristic_templates() {
+        let on = Characteristic::<bool>::from(on::OnCharacteristic::new(9, 1));
+        assert_eq!(
+            serde_json::to_string(&on).unwrap(),
+            "{\"iid\":9,\"type\":\"25\",\"format\":\"bool\",\"perms\":[\"pr\",\"pw\",\"ev\"],\"value\":false}"
+        );
+
+        let brightness = Characteristic::<i32>::from(brightness::BrightnessCharacteristic::new(10, 1));
+        assert_eq!(
+            serde_json::to_string(&brightness).unwrap(),
+            "{\"iid\":10,\"type\":\"8\",\"format\":\"int32\",\"perms\":[\"pr\",\"pw\",\"ev\"],\"value\":0,\"unit\":\"percentage\",\"maxValue\":100,\"minValue\":0,\"minStep\":1}"
+        );
+
+        let hue = Characteristic::<f32>::from(hue::HueCharacteristic::new(11, 1));
+        assert_eq!(hue.get_format(), Format::Float);
+        assert_eq!(hue.get_unit(), Some(Unit::ArcDegrees));
+        assert_eq!(hue.get_max_value(), Some(360.0));
+        assert_eq!(hue.get_min_value(), Some(0.0));
+
+        let current_temperature = Characteristic::<f32>::from(current_temperature::CurrentTemperatureCharacteristic::new(12, 1));
+        assert_eq!(current_temperature.get_perms(), vec![Perm::PairedRead, Perm::Events]);
+        assert_eq!(current_temperature.get_unit(), Some(Unit::Celsius));
+        assert_eq!(current_temperature.get_max_value(), Some(100.0));
+        assert_eq!(current_temperature.get_min_value(), Some(0.0));
+        assert_eq!(current_temperature.get_step_value(), Some(0.1));
+
+        let target_temperature = Characteristic::<f32>::from(target_temperature::TargetTemperatureCharacteristic::new(13, 1));
+        assert_eq!(target_temperature.get_perms(), vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events]);
+        assert_eq!(target_temperature.get_max_value(), Some(38.0));
+        assert_eq!(target_temperature.get_min_value(), Some(10.0));
+    }
+
+    #[test]
+    fn test_subscriber_count() {
+        let characteristic = Characteristic::<f32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            ..Default::default()
+        };
+        assert_eq!(characteristic.subscriber_count(), 0);
+
+        executor::block_on(characteristic.handle_subscribe());
+        executor::block_on(characteristic.handle_subscribe());
+        assert_eq!(characteristic.subscriber_count(), 2);
+
+        characteristic.handle_unsubscribe();
+        assert_eq!(characteristic.subscriber_count(), 1);
+        characteristic.handle_unsubscribe();
+        characteristic.handle_unsubscribe();
+        assert_eq!(characteristic.subscriber_count(), 0);
+    }
+
+    #[test]
+    fn test_serialization_mode() {
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::TargetHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            max_data_len: Some(64),
+            valid_values_range: Some([0, 3]),
+            ..Default::default()
+        };
+
+        let fields = |characteristic: &Characteristic<u8>| {
+            json!(characteristic)
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(characteristic.get_serialization_mode(), SerializationMode::Modern);
+        assert_eq!(fields(&characteristic), vec![
+            "format",
+            "iid",
+            "maxDataLen",
+            "perms",
+            "type",
+            "valid-values-range",
+            "value"
+        ]);
+
+        characteristic.set_serialization_mode(SerializationMode::Legacy).unwrap();
+        assert_eq!(fields(&characteristic), vec!["format", "iid", "perms", "type", "value"]);
+        assert_eq!(
+            serde_json::to_string(&characteristic).unwrap(),
+            "{\"iid\":1,\"type\":\"33\",\"format\":\"uint8\",\"perms\":[\"pr\",\"pw\"],\"value\":0}"
+        );
+    }
+
+    #[test]
+    fn test_metadata_hash() {
+        let mut characteristic = Characteristic::<i32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::Brightness,
+            format: Format::Int32,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            unit: Some(Unit::Percentage),
+            max_value: Some(100),
+            min_value: Some(0),
+            ..Default::default()
+        };
+        let hash = characteristic.metadata_hash();
+        assert_eq!(characteristic.metadata_hash(), hash);
+
+        executor::block_on(characteristic.set_value(42)).unwrap();
+        assert_eq!(characteristic.metadata_hash(), hash);
+
+        let with_events = Characteristic::<i32> {
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            ..characteristic
+        };
+        assert_ne!(with_events.metadata_hash(), hash);
+    }
+
+    #[test]
+    fn test_format_from_str() {
+        assert_eq!(Format::from_str("uint16").unwrap(), Format::UInt16);
+        assert_eq!(Format::from_str_lenient("uint16"), Format::UInt16);
+
+        match Format::from_str("int64") {
+            Err(Error::InvalidFormat(format)) => assert_eq!(format, "int64"),
+            res => panic!("expected an invalid format error, got {:?}", res),
+        }
+        assert_eq!(Format::from_str_lenient("int64"), Format::Data);
+    }
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_spans() {
+        use tracing::{
+            span::{Attributes, Id, Record},
+            Metadata,
+        };
+
+        struct SpanRecorder(Arc<StdMutex<Vec<&'static str>>>);
+
+        impl tracing::Subscriber for SpanRecorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool { true }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let metadata = span.metadata();
+                assert!(metadata.fields().field("aid").is_some());
+                assert!(metadata.fields().field("iid").is_some());
+                assert!(metadata.fields().field("hap_type").is_some());
+                let mut spans = self.0.lock().unwrap();
+                spans.push(metadata.name());
+                Id::from_u64(spans.len() as u64)
+            }
+
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, _: &tracing::Event<'_>) {}
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::TargetHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            event_notifications: Some(true),
+            ..Default::default()
+        };
+
+        let spans = Arc::new(StdMutex::new(Vec::new()));
+        tracing::subscriber::with_default(SpanRecorder(spans.clone()), || {
+            executor::block_on(characteristic.set_value(1)).unwrap();
+        });
+        assert_eq!(*spans.lock().unwrap(), vec!["set_value", "emit"]);
+    }
+
+    #[test]
+    fn test_float_coercion() {
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::TargetHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            ..Default::default()
+        };
+
+        executor::block_on(characteristic.set_json_value(json!(3.0))).unwrap();
+        assert_eq!(*characteristic.value_ref(), 3);
+        assert!(matches!(
+            executor::block_on(characteristic.set_json_value(json!(3.5))),
+            Err(Error::InvalidValue(Format::UInt8))
+        ));
+        assert_eq!(*characteristic.value_ref(), 3);
+
+        characteristic.set_float_coercion(FloatCoercion::Round);
+        executor::block_on(characteristic.set_json_value(json!(1.5))).unwrap();
+        assert_eq!(*characteristic.value_ref(), 2);
+        assert!(matches!(
+            executor::block_on(characteristic.set_json_value(json!(-1.0))),
+            Err(Error::InvalidValue(Format::UInt8))
+        ));
+    }
+
+    #[test]
+    fn test_detach() {
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::TargetHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            event_notifications: Some(true),
+            ..Default::default()
+        };
+
+        let (event_emitter, values) = recording_event_emitter();
+        characteristic.add_event_emitter(event_emitter);
+        characteristic.on_read(Some(|| Some(3)));
+        characteristic.on_update(Some(|_: &u8, _: &u8| panic!("detached callback called")));
+
+        characteristic.detach();
+
+        executor::block_on(characteristic.set_value(1)).unwrap();
+        assert_eq!(executor::block_on(characteristic.get_value()).unwrap(), 1);
+        assert!(values.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_range() {
+        let mut characteristic = Characteristic::<i32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::Brightness,
+            format: Format::Int32,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            ..Default::default()
+        };
+        assert_eq!(characteristic.get_range(), None);
+
+        characteristic.set_range(10..=90).unwrap();
+        assert_eq!(characteristic.get_min_value(), Some(10));
+        assert_eq!(characteristic.get_max_value(), Some(90));
+        assert_eq!(characteristic.get_range(), Some(10..=90));
+
+        assert!(matches!(characteristic.set_range(RangeInclusive::new(90, 10)), Err(Error::InvertedRange)));
+        assert_eq!(characteristic.get_range(), Some(10..=90));
+    }
+
+    #[test]
+    fn test_link_rule() {
+        let mut characteristic = Characteristic::<i32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::Brightness,
+            format: Format::Int32,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            ..Default::default()
+        };
+        characteristic.link_rule(2, |brightness: &i32| if *brightness > 0 { Some(json!(true)) } else { None });
+
+        executor::block_on(characteristic.set_value(50)).unwrap();
+        assert!(characteristic.take_linked_updates().is_empty());
+
+        executor::block_on(characteristic.set_json_value(json!(0))).unwrap();
+        assert!(characteristic.take_linked_updates().is_empty());
+
+        executor::block_on(characteristic.set_json_value(json!(50))).unwrap();
+        assert_eq!(characteristic.take_linked_updates(), vec![(2, json!(true))]);
+        assert!(characteristic.take_linked_updates().is_empty());
+    }
+
+    #[test]
+    fn test_json_serialization_with_aid() {
+        let characteristic = Characteristic::<i32> {
+            id: 9,
+            accessory_id: 2,
+            hap_type: HapType::Brightness,
+            format: Format::Int32,
+            perms: vec![Perm::PairedRead],
+            ..Default::default()
+        };
+
+        assert!(json!(&characteristic).get("aid").is_none());
+        assert_eq!(
+            serde_json::to_string(&characteristic.with_aid()).unwrap(),
+            "{\"aid\":2,\"iid\":9,\"type\":\"8\",\"format\":\"int32\",\"perms\":[\"pr\"],\"value\":0}"
+        );
+    }
+
+    #[test]
+    fn test_step_value_from_min_value() {
+        let mut characteristic = Characteristic::<f32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::TargetTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            max_value: Some(38.0),
+            min_value: Some(10.25),
+            step_value: Some(0.5),
+            ..Default::default()
+        };
+
+        assert!(characteristic.would_accept(&10.25).is_ok());
+        assert!(characteristic.would_accept(&10.75).is_ok());
+        assert!(characteristic.would_accept(&21.25).is_ok());
+        assert!(matches!(characteristic.would_accept(&10.5), Err(Error::ValueNotOnStep)));
+        assert!(matches!(characteristic.would_accept(&21.0), Err(Error::ValueNotOnStep)));
+
+        characteristic.set_min_value(None);
+        assert!(characteristic.would_accept(&10.5).is_ok());
+        assert!(matches!(characteristic.would_accept(&10.25), Err(Error::ValueNotOnStep)));
+    }
+
+    #[test]
+    fn test_notify_threshold() {
+        let mut characteristic = Characteristic::<f32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            event_notifications: Some(true),
+            ..Default::default()
+        };
+        characteristic.set_notify_threshold(Some(0.5)).unwrap();
+        let (event_emitter, values) = recording_event_emitter();
+        characteristic.add_event_emitter(event_emitter);
+
+        for val in &[20.0, 20.25, 20.375, 20.5, 20.75, 20.25, 19.75] {
+            executor::block_on(characteristic.set_value(*val)).unwrap();
+        }
+        assert_eq!(*characteristic.value_ref(), 19.75);
+        assert_eq!(*values.lock().unwrap(), vec![json!(20.0), json!(20.5), json!(19.75)]);
+    }
+
+    #[test]
+    fn test_get_cached_value() {
+        let mut characteristic = Characteristic::<f32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            value: 21.5,
+            ..Default::default()
+        };
+        let reads = Arc::new(AtomicUsize::new(0));
+        let r = reads.clone();
+        characteristic.on_read(Some(move || {
+            r.fetch_add(1, Ordering::Relaxed);
+            Some(22.5)
+        }));
+
+        assert_eq!(characteristic.get_cached_value(), json!(21.5));
+        assert_eq!(json!(&characteristic)["value"], json!(21.5));
+        assert_eq!(reads.load(Ordering::Relaxed), 0);
+
+        assert_eq!(executor::block_on(characteristic.get_value()).unwrap(), 22.5);
+        assert_eq!(reads.load(Ordering::Relaxed), 1);
+        assert_eq!(characteristic.get_cached_value(), json!(22.5));
+    }
+
+    #[test]
+    fn test_serialized_field_count() {
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::TargetHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedWrite],
+            ..Default::default()
+        };
+        let field_count = |json: serde_json::Value| json.as_object().unwrap().len();
+
+        assert_eq!(characteristic.serialized_field_count(false), 4);
+        assert_eq!(characteristic.serialized_field_count(false), field_count(json!(&characteristic)));
+        assert_eq!(characteristic.serialized_field_count(true), field_count(json!(characteristic.with_aid())));
+
+        characteristic.perms.push(Perm::PairedRead);
+        characteristic.event_notifications = Some(true);
+        characteristic.max_data_len = Some(64);
+        characteristic.valid_values = Some(vec![0, 1, 3]);
+        characteristic.valid_values_range = Some([0, 3]);
+        assert_eq!(characteristic.serialized_field_count(false), 8);
+        assert_eq!(characteristic.serialized_field_count(false), field_count(json!(&characteristic)));
+
+        characteristic.set_serialization_mode(SerializationMode::Legacy).unwrap();
+        assert_eq!(characteristic.serialized_field_count(false), field_count(json!(&characteristic)));
+    }
+
+    #[test]
+    fn test_programmable() {
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::ProgrammableSwitchEvent,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            ..Default::default()
+        };
+        assert!(!characteristic.get_programmable());
+        assert!(json!(&characteristic).get("programmable").is_none());
+
+        characteristic.set_programmable(true).unwrap();
+        assert!(characteristic.get_programmable());
+        assert_eq!(
+            serde_json::to_string(&characteristic).unwrap(),
+            "{\"iid\":1,\"type\":\"73\",\"format\":\"uint8\",\"perms\":[\"pr\",\"ev\"],\"programmable\":true,\"value\":0}"
+        );
+    }
+
+    #[test]
+    fn test_as_any() {
+        let mut characteristic: Box<dyn HapCharacteristic> = Box::new(brightness::BrightnessCharacteristic::new(1, 1));
+
+        let brightness = characteristic.as_any_mut().downcast_mut::<Characteristic<i32>>().unwrap();
+        executor::block_on(brightness.set_value(42)).unwrap();
+        assert_eq!(characteristic.as_any().downcast_ref::<Characteristic<i32>>().unwrap().value_ref(), &42);
+
+        assert!(characteristic.as_any().downcast_ref::<Characteristic<u8>>().is_none());
+        assert!(characteristic.as_any().downcast_ref::<brightness::BrightnessCharacteristic>().is_none());
+    }
+
+    #[test]
+    fn test_bool_valid_values() {
+        let mut characteristic = Characteristic::<bool> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::On,
+            format: Format::Bool,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            ..Default::default()
+        };
+        assert!(json!(&characteristic).get("valid-values").is_none());
+
+        characteristic.set_bool_valid_values(true).unwrap();
+        assert!(characteristic.get_bool_valid_values());
+        assert_eq!(
+            serde_json::to_string(&characteristic).unwrap(),
+            "{\"iid\":1,\"type\":\"25\",\"format\":\"bool\",\"perms\":[\"pr\",\"pw\"],\"value\":false,\"valid-values\":[0,1]}"
+        );
+        assert_eq!(
+            characteristic.serialized_field_count(false),
+            json!(&characteristic).as_object().unwrap().len()
+        );
+    }
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_metrics_adjusted() {
+        let mut characteristic = Characteristic::<i32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::Brightness,
+            format: Format::Int32,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            max_value: Some(100),
+            min_value: Some(0),
+            step_value: Some(1),
+            ..Default::default()
+        };
+        characteristic.set_advertised_step(Some(5)).unwrap();
+
+        executor::block_on(characteristic.set_json_value(json!(40))).unwrap();
+        assert_eq!(characteristic.metrics().adjusted, 0);
+
+        executor::block_on(characteristic.set_json_value(json!(42))).unwrap();
+        assert_eq!(*characteristic.value_ref(), 40);
+        assert_eq!(characteristic.metrics().adjusted, 1);
+
+        characteristic.set_float_coercion(FloatCoercion::Round);
+        executor::block_on(characteristic.set_json_value(json!(45.0))).unwrap();
+        assert_eq!(characteristic.metrics().adjusted, 1);
+        executor::block_on(characteristic.set_json_value(json!(49.6))).unwrap();
+        assert_eq!(*characteristic.value_ref(), 50);
+        assert_eq!(characteristic.metrics().adjusted, 2);
+    }
+
+    #[test]
+    fn test_json_serialization_with_id_format() {
+        let mut characteristic = Characteristic::<i32> {
+            id: 9,
+            accessory_id: 2,
+            hap_type: HapType::Brightness,
+            format: Format::Int32,
+            perms: vec![Perm::PairedRead],
+            ..Default::default()
+        };
+
+        let json = json!(characteristic.with_aid());
+        assert_eq!(json["aid"], json!(2));
+        assert_eq!(json["iid"], json!(9));
+
+        characteristic.set_id_format(IdFormat::String).unwrap();
+        let json = json!(characteristic.with_aid());
+        assert_eq!(json["aid"], json!("2"));
+        assert_eq!(json["iid"], json!("9"));
+        assert_eq!(
+            serde_json::to_string(&characteristic).unwrap(),
+            "{\"iid\":\"9\",\"type\":\"8\",\"format\":\"int32\",\"perms\":[\"pr\"],\"value\":0}"
+        );
+    }
+
+    #[test]
+    fn test_catch_panics() {
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::TargetHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            ..Default::default()
+        };
+        characteristic.set_catch_panics(true);
+        characteristic.on_update(Some(|_: &u8, new: &u8| {
+            if *new == 3 {
+                panic!("unsupported mode");
+            }
+        }));
+        characteristic.on_read_async(Some(|| async { panic!("device unreachable") }.boxed()));
+
+        executor::block_on(characteristic.set_value(1)).unwrap();
+        assert!(matches!(
+            executor::block_on(characteristic.set_value(3)),
+            Err(Error::CallbackPanicked)
+        ));
+        assert_eq!(*characteristic.value_ref(), 1);
+        assert!(matches!(
+            executor::block_on(characteristic.get_value()),
+            Err(Error::CallbackPanicked)
+        ));
+    }
+
+    #[test]
+    fn test_idempotent_write_policy() {
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::TargetHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            event_notifications: Some(true),
+            ..Default::default()
+        };
+        let (event_emitter, values) = recording_event_emitter();
+        characteristic.add_event_emitter(event_emitter);
+        let updates = Arc::new(AtomicUsize::new(0));
+        let u = updates.clone();
+        characteristic.on_update(Some(move |_: &u8, _: &u8| {
+            u.fetch_add(1, Ordering::Relaxed);
+        }));
+
+        executor::block_on(characteristic.set_value(1)).unwrap();
+        executor::block_on(characteristic.set_value(1)).unwrap();
+        assert_eq!(updates.load(Ordering::Relaxed), 2);
+        assert_eq!(values.lock().unwrap().len(), 2);
+
+        characteristic.set_idempotent_write_policy(IdempotentWritePolicy::SkipCallback);
+        executor::block_on(characteristic.set_value(1)).unwrap();
+        assert_eq!(updates.load(Ordering::Relaxed), 2);
+        assert_eq!(values.lock().unwrap().len(), 3);
+
+        characteristic.set_idempotent_write_policy(IdempotentWritePolicy::SkipAll);
+        executor::block_on(characteristic.set_value(1)).unwrap();
+        assert_eq!(updates.load(Ordering::Relaxed), 2);
+        assert_eq!(values.lock().unwrap().len(), 3);
+
+        executor::block_on(characteristic.set_value(3)).unwrap();
+        assert_eq!(updates.load(Ordering::Relaxed), 3);
+        assert_eq!(values.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_json_serialization_of_pairing_characteristics() {
+        assert_eq!(
+            serde_json::to_string(&pair_setup::PairSetupCharacteristic::new(1, 1)).unwrap(),
+            "{\"iid\":1,\"type\":\"4C\",\"format\":\"tlv8\",\"perms\":[\"pr\",\"pw\"]}"
+        );
+        assert_eq!(
+            serde_json::to_string(&pair_verify::PairVerifyCharacteristic::new(2, 1)).unwrap(),
+            "{\"iid\":2,\"type\":\"4E\",\"format\":\"tlv8\",\"perms\":[\"pr\",\"pw\"]}"
+        );
+        assert_eq!(
+            serde_json::to_string(&pairing_pairings::PairingPairingsCharacteristic::new(3, 1)).unwrap(),
+            "{\"iid\":3,\"type\":\"50\",\"format\":\"tlv8\",\"perms\":[\"pr\",\"pw\"]}"
+        );
+        assert_eq!(
+            serde_json::to_string(&pairing_features::PairingFeaturesCharacteristic::new(4, 1)).unwrap(),
+            "{\"iid\":4,\"type\":\"4F\",\"format\":\"uint8\",\"perms\":[\"pr\"],\"value\":0}"
+        );
+    }
+
+    #[test]
+    fn test_get_typed() {
+        let mut level: Box<dyn HapCharacteristic> = Box::new(battery_level::BatteryLevelCharacteristic::new(1, 1));
+        let mut feedback: Box<dyn HapCharacteristic> = Box::new(audio_feedback::AudioFeedbackCharacteristic::new(2, 1));
+        executor::block_on(level.set_value(json!(42))).unwrap();
+        executor::block_on(feedback.set_value(json!(true))).unwrap();
 
-serialize_trait_object!(HapCharacteristic);
+        assert_eq!(executor::block_on(get_typed::<u8>(level.as_mut())).unwrap(), 42);
+        assert!(executor::block_on(get_typed::<bool>(feedback.as_mut())).unwrap());
+        assert!(matches!(
+            executor::block_on(get_typed::<String>(level.as_mut())),
+            Err(Error::Json(_))
+        ));
+    }
 
-pub trait HapCharacteristicSetup {
-    /// Sets a `hap::event::pointer::EventEmitter` on the characteristic.
-    fn set_event_emitter(&mut self, event_emitter: Option<pointer::EventEmitter>);
-}
+    #[test]
+    fn test_min_notify_interval() {
+        let (event_emitter, values) = recording_event_emitter();
+        let clock = MockClock::new();
+        let mut characteristic = Characteristic::<f32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            event_notifications: Some(true),
+            event_emitters: vec![event_emitter],
+            ..Default::default()
+        };
+        characteristic.set_clock(Some(clock.clone()));
+        assert_eq!(characteristic.get_min_notify_interval(), None);
+        assert_eq!(default_min_notify_interval(HapType::CurrentTemperature), Some(Duration::from_secs(1)));
+        assert_eq!(default_min_notify_interval(HapType::Brightness), None);
 
-pub trait OnReadFn<T: Default + Clone + Serialize + Send + Sync>: Fn() -> Option<T> + 'static + Send + Sync {}
-impl<F, T: Default + Clone + Serialize + Send + Sync> OnReadFn<T> for F where
-    F: Fn() -> Option<T> + 'static + Send + Sync
-{
-}
+        characteristic.set_min_notify_interval(default_min_notify_interval(HapType::CurrentTemperature));
+        executor::block_on(characteristic.set_value(20.0)).unwrap();
+        clock.advance(Duration::from_millis(500));
+        executor::block_on(characteristic.set_value(20.5)).unwrap();
+        clock.advance(Duration::from_millis(500));
+        executor::block_on(characteristic.set_value(21.0)).unwrap();
+        assert_eq!(*characteristic.value_ref(), 21.0);
+        assert_eq!(*values.lock().unwrap(), vec![json!(20.0), json!(21.0)]);
 
-pub trait OnUpdateFn<T: Default + Clone + Serialize + Send + Sync>: Fn(&T, &T) + 'static + Send + Sync {}
-impl<F, T: Default + Clone + Serialize + Send + Sync> OnUpdateFn<T> for F where F: Fn(&T, &T) + 'static + Send + Sync {}
+        // a change within the interval is notified once the interval has passed
+        clock.advance(Duration::from_millis(200));
+        executor::block_on(characteristic.set_value(21.5)).unwrap();
+        assert!(!executor::block_on(characteristic.tick()).unwrap());
+        clock.advance(Duration::from_millis(800));
+        assert!(executor::block_on(characteristic.tick()).unwrap());
+        assert_eq!(*values.lock().unwrap(), vec![json!(20.0), json!(21.0), json!(21.5)]);
+        assert!(!executor::block_on(characteristic.tick()).unwrap());
 
-pub trait OnReadFuture<T: Default + Clone + Serialize + Send + Sync>:
-    Fn() -> BoxFuture<'static, Option<T>> + 'static + Send + Sync
-{
-}
-impl<F, T: Default + Clone + Serialize + Send + Sync> OnReadFuture<T> for F where
-    F: Fn() -> BoxFuture<'static, Option<T>> + 'static + Send + Sync
-{
-}
+        characteristic.set_min_notify_interval(None);
+        executor::block_on(characteristic.set_value(22.0)).unwrap();
+        assert_eq!(values.lock().unwrap().len(), 4);
+    }
 
-pub trait OnUpdateFuture<T: Default + Clone + Serialize + Send + Sync>:
-    Fn(T, T) -> BoxFuture<'static, ()> + 'static + Send + Sync
-{
-}
-impl<F, T: Default + Clone + Serialize + Send + Sync> OnUpdateFuture<T> for F where
-    F: Fn(T, T) -> BoxFuture<'static, ()> + 'static + Send + Sync
-{
-}
+    #[test]
+    fn test_constraints_json() {
+        let characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::TargetHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            description: Some("Mode".into()),
+            value: 1,
+            max_value: Some(3),
+            min_value: Some(0),
+            step_value: Some(1),
+            valid_values: Some(vec![0, 1, 3]),
+            ..Default::default()
+        };
 
-// Fn() -> impl Future<Output = Option<T>>
-// Fn(&T, &T) -> Future<Output = ()>
+        assert_eq!(
+            characteristic.constraints_json(),
+            json!({"maxValue": 3, "minValue": 0, "minStep": 1, "valid-values": [0, 1, 3]})
+        );
+        assert_eq!(Characteristic::<String>::default().constraints_json(), json!({}));
+    }
 
-pub trait CharacteristicCallbacks<T: fmt::Debug + Default + Clone + Serialize + Send + Sync> {
-    /// Sets a callback function on a characteristic that is called every time a controller attempts to read its value.
-    /// Returning a `Some(T)` from this function changes the value of the `Characteristic` before the Controller reads
-    /// it so the Controller reads the new value.
-    fn on_read(&mut self, f: Option<impl OnReadFn<T>>);
-    /// Sets a callback function on a characteristic that is called every time a controller attempts to update its
-    /// value. The first argument is a reference to the current value of the characteristic and the second argument is a
-    /// reference to the value the controller attempts to change the characteristic's to.
-    fn on_update(&mut self, f: Option<impl OnUpdateFn<T>>);
-}
+    #[test]
+    fn test_write_coalescing() {
+        let updates = Arc::new(StdMutex::new(Vec::new()));
+        let clock = MockClock::new();
+        let mut characteristic = Characteristic::<i32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::Brightness,
+            format: Format::Int32,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            value: 10,
+            ..Default::default()
+        };
+        characteristic.set_clock(Some(clock.clone()));
+        characteristic.set_write_coalescing(Some(Duration::from_millis(300)));
+        let u = updates.clone();
+        characteristic.on_update(Some(move |old: &i32, new: &i32| u.lock().unwrap().push((*old, *new))));
 
-pub trait AsyncCharacteristicCallbacks<T: fmt::Debug + Default + Clone + Serialize + Send + Sync> {
-    /// Sets an async callback function on a characteristic that is driven to completion by the async runtime driving
-    /// the HAP server every time a controller attempts to read its value. Returning a `Some(T)` from this function
-    /// changes the value of the characteristic before the controller reads it so the controller reads the new value.
-    fn on_read_async(&mut self, f: Option<impl OnReadFuture<T>>);
-    /// Sets an async callback function on a characteristic that is driven to completion by the async runtime driving
-    /// the HAP server every time a controller attempts to update its value. The first argument is a reference to the
-    /// current value of the characteristic and the second argument is a reference to the value the controller attempts
-    /// to change the characteristic's to.
-    fn on_update_async(&mut self, f: Option<impl OnUpdateFuture<T>>);
-}
+        for val in &[20, 35, 50, 65] {
+            executor::block_on(characteristic.set_value(*val)).unwrap();
+            clock.advance(Duration::from_millis(100));
+        }
+        assert_eq!(executor::block_on(characteristic.get_value()).unwrap(), 65);
+        assert!(updates.lock().unwrap().is_empty());
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        clock.advance(Duration::from_millis(200));
+        assert!(executor::block_on(characteristic.update_if_settled()).unwrap());
+        assert_eq!(*updates.lock().unwrap(), vec![(10, 65)]);
+        assert!(!executor::block_on(characteristic.update_if_settled()).unwrap());
+    }
 
     #[test]
-    fn test_json_serialization() {
+    fn test_descriptor() {
+        let characteristic: Box<dyn HapCharacteristic> = Box::new(brightness::BrightnessCharacteristic::new(1, 1));
+
+        assert_eq!(
+            characteristic.descriptor(),
+            (HapType::Brightness, Format::Int32, vec![
+                Perm::PairedRead,
+                Perm::PairedWrite,
+                Perm::Events,
+            ])
+        );
+    }
+
+    #[test]
+    fn test_with_initial_value() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let c = calls.clone();
+        let mut characteristic = brightness::BrightnessCharacteristic::with_initial_value(1, 1, move || {
+            c.fetch_add(1, Ordering::Relaxed);
+            42
+        });
+        let (event_emitter, values) = recording_event_emitter();
+        characteristic.set_event_emitter(Some(event_emitter));
+        characteristic.set_event_notifications(Some(true));
+
+        assert_eq!(*characteristic.value_ref(), 42);
+        assert_eq!(executor::block_on(HapCharacteristic::get_value(&mut characteristic)).unwrap(), json!(42));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert!(values.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_write_pipeline() {
+        let mut characteristic = Characteristic::<f32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::TargetTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            max_value: Some(38.0),
+            min_value: Some(10.0),
+            value: 20.0,
+            ..Default::default()
+        };
+        characteristic.set_write_pipeline(Some(
+            WritePipeline::new()
+                .stage(|value: &mut serde_json::Value| {
+                    let fahrenheit = value.as_f64().ok_or(Error::InvalidValue(Format::Float))?;
+                    *value = json!((fahrenheit - 32.0) * 5.0 / 9.0);
+                    Ok(())
+                })
+                .stage(|value: &mut serde_json::Value| {
+                    let celsius = value.as_f64().ok_or(Error::InvalidValue(Format::Float))?;
+                    *value = json!(celsius.clamp(10.0, 38.0));
+                    Ok(())
+                }),
+        ));
+
+        executor::block_on(characteristic.set_json_value(json!(77))).unwrap();
+        assert_eq!(*characteristic.value_ref(), 25.0);
+        executor::block_on(characteristic.set_json_value(json!(212))).unwrap();
+        assert_eq!(*characteristic.value_ref(), 38.0);
+        assert!(matches!(
+            executor::block_on(characteristic.set_json_value(json!("hot"))),
+            Err(Error::InvalidValue(Format::Float))
+        ));
+        assert_eq!(*characteristic.value_ref(), 38.0);
+    }
+
+    #[test]
+    fn test_effective_min_max() {
+        let brightness = Characteristic::<i32>::from(brightness::BrightnessCharacteristic::new(1, 1));
+        assert_eq!((brightness.effective_min(), brightness.effective_max()), (Some(0), Some(100)));
+
+        let uint8 = Characteristic::<u8> {
+            format: Format::UInt8,
+            max_value: Some(3),
+            ..Default::default()
+        };
+        assert_eq!((uint8.effective_min(), uint8.effective_max()), (Some(0), Some(3)));
+        let uint16 = Characteristic::<u16> {
+            format: Format::UInt16,
+            ..Default::default()
+        };
+        assert_eq!((uint16.effective_min(), uint16.effective_max()), (Some(0), Some(u16::MAX)));
+        let uint32 = Characteristic::<u32> {
+            format: Format::UInt32,
+            ..Default::default()
+        };
+        assert_eq!((uint32.effective_min(), uint32.effective_max()), (Some(0), Some(u32::MAX)));
+        let uint64 = Characteristic::<u64> {
+            format: Format::UInt64,
+            ..Default::default()
+        };
+        assert_eq!((uint64.effective_min(), uint64.effective_max()), (Some(0), Some(u64::MAX)));
+        let int32 = Characteristic::<i32> {
+            format: Format::Int32,
+            ..Default::default()
+        };
+        assert_eq!((int32.effective_min(), int32.effective_max()), (Some(i32::MIN), Some(i32::MAX)));
+        let float = Characteristic::<f32> {
+            format: Format::Float,
+            min_value: Some(-40.0),
+            ..Default::default()
+        };
+        assert_eq!((float.effective_min(), float.effective_max()), (Some(-40.0), Some(f32::MAX)));
+
+        let string = Characteristic::<String> {
+            format: Format::String,
+            ..Default::default()
+        };
+        assert_eq!((string.effective_min(), string.effective_max()), (None, None));
+    }
+
+    #[test]
+    fn test_to_canonical_json() {
         let characteristic = Characteristic::<u16> {
             id: 1,
             accessory_id: 1,
@@ -425,26 +5088,623 @@ mod tests {
             perms: vec![Perm::PairedRead, Perm::Events],
             description: Some("Acme Tilt Angle".into()),
             event_notifications: Some(true),
-
+            linked_iids: vec![2],
             value: 123,
             unit: Some(Unit::ArcDegrees),
-
             max_value: Some(360),
             min_value: Some(0),
             step_value: Some(1),
-            max_len: None,
-            max_data_len: None,
-            valid_values: None,
+            max_len: Some(3),
             valid_values_range: Some([0, 360]),
+            ..Default::default()
+        };
 
-            on_read: None,
-            on_update: None,
-            on_read_async: None,
-            on_update_async: None,
+        let canonical = "{\"description\":\"Acme Tilt Angle\",\"ev\":true,\"format\":\"uint16\",\"iid\":1,\"linked\":[2],\"maxLen\":3,\"maxValue\":360,\"minStep\":1,\"minValue\":0,\"perms\":[\"pr\",\"ev\"],\"type\":\"C1\",\"unit\":\"arcdegrees\",\"valid-values-range\":[0,360],\"value\":123}";
+        assert_eq!(characteristic.to_canonical_json().unwrap(), canonical);
+    }
 
-            event_emitter: None,
+    #[test]
+    fn test_history_csv() {
+        let clock = MockClock::new();
+        let mut temperature = Characteristic::<f32> {
+            format: Format::Float,
+            ..Default::default()
         };
-        let json = serde_json::to_string(&characteristic).unwrap();
-        assert_eq!(json, "{\"iid\":1,\"type\":\"C1\",\"format\":\"uint16\",\"perms\":[\"pr\",\"ev\"],\"description\":\"Acme Tilt Angle\",\"ev\":true,\"value\":123,\"unit\":\"arcdegrees\",\"maxValue\":360,\"minValue\":0,\"minStep\":1,\"valid-values-range\":[0,360]}".to_string());
+        temperature.set_clock(Some(clock.clone()));
+        temperature.set_history_capacity(3);
+        assert_eq!(temperature.history_csv(), "timestamp,value\n");
+
+        for val in &[20.5, 21.0, 21.25, 22.0] {
+            executor::block_on(temperature.set_value(*val)).unwrap();
+            clock.advance(Duration::from_millis(1500));
+        }
+        assert_eq!(temperature.history_csv(), "timestamp,value\n0.000,21.0\n1.500,21.25\n3.000,22.0\n");
+
+        let mut name = Characteristic::<String> {
+            format: Format::String,
+            ..Default::default()
+        };
+        name.set_history_capacity(1);
+        executor::block_on(name.set_value("Living Room, North".into())).unwrap();
+        assert_eq!(name.history_csv(), "timestamp,value\n0.000,\"\"\"Living Room, North\"\"\"\n");
+    }
+
+    #[test]
+    fn test_write_interceptor() {
+        // the interceptor is global, so only writes to this test's accessory are recorded
+        let writes = Arc::new(StdMutex::new(Vec::new()));
+        let w = writes.clone();
+        set_write_interceptor(Some(move |aid: u64, iid: u64, value: &serde_json::Value| {
+            if aid == 176 {
+                w.lock().unwrap().push((iid, value.clone()));
+            }
+        }));
+
+        let mut on: Box<dyn HapCharacteristic> = Box::new(on::OnCharacteristic::new(1, 176));
+        let mut brightness: Box<dyn HapCharacteristic> = Box::new(brightness::BrightnessCharacteristic::new(2, 176));
+        executor::block_on(on.set_value(json!(true))).unwrap();
+        executor::block_on(brightness.set_value(json!(50))).unwrap();
+        assert!(executor::block_on(brightness.set_value(json!(150))).is_err());
+        set_write_interceptor(None::<fn(u64, u64, &serde_json::Value)>);
+        executor::block_on(on.set_value(json!(false))).unwrap();
+
+        assert_eq!(*writes.lock().unwrap(), vec![(1, json!(true)), (2, json!(50)), (2, json!(150))]);
+    }
+
+    #[test]
+    fn test_compare_and_set() {
+        let mut characteristic = Characteristic::<i32> {
+            hap_type: HapType::Brightness,
+            format: Format::Int32,
+            max_value: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(characteristic.version(), 0);
+
+        executor::block_on(characteristic.set_value(10)).unwrap();
+        let version = characteristic.version();
+        assert_eq!(version, 1);
+        executor::block_on(characteristic.compare_and_set(version, 20)).unwrap();
+        assert_eq!(characteristic.version(), 2);
+
+        // a writer that read the value before the last write is stale
+        assert!(matches!(
+            executor::block_on(characteristic.compare_and_set(version, 30)),
+            Err(Error::VersionMismatch { expected: 1, actual: 2 })
+        ));
+        assert_eq!(*characteristic.value_ref(), 20);
+
+        // rejected values don't bump the version
+        assert!(executor::block_on(characteristic.compare_and_set(2, 101)).is_err());
+        assert_eq!(characteristic.version(), 2);
+    }
+
+    #[test]
+    fn test_debug_summary() {
+        let mut brightness = Characteristic::<i32>::from(brightness::BrightnessCharacteristic::new(1, 1));
+        executor::block_on(brightness.set_value(42)).unwrap();
+        assert_eq!(
+            brightness.debug_summary(),
+            "Brightness (int32, [\"pr\",\"pw\",\"ev\"]) = 42, callbacks: []"
+        );
+
+        brightness.on_update(Some(|_: &i32, _: &i32| {}));
+        brightness.on_committed(Some(|_: &i32| {}));
+        assert!(brightness.debug_summary().ends_with("= 42, callbacks: [on_update, on_committed]"));
+    }
+
+    #[test]
+    fn test_characteristic_macro() {
+        let on = crate::characteristic! { id: 9, accessory_id: 1, type: On, format: Bool, perms: [pr, pw, ev] }.unwrap();
+        let hand_built = Characteristic::<bool> {
+            id: 9,
+            accessory_id: 1,
+            hap_type: HapType::On,
+            format: Format::Bool,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            ..Default::default()
+        };
+        assert_eq!(serde_json::to_string(&on).unwrap(), serde_json::to_string(&hand_built).unwrap());
+        assert_eq!(
+            serde_json::to_string(&on).unwrap(),
+            serde_json::to_string(&on::OnCharacteristic::new(9, 1)).unwrap()
+        );
+
+        let name = crate::characteristic! { type: Name, format: String, perms: [pr] }.unwrap();
+        assert_eq!((name.get_id(), name.get_format()), (0, Format::String));
+
+        assert!(matches!(
+            Characteristic::<bool>::new(1, 1, HapType::Brightness, Format::Int32, vec![Perm::PairedRead]),
+            Err(Error::InvalidValue(Format::Int32))
+        ));
+    }
+
+    #[test]
+    fn test_first_value_marker() {
+        let firsts = Arc::new(StdMutex::new(Vec::new()));
+        let mut event_emitter = EventEmitter::new();
+        let f = firsts.clone();
+        event_emitter.add_listener(Box::new(move |event| {
+            if let Event::CharacteristicValueChanged { first, .. } = event {
+                f.lock().unwrap().push(*first);
+            }
+            async {}.boxed()
+        }));
+        let clock = MockClock::new();
+        let mut characteristic = Characteristic::<bool> {
+            hap_type: HapType::MotionDetected,
+            format: Format::Bool,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            event_notifications: Some(true),
+            event_emitters: vec![Arc::new(Mutex::new(event_emitter))],
+            ..Default::default()
+        };
+        characteristic.set_clock(Some(clock.clone()));
+
+        executor::block_on(characteristic.set_value(true)).unwrap();
+        executor::block_on(characteristic.set_value(false)).unwrap();
+        executor::block_on(characteristic.emit_current());
+        assert_eq!(*firsts.lock().unwrap(), vec![true, false, false]);
+
+        characteristic.set_auto_reset(Some(Duration::from_secs(10)));
+        executor::block_on(characteristic.set_value(true)).unwrap();
+        clock.advance(Duration::from_secs(10));
+        assert!(executor::block_on(characteristic.reset_if_expired()).unwrap());
+        executor::block_on(characteristic.set_value(true)).unwrap();
+        assert_eq!(*firsts.lock().unwrap(), vec![true, false, false, false, false, true]);
+    }
+
+    #[test]
+    fn test_reject_hidden_writes() {
+        let mut characteristic = Characteristic::<u8> {
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Hidden],
+            ..Default::default()
+        };
+        executor::block_on(characteristic.set_json_value(json!(1))).unwrap();
+
+        characteristic.set_reject_hidden_writes(true).unwrap();
+        assert!(matches!(
+            executor::block_on(characteristic.set_json_value(json!(2))),
+            Err(Error::HiddenCharacteristic)
+        ));
+        assert_eq!(*characteristic.value_ref(), 1);
+        executor::block_on(characteristic.set_value(3)).unwrap();
+        assert_eq!(*characteristic.value_ref(), 3);
+
+        // the policy only applies to hidden Characteristics
+        let mut visible = Characteristic::<u8> {
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            ..Default::default()
+        };
+        visible.set_reject_hidden_writes(true).unwrap();
+        executor::block_on(visible.set_json_value(json!(2))).unwrap();
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let brightness = Characteristic::<i32>::from(brightness::BrightnessCharacteristic::new(1, 1));
+        assert_eq!(
+            brightness.json_schema(),
+            json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "type": "integer",
+                "minimum": 0,
+                "maximum": 100,
+                "multipleOf": 1,
+            })
+        );
+
+        let target_state = Characteristic::<u8>::from(
+            target_heating_cooling_state::TargetHeatingCoolingStateCharacteristic::new(1, 1),
+        );
+        assert_eq!(
+            target_state.json_schema(),
+            json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "type": "integer",
+                "minimum": 0,
+                "maximum": 255,
+                "enum": [0, 1, 2, 3],
+            })
+        );
+
+        let temperature = Characteristic::<f32> {
+            format: Format::Float,
+            description: Some("Target".into()),
+            min_value: Some(10.0),
+            max_value: Some(38.0),
+            step_value: Some(0.5),
+            ..Default::default()
+        };
+        assert_eq!(
+            temperature.json_schema(),
+            json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "description": "Target",
+                "type": "number",
+                "minimum": 10.0,
+                "maximum": 38.0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_closed_event_emitter() {
+        let (sender, receiver) = mpsc::channel(1);
+        let mut event_emitter = EventEmitter::new();
+        event_emitter.add_sender(sender);
+        let event_emitter = Arc::new(Mutex::new(event_emitter));
+        let mut characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            event_notifications: Some(true),
+            event_emitters: vec![event_emitter.clone()],
+            ..Default::default()
+        };
+
+        drop(receiver);
+        executor::block_on(characteristic.set_value(1)).unwrap();
+        assert!(executor::block_on(event_emitter.lock()).is_closed());
+        executor::block_on(characteristic.set_value(2)).unwrap();
+        assert_eq!(characteristic.value_ref(), &2);
+        assert_eq!(characteristic.event_emitters.len(), 1);
+
+        let (other_event_emitter, values) = recording_event_emitter();
+        characteristic.add_event_emitter(other_event_emitter);
+        characteristic.set_drop_closed_event_emitters(true);
+        executor::block_on(characteristic.set_value(3)).unwrap();
+        assert_eq!(characteristic.value_ref(), &3);
+        assert_eq!(characteristic.event_emitters.len(), 1);
+        assert_eq!(values.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_default_unit() {
+        assert_eq!(default_unit(HapType::CurrentTemperature), Some(Unit::Celsius));
+        assert_eq!(default_unit(HapType::Brightness), Some(Unit::Percentage));
+        assert_eq!(default_unit(HapType::Hue), Some(Unit::ArcDegrees));
+        assert_eq!(default_unit(HapType::CurrentAmbientLightLevel), Some(Unit::Lux));
+        assert_eq!(default_unit(HapType::On), None);
+        assert_eq!(default_unit(HapType::CurrentMediaState), None);
+
+        let temperature = crate::characteristic! { type: CurrentTemperature, format: Float, perms: [pr, ev] }.unwrap();
+        assert_eq!(temperature.get_unit(), Some(Unit::Celsius));
+        assert_eq!(
+            temperature.get_unit(),
+            current_temperature::CurrentTemperatureCharacteristic::new(1, 1).get_unit()
+        );
+        let name = crate::characteristic! { type: Name, format: String, perms: [pr] }.unwrap();
+        assert_eq!(name.get_unit(), None);
+    }
+
+    #[test]
+    fn test_error_events() {
+        let errors = Arc::new(StdMutex::new(Vec::new()));
+        let mut event_emitter = EventEmitter::new();
+        let e = errors.clone();
+        event_emitter.add_listener(Box::new(move |event| {
+            if let Event::CharacteristicError { aid, iid, kind } = event {
+                e.lock().unwrap().push((*aid, *iid, kind.clone()));
+            }
+            async {}.boxed()
+        }));
+        let mut characteristic = Characteristic::<u8> {
+            id: 9,
+            accessory_id: 1,
+            hap_type: HapType::BatteryLevel,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            min_value: Some(0),
+            max_value: Some(100),
+            event_emitters: vec![Arc::new(Mutex::new(event_emitter))],
+            ..Default::default()
+        };
+
+        assert!(executor::block_on(characteristic.set_value(101)).is_err());
+        assert!(errors.lock().unwrap().is_empty());
+
+        characteristic.set_error_events(true);
+        assert!(matches!(
+            executor::block_on(characteristic.set_value(101)),
+            Err(Error::ValueAboveMaxValue)
+        ));
+        executor::block_on(characteristic.set_value(50)).unwrap();
+        assert_eq!(*errors.lock().unwrap(), vec![(1, 9, "ValueAboveMaxValue".to_string())]);
+    }
+
+    #[test]
+    fn test_get_value_cow() {
+        let mut characteristic = Characteristic::<String> {
+            hap_type: HapType::Name,
+            format: Format::String,
+            perms: vec![Perm::PairedRead],
+            value: "Lamp".into(),
+            ..Default::default()
+        };
+        let value = executor::block_on(characteristic.get_value_cow()).unwrap();
+        assert!(matches!(value, Cow::Borrowed(v) if v == "Lamp"));
+
+        characteristic.on_read(Some(|| Some("Desk Lamp".to_string())));
+        let value = executor::block_on(characteristic.get_value_cow()).unwrap();
+        assert!(matches!(value, Cow::Owned(ref v) if v == "Desk Lamp"));
+        assert_eq!(characteristic.value_ref(), "Desk Lamp");
+
+        characteristic.on_read(Some(|| None));
+        let value = executor::block_on(characteristic.get_value_cow()).unwrap();
+        assert!(matches!(value, Cow::Borrowed(v) if v == "Desk Lamp"));
+    }
+
+    #[test]
+    fn test_encryption_at_rest() {
+        let mut characteristic = Characteristic::<Vec<u8>> {
+            hap_type: HapType::LockControlPoint,
+            format: Format::Tlv8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            value: vec![1, 2, 3],
+            ..Default::default()
+        };
+        characteristic.set_encryption_key(Some([7; 32])).unwrap();
+        assert!(characteristic.is_encrypted());
+        assert!(characteristic.value_ref().is_empty());
+        assert_eq!(executor::block_on(characteristic.get_value()).unwrap(), vec![1, 2, 3]);
+
+        let secret = b"secret".to_vec();
+        executor::block_on(characteristic.set_value(secret.clone())).unwrap();
+        assert!(characteristic.value_ref().is_empty());
+        let sealed = &characteristic.encryption.as_ref().unwrap().sealed;
+        assert!(!sealed.windows(secret.len()).any(|w| w == secret.as_slice()));
+        assert!(!serde_json::to_string(&characteristic).unwrap().contains("value"));
+        assert_eq!(executor::block_on(characteristic.get_value()).unwrap(), secret);
+        assert!(matches!(
+            executor::block_on(characteristic.get_value_cow()).unwrap(),
+            Cow::Owned(ref v) if v == &secret
+        ));
+
+        characteristic.set_encryption_key(None).unwrap();
+        assert!(!characteristic.is_encrypted());
+        assert_eq!(characteristic.value_ref(), &secret);
+    }
+
+    #[test]
+    fn test_auto_expand_bounds() {
+        let (event_emitter, structure_changes) = structure_event_emitter();
+        let mut characteristic = Characteristic::<f32> {
+            hap_type: HapType::CurrentTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            min_value: Some(0.0),
+            max_value: Some(100.0),
+            ..Default::default()
+        };
+        characteristic.add_event_emitter(event_emitter);
+
+        assert!(matches!(
+            executor::block_on(characteristic.set_value(150.0)),
+            Err(Error::ValueAboveMaxValue)
+        ));
+
+        characteristic.set_bounds_mode(BoundsMode::AutoExpand).unwrap();
+        executor::block_on(characteristic.set_value(50.0)).unwrap();
+        assert!(!executor::block_on(characteristic.tick()).unwrap());
+        executor::block_on(characteristic.set_value(150.0)).unwrap();
+        assert_eq!(characteristic.get_max_value(), Some(250.0));
+        assert_eq!(characteristic.value_ref(), &150.0);
+        executor::block_on(characteristic.set_value(200.0)).unwrap();
+        assert_eq!(*structure_changes.lock().unwrap(), 0);
+        assert!(executor::block_on(characteristic.tick()).unwrap());
+        assert_eq!(*structure_changes.lock().unwrap(), 1);
+
+        executor::block_on(characteristic.set_value(-10.0)).unwrap();
+        assert_eq!(characteristic.get_min_value(), Some(-260.0));
+        executor::block_on(characteristic.get_value()).unwrap();
+        assert_eq!(*structure_changes.lock().unwrap(), 2);
+        assert!(!executor::block_on(characteristic.tick()).unwrap());
+    }
+
+    #[test]
+    fn test_auto_expand_integer_bounds() {
+        let mut characteristic = Characteristic::<u8> {
+            hap_type: HapType::CurrentPosition,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            min_value: Some(0),
+            max_value: Some(100),
+            ..Default::default()
+        };
+        characteristic.set_bounds_mode(BoundsMode::AutoExpand).unwrap();
+
+        executor::block_on(characteristic.set_value(120)).unwrap();
+        assert_eq!(characteristic.get_max_value(), Some(220));
+        executor::block_on(characteristic.set_value(230)).unwrap();
+        assert_eq!(characteristic.get_max_value(), Some(u8::MAX));
+        assert_eq!(characteristic.get_min_value(), Some(0));
+    }
+
+    #[test]
+    fn test_reachability() {
+        let mut characteristic = brightness::BrightnessCharacteristic::new(1, 1);
+        executor::block_on(HapCharacteristic::set_value(&mut characteristic, json!(40))).unwrap();
+        assert!(characteristic.is_reachable());
+        assert_eq!(executor::block_on(HapCharacteristic::get_value(&mut characteristic)).unwrap(), json!(40));
+
+        characteristic.set_reachable(false);
+        assert!(!characteristic.is_reachable());
+        assert!(matches!(
+            executor::block_on(HapCharacteristic::get_value(&mut characteristic)),
+            Err(Error::Unreachable)
+        ));
+        assert!(matches!(
+            executor::block_on(HapCharacteristic::set_value(&mut characteristic, json!(60))),
+            Err(Error::Unreachable)
+        ));
+        assert_eq!(characteristic.get_cached_value(), json!(40));
+
+        characteristic.set_reachable(true);
+        assert_eq!(executor::block_on(HapCharacteristic::get_value(&mut characteristic)).unwrap(), json!(40));
+    }
+
+    #[test]
+    fn test_bool_encoding() {
+        let mut characteristic = on::OnCharacteristic::new(9, 1);
+        executor::block_on(HapCharacteristic::set_value(&mut characteristic, json!(1))).unwrap();
+        assert_eq!(executor::block_on(HapCharacteristic::get_value(&mut characteristic)).unwrap(), json!(true));
+        assert_eq!(serde_json::to_value(&characteristic).unwrap()["value"], json!(true));
+
+        let mut characteristic = Characteristic::<bool>::from(characteristic);
+        characteristic.set_bool_encoding(BoolEncoding::Integer);
+        assert_eq!(executor::block_on(characteristic.get_json_value()).unwrap(), json!(1));
+        assert_eq!(characteristic.get_cached_value(), json!(1));
+        assert_eq!(serde_json::to_value(&characteristic).unwrap()["value"], json!(1));
+        executor::block_on(characteristic.set_json_value(json!(false))).unwrap();
+        assert_eq!(executor::block_on(characteristic.get_json_value()).unwrap(), json!(0));
+    }
+
+    #[test]
+    fn test_write_dedup_window() {
+        let updates = Arc::new(StdMutex::new(0));
+        let clock = MockClock::new();
+        let mut characteristic = Characteristic::<bool> {
+            hap_type: HapType::On,
+            format: Format::Bool,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            ..Default::default()
+        };
+        characteristic.set_clock(Some(clock.clone()));
+        let u = updates.clone();
+        characteristic.on_update(Some(move |_: &bool, _: &bool| *u.lock().unwrap() += 1));
+
+        // without a window, every write is processed
+        executor::block_on(characteristic.set_json_value(json!(true))).unwrap();
+        executor::block_on(characteristic.set_json_value(json!(true))).unwrap();
+        assert_eq!(*updates.lock().unwrap(), 2);
+
+        characteristic.set_write_dedup_window(Some(Duration::from_millis(500)));
+        executor::block_on(characteristic.set_json_value(json!(false))).unwrap();
+        clock.advance(Duration::from_millis(50));
+        executor::block_on(characteristic.set_json_value(json!(false))).unwrap();
+        assert_eq!(*updates.lock().unwrap(), 3);
+        assert!(!characteristic.value_ref());
+
+        executor::block_on(characteristic.set_json_value(json!(true))).unwrap();
+        assert_eq!(*updates.lock().unwrap(), 4);
+        clock.advance(Duration::from_millis(500));
+        executor::block_on(characteristic.set_json_value(json!(true))).unwrap();
+        assert_eq!(*updates.lock().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_read_cache_ttl() {
+        let reads = Arc::new(StdMutex::new(0));
+        let clock = MockClock::new();
+        let mut characteristic = Characteristic::<f32> {
+            hap_type: HapType::CurrentTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            ..Default::default()
+        };
+        characteristic.set_clock(Some(clock.clone()));
+        let r = reads.clone();
+        characteristic.on_read(Some(move || {
+            let mut reads = r.lock().unwrap();
+            *reads += 1;
+            Some(*reads as f32)
+        }));
+
+        // without a TTL, every read invokes the callback
+        executor::block_on(characteristic.get_value()).unwrap();
+        executor::block_on(characteristic.get_value()).unwrap();
+        assert_eq!(*reads.lock().unwrap(), 2);
+
+        characteristic.set_read_cache_ttl(Some(Duration::from_secs(5)));
+        assert_eq!(executor::block_on(characteristic.get_value()).unwrap(), 2.0);
+        clock.advance(Duration::from_secs(4));
+        assert_eq!(executor::block_on(characteristic.get_value()).unwrap(), 2.0);
+        assert_eq!(*reads.lock().unwrap(), 2);
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(executor::block_on(characteristic.get_value()).unwrap(), 3.0);
+        assert_eq!(*reads.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_numeric_constraints() {
+        let mut characteristic = Characteristic::<u8> {
+            hap_type: HapType::Brightness,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            ..Default::default()
+        };
+
+        characteristic
+            .set_numeric_constraints(NumericConstraints {
+                min_value: Some(0),
+                max_value: Some(100),
+                step_value: Some(25),
+                unit: Some(Unit::Percentage),
+                valid_values: Some(vec![0, 50, 100]),
+            })
+            .unwrap();
+        assert_eq!(characteristic.get_range(), Some(0..=100));
+        assert_eq!(characteristic.get_step_value(), Some(25));
+        assert_eq!(characteristic.get_unit(), Some(Unit::Percentage));
+        assert!(executor::block_on(characteristic.set_value(50)).is_ok());
+        assert!(executor::block_on(characteristic.set_value(25)).is_err());
+
+        let conflicting = [
+            (Some(0), Some(100), Some(0), None),
+            (Some(100), Some(0), Some(1), None),
+            (Some(0), Some(100), Some(1), Some(vec![50, 150])),
+        ];
+        for (min_value, max_value, step_value, valid_values) in conflicting {
+            let result = characteristic.set_numeric_constraints(NumericConstraints {
+                min_value,
+                max_value,
+                step_value,
+                unit: None,
+                valid_values,
+            });
+            assert!(result.is_err());
+        }
+        assert!(matches!(
+            characteristic.set_numeric_constraints(NumericConstraints {
+                step_value: Some(0),
+                ..Default::default()
+            }),
+            Err(Error::InvalidStepValue)
+        ));
+        // rejected combinations leave the constraints unchanged
+        assert_eq!(characteristic.get_step_value(), Some(25));
+        assert_eq!(characteristic.get_unit(), Some(Unit::Percentage));
+    }
+
+    #[test]
+    fn test_tick() {
+        let updates = Arc::new(StdMutex::new(Vec::new()));
+        let clock = MockClock::new();
+        let mut characteristic = Characteristic::<i32> {
+            hap_type: HapType::Brightness,
+            format: Format::Int32,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            value: 10,
+            ..Default::default()
+        };
+        characteristic.set_clock(Some(clock.clone()));
+        characteristic.set_write_coalescing(Some(Duration::from_millis(300)));
+        let u = updates.clone();
+        characteristic.on_update(Some(move |old: &i32, new: &i32| u.lock().unwrap().push((*old, *new))));
+
+        executor::block_on(characteristic.set_value(20)).unwrap();
+        executor::block_on(characteristic.set_value(30)).unwrap();
+        assert!(!executor::block_on(characteristic.tick()).unwrap());
+        assert!(updates.lock().unwrap().is_empty());
+
+        clock.advance(Duration::from_millis(300));
+        assert!(executor::block_on(characteristic.tick()).unwrap());
+        assert_eq!(*updates.lock().unwrap(), vec![(10, 30)]);
     }
 }
@@ -0,0 +1,506 @@
+use std::{
+    fmt,
+    io::{Cursor, Read},
+    str::FromStr,
+    time::Duration,
+};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    characteristic::{Characteristic, Format, Perm, Sanitization, TypeFormat, Unit},
+    Error,
+    HapType,
+    Result,
+};
+
+impl<T: fmt::Debug + Default + Clone + Serialize + Send + Sync> Characteristic<T>
+where
+    for<'de> T: Deserialize<'de>,
+{
+    /// Encodes the value and metadata of a Characteristic to a compact binary form for persistence, e.g. to flash
+    /// storage. Callbacks, event emitters and the `Clock` of the Characteristic aren't encoded.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+
+        bytes.write_u64::<LittleEndian>(self.id)?;
+        bytes.write_u64::<LittleEndian>(self.accessory_id)?;
+        write_string(&mut bytes, &self.hap_type.to_string())?;
+        bytes.write_u8(type_format_code(self.type_format))?;
+        bytes.write_u8(format_code(self.format))?;
+        bytes.write_u8(self.perms.len() as u8)?;
+        for perm in &self.perms {
+            bytes.write_u8(perm_code(*perm))?;
+        }
+        write_option(&mut bytes, self.description.as_ref(), |b, d| write_string(b, d))?;
+        write_option(&mut bytes, self.event_notifications.as_ref(), |b, ev| {
+            b.write_u8(*ev as u8).map_err(Error::from)
+        })?;
+        bytes.write_u8(self.emit_on_subscribe as u8)?;
+        bytes.write_u32::<LittleEndian>(self.linked_iids.len() as u32)?;
+        for iid in &self.linked_iids {
+            bytes.write_u64::<LittleEndian>(*iid)?;
+        }
+
+        self.write_value(&mut bytes, &self.value)?;
+        write_option(&mut bytes, self.unit.as_ref(), |b, u| b.write_u8(unit_code(*u)).map_err(Error::from))?;
+
+        write_option(&mut bytes, self.max_value.as_ref(), |b, v| self.write_value(b, v))?;
+        write_option(&mut bytes, self.min_value.as_ref(), |b, v| self.write_value(b, v))?;
+        write_option(&mut bytes, self.step_value.as_ref(), |b, v| self.write_value(b, v))?;
+        write_option(&mut bytes, self.advertised_step.as_ref(), |b, v| self.write_value(b, v))?;
+        write_option(&mut bytes, self.max_len.as_ref(), |b, l| {
+            b.write_u16::<LittleEndian>(*l).map_err(Error::from)
+        })?;
+        write_option(&mut bytes, self.max_data_len.as_ref(), |b, l| {
+            b.write_u32::<LittleEndian>(*l).map_err(Error::from)
+        })?;
+        write_option(&mut bytes, self.valid_values.as_ref(), |b, values| {
+            b.write_u32::<LittleEndian>(values.len() as u32)?;
+            for value in values {
+                self.write_value(b, value)?;
+            }
+            Ok(())
+        })?;
+        write_option(&mut bytes, self.valid_values_range.as_ref(), |b, [start, end]| {
+            self.write_value(b, start)?;
+            self.write_value(b, end)
+        })?;
+        write_option(&mut bytes, self.precision.as_ref(), |b, p| b.write_u8(*p).map_err(Error::from))?;
+        bytes.write_u8(sanitization_code(self.sanitization))?;
+        bytes.write_u8(self.writes_locked as u8)?;
+        write_option(&mut bytes, self.auto_reset.as_ref(), |b, d| {
+            b.write_u64::<LittleEndian>(d.as_millis() as u64).map_err(Error::from)
+        })?;
+
+        Ok(bytes)
+    }
+
+    /// Restores a Characteristic from the binary form produced by `to_bytes`. The restored Characteristic has no
+    /// callbacks or event emitters set.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut cursor = Cursor::new(bytes);
+        let c = &mut cursor;
+
+        let id = c.read_u64::<LittleEndian>()?;
+        let accessory_id = c.read_u64::<LittleEndian>()?;
+        let hap_type = HapType::from_str(&read_string(c)?)?;
+        let type_format = type_format_from_code(c.read_u8()?)?;
+        let format = format_from_code(c.read_u8()?)?;
+        let mut perms = Vec::new();
+        for _ in 0..c.read_u8()? {
+            perms.push(perm_from_code(c.read_u8()?)?);
+        }
+        let description = read_option(c, read_string)?;
+        let event_notifications = read_option(c, |c| Ok(c.read_u8()? != 0))?;
+        let emit_on_subscribe = c.read_u8()? != 0;
+        let mut linked_iids = Vec::new();
+        for _ in 0..c.read_u32::<LittleEndian>()? {
+            linked_iids.push(c.read_u64::<LittleEndian>()?);
+        }
+
+        let value = read_value(c, format)?;
+        let unit = read_option(c, |c| unit_from_code(c.read_u8()?))?;
+
+        let max_value = read_option(c, |c| read_value(c, format))?;
+        let min_value = read_option(c, |c| read_value(c, format))?;
+        let step_value = read_option(c, |c| read_value(c, format))?;
+        let advertised_step = read_option(c, |c| read_value(c, format))?;
+        let max_len = read_option(c, |c| Ok(c.read_u16::<LittleEndian>()?))?;
+        let max_data_len = read_option(c, |c| Ok(c.read_u32::<LittleEndian>()?))?;
+        let valid_values = read_option(c, |c| {
+            let mut values = Vec::new();
+            for _ in 0..c.read_u32::<LittleEndian>()? {
+                values.push(read_value(c, format)?);
+            }
+            Ok(values)
+        })?;
+        let valid_values_range = read_option(c, |c| Ok([read_value(c, format)?, read_value(c, format)?]))?;
+        let precision = read_option(c, |c| Ok(c.read_u8()?))?;
+        let sanitization = sanitization_from_code(c.read_u8()?)?;
+        let writes_locked = c.read_u8()? != 0;
+        let auto_reset = read_option(c, |c| Ok(Duration::from_millis(c.read_u64::<LittleEndian>()?)))?;
+
+        if (c.position() as usize) != bytes.len() {
+            return Err(Error::InvalidCharacteristicBytes);
+        }
+
+        Ok(Self {
+            id,
+            accessory_id,
+            hap_type,
+            type_format,
+            format,
+            perms,
+            description,
+            event_notifications,
+            emit_on_subscribe,
+            linked_iids,
+            value,
+            unit,
+            max_value,
+            min_value,
+            step_value,
+            advertised_step,
+            max_len,
+            max_data_len,
+            valid_values,
+            valid_values_range,
+            precision,
+            sanitization,
+            writes_locked,
+            auto_reset,
+            ..Default::default()
+        })
+    }
+
+    /// Encodes a value as the little-endian representation of the `Format` of the Characteristic. Strings, TLV8 and
+    /// data values are prefixed with their length.
+    fn write_value(&self, bytes: &mut Vec<u8>, value: &T) -> Result<()> {
+        let value = json!(value);
+        let invalid = || Error::InvalidValue(self.format);
+        match self.format {
+            Format::Bool => bytes.write_u8(value.as_bool().ok_or_else(invalid)? as u8)?,
+            Format::UInt8 => bytes.write_u8(value.as_u64().ok_or_else(invalid)? as u8)?,
+            Format::UInt16 => bytes.write_u16::<LittleEndian>(value.as_u64().ok_or_else(invalid)? as u16)?,
+            Format::UInt32 => bytes.write_u32::<LittleEndian>(value.as_u64().ok_or_else(invalid)? as u32)?,
+            Format::UInt64 => bytes.write_u64::<LittleEndian>(value.as_u64().ok_or_else(invalid)?)?,
+            Format::Int32 => bytes.write_i32::<LittleEndian>(value.as_i64().ok_or_else(invalid)? as i32)?,
+            Format::Float => bytes.write_f32::<LittleEndian>(value.as_f64().ok_or_else(invalid)? as f32)?,
+            Format::String => write_string(bytes, value.as_str().ok_or_else(invalid)?)?,
+            Format::Tlv8 | Format::Data => {
+                let data: Vec<u8> = serde_json::from_value(value).map_err(|_| invalid())?;
+                bytes.write_u32::<LittleEndian>(data.len() as u32)?;
+                bytes.extend(data);
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// Decodes a value encoded by `Characteristic::write_value`.
+fn read_value<T>(c: &mut Cursor<&[u8]>, format: Format) -> Result<T>
+where
+    for<'de> T: Deserialize<'de>,
+{
+    let value = match format {
+        Format::Bool => json!(c.read_u8()? != 0),
+        Format::UInt8 => json!(c.read_u8()?),
+        Format::UInt16 => json!(c.read_u16::<LittleEndian>()?),
+        Format::UInt32 => json!(c.read_u32::<LittleEndian>()?),
+        Format::UInt64 => json!(c.read_u64::<LittleEndian>()?),
+        Format::Int32 => json!(c.read_i32::<LittleEndian>()?),
+        Format::Float => json!(c.read_f32::<LittleEndian>()?),
+        Format::String => json!(read_string(c)?),
+        Format::Tlv8 | Format::Data => json!(read_data(c)?),
+    };
+
+    serde_json::from_value(value).map_err(|_| Error::InvalidValue(format))
+}
+
+fn write_string(bytes: &mut Vec<u8>, s: &str) -> Result<()> {
+    bytes.write_u32::<LittleEndian>(s.len() as u32)?;
+    bytes.extend(s.as_bytes());
+    Ok(())
+}
+
+fn read_string(c: &mut Cursor<&[u8]>) -> Result<String> {
+    let data = read_data(c)?;
+    Ok(std::str::from_utf8(&data)?.to_owned())
+}
+
+fn read_data(c: &mut Cursor<&[u8]>) -> Result<Vec<u8>> {
+    let len = c.read_u32::<LittleEndian>()? as usize;
+    if len > c.get_ref().len() - c.position() as usize {
+        return Err(Error::InvalidCharacteristicBytes);
+    }
+    let mut data = vec![0; len];
+    c.read_exact(&mut data)?;
+    Ok(data)
+}
+
+fn write_option<V>(
+    bytes: &mut Vec<u8>,
+    option: Option<&V>,
+    write: impl FnOnce(&mut Vec<u8>, &V) -> Result<()>,
+) -> Result<()> {
+    match option {
+        Some(v) => {
+            bytes.write_u8(1)?;
+            write(bytes, v)
+        },
+        None => Ok(bytes.write_u8(0)?),
+    }
+}
+
+fn read_option<V>(
+    c: &mut Cursor<&[u8]>,
+    read: impl FnOnce(&mut Cursor<&[u8]>) -> Result<V>,
+) -> Result<Option<V>> {
+    match c.read_u8()? {
+        0 => Ok(None),
+        1 => Ok(Some(read(c)?)),
+        _ => Err(Error::InvalidCharacteristicBytes),
+    }
+}
+
+fn format_code(format: Format) -> u8 {
+    match format {
+        Format::String => 0,
+        Format::Bool => 1,
+        Format::Float => 2,
+        Format::UInt8 => 3,
+        Format::UInt16 => 4,
+        Format::UInt32 => 5,
+        Format::UInt64 => 6,
+        Format::Int32 => 7,
+        Format::Tlv8 => 8,
+        Format::Data => 9,
+    }
+}
+
+fn format_from_code(code: u8) -> Result<Format> {
+    match code {
+        0 => Ok(Format::String),
+        1 => Ok(Format::Bool),
+        2 => Ok(Format::Float),
+        3 => Ok(Format::UInt8),
+        4 => Ok(Format::UInt16),
+        5 => Ok(Format::UInt32),
+        6 => Ok(Format::UInt64),
+        7 => Ok(Format::Int32),
+        8 => Ok(Format::Tlv8),
+        9 => Ok(Format::Data),
+        _ => Err(Error::InvalidCharacteristicBytes),
+    }
+}
+
+fn perm_code(perm: Perm) -> u8 {
+    match perm {
+        Perm::PairedRead => 0,
+        Perm::PairedWrite => 1,
+        Perm::Events => 2,
+        Perm::AdditionalAuthorization => 3,
+        Perm::TimedWrite => 4,
+        Perm::Hidden => 5,
+    }
+}
+
+fn perm_from_code(code: u8) -> Result<Perm> {
+    match code {
+        0 => Ok(Perm::PairedRead),
+        1 => Ok(Perm::PairedWrite),
+        2 => Ok(Perm::Events),
+        3 => Ok(Perm::AdditionalAuthorization),
+        4 => Ok(Perm::TimedWrite),
+        5 => Ok(Perm::Hidden),
+        _ => Err(Error::InvalidCharacteristicBytes),
+    }
+}
+
+fn unit_code(unit: Unit) -> u8 {
+    match unit {
+        Unit::Percentage => 0,
+        Unit::ArcDegrees => 1,
+        Unit::Celsius => 2,
+        Unit::Lux => 3,
+        Unit::Seconds => 4,
+    }
+}
+
+fn unit_from_code(code: u8) -> Result<Unit> {
+    match code {
+        0 => Ok(Unit::Percentage),
+        1 => Ok(Unit::ArcDegrees),
+        2 => Ok(Unit::Celsius),
+        3 => Ok(Unit::Lux),
+        4 => Ok(Unit::Seconds),
+        _ => Err(Error::InvalidCharacteristicBytes),
+    }
+}
+
+fn type_format_code(type_format: TypeFormat) -> u8 {
+    match type_format {
+        TypeFormat::Short => 0,
+        TypeFormat::Uuid => 1,
+    }
+}
+
+fn type_format_from_code(code: u8) -> Result<TypeFormat> {
+    match code {
+        0 => Ok(TypeFormat::Short),
+        1 => Ok(TypeFormat::Uuid),
+        _ => Err(Error::InvalidCharacteristicBytes),
+    }
+}
+
+fn sanitization_code(sanitization: Sanitization) -> u8 {
+    match sanitization {
+        Sanitization::Allow => 0,
+        Sanitization::Strip => 1,
+        Sanitization::Reject => 2,
+    }
+}
+
+fn sanitization_from_code(code: u8) -> Result<Sanitization> {
+    match code {
+        0 => Ok(Sanitization::Allow),
+        1 => Ok(Sanitization::Strip),
+        2 => Ok(Sanitization::Reject),
+        _ => Err(Error::InvalidCharacteristicBytes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_round_trip<T>(characteristic: Characteristic<T>)
+    where
+        T: fmt::Debug + Default + Clone + Serialize + Send + Sync,
+        for<'de> T: Deserialize<'de>,
+    {
+        let bytes = characteristic.to_bytes().unwrap();
+        let restored = Characteristic::<T>::from_bytes(&bytes).unwrap();
+        assert_eq!(format!("{:?}", restored), format!("{:?}", characteristic));
+        assert_eq!(json!(&restored), json!(&characteristic));
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        assert_round_trip(Characteristic::<bool> {
+            id: 1,
+            accessory_id: 2,
+            hap_type: HapType::On,
+            format: Format::Bool,
+            perms: vec![Perm::PairedRead, Perm::Events, Perm::PairedWrite],
+            event_notifications: Some(true),
+            value: true,
+            writes_locked: true,
+            auto_reset: Some(Duration::from_millis(1500)),
+            ..Default::default()
+        });
+        assert_round_trip(Characteristic::<u8> {
+            id: 3,
+            accessory_id: 1,
+            hap_type: HapType::TargetHeatingCoolingState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            value: 3,
+            valid_values: Some(vec![0, 1, 3]),
+            ..Default::default()
+        });
+        assert_round_trip(Characteristic::<u16> {
+            id: 4,
+            accessory_id: 1,
+            hap_type: HapType::RotationSpeed,
+            format: Format::UInt16,
+            perms: vec![Perm::PairedRead],
+            value: 1200,
+            valid_values_range: Some([0, 4000]),
+            ..Default::default()
+        });
+        assert_round_trip(Characteristic::<u32> {
+            id: 5,
+            accessory_id: 1,
+            hap_type: HapType::SetDuration,
+            format: Format::UInt32,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite],
+            unit: Some(Unit::Seconds),
+            value: 3600,
+            max_value: Some(3600),
+            min_value: Some(0),
+            linked_iids: vec![6, 7],
+            ..Default::default()
+        });
+        assert_round_trip(Characteristic::<u64> {
+            id: 6,
+            accessory_id: 1,
+            hap_type: HapType::Unknown,
+            format: Format::UInt64,
+            perms: vec![Perm::PairedRead, Perm::Hidden],
+            value: u64::MAX,
+            ..Default::default()
+        });
+        assert_round_trip(Characteristic::<i32> {
+            id: 7,
+            accessory_id: 1,
+            hap_type: HapType::Brightness,
+            type_format: TypeFormat::Uuid,
+            format: Format::Int32,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::Events],
+            unit: Some(Unit::Percentage),
+            value: -5,
+            max_value: Some(100),
+            min_value: Some(-100),
+            step_value: Some(1),
+            advertised_step: Some(5),
+            ..Default::default()
+        });
+        assert_round_trip(Characteristic::<f32> {
+            id: 8,
+            accessory_id: 1,
+            hap_type: HapType::CurrentTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            unit: Some(Unit::Celsius),
+            value: 21.5,
+            max_value: Some(100.0),
+            min_value: Some(0.0),
+            step_value: Some(0.1),
+            precision: Some(1),
+            ..Default::default()
+        });
+        assert_round_trip(Characteristic::<String> {
+            id: 9,
+            accessory_id: 1,
+            hap_type: HapType::Name,
+            format: Format::String,
+            perms: vec![Perm::PairedRead],
+            description: Some("Name".into()),
+            value: "Living Room Lamp".into(),
+            max_len: Some(64),
+            sanitization: Sanitization::Strip,
+            ..Default::default()
+        });
+        assert_round_trip(Characteristic::<Vec<u8>> {
+            id: 10,
+            accessory_id: 1,
+            hap_type: HapType::ProgrammableSwitchEvent,
+            format: Format::Tlv8,
+            perms: vec![Perm::PairedRead, Perm::PairedWrite, Perm::AdditionalAuthorization, Perm::TimedWrite],
+            value: vec![0x01, 0x02, 0xAB, 0xCD],
+            ..Default::default()
+        });
+        assert_round_trip(Characteristic::<Vec<u8>> {
+            id: 11,
+            accessory_id: 1,
+            hap_type: HapType::Identifier,
+            format: Format::Data,
+            perms: vec![Perm::PairedRead],
+            value: vec![0; 300],
+            max_data_len: Some(2048),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_bytes_invalid() {
+        let characteristic = Characteristic::<u8> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentDoorState,
+            format: Format::UInt8,
+            perms: vec![Perm::PairedRead],
+            ..Default::default()
+        };
+        let bytes = characteristic.to_bytes().unwrap();
+
+        assert!(Characteristic::<u8>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Characteristic::<u8>::from_bytes(&[bytes.clone(), vec![0]].concat()).is_err());
+        assert!(Characteristic::<String>::from_bytes(&bytes).is_err());
+    }
+}
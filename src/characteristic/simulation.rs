@@ -0,0 +1,135 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use crate::characteristic::{Clock, SystemClock};
+
+/// Scripted source of the values of a Characteristic for testing accessories without real hardware. The script is a
+/// sequence of `(delay, value)` pairs; each value becomes current once its delay has passed after the previous one, so
+/// the timeline starts when the `SimulationSource` is created. After the last value, the script either starts over or
+/// keeps returning the last value. Attach it to a Characteristic with `Characteristic::set_simulation_source`.
+pub struct SimulationSource<T> {
+    script: Vec<(Duration, T)>,
+    looping: bool,
+    clock: Box<dyn Clock>,
+    started: Instant,
+}
+
+impl<T: Clone> SimulationSource<T> {
+    /// Creates a new `SimulationSource` replaying `script` once on the system clock.
+    pub fn new(script: Vec<(Duration, T)>) -> Self {
+        let clock = SystemClock;
+        SimulationSource {
+            script,
+            looping: false,
+            started: clock.now(),
+            clock: Box::new(clock),
+        }
+    }
+
+    /// Sets whether the script starts over after its last value.
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Replaces the clock driving the timeline, e.g. to control time in tests. The timeline restarts at the current
+    /// time of the new clock.
+    pub fn clock(mut self, clock: impl Clock) -> Self {
+        self.started = clock.now();
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Returns the scripted value for the current point of the timeline. Returns `None` before the delay of the first
+    /// value has passed or if the script is empty.
+    pub fn current(&self) -> Option<T> {
+        let mut elapsed = self.clock.now().saturating_duration_since(self.started);
+        let total: Duration = self.script.iter().map(|(delay, _)| *delay).sum();
+        if self.looping && !total.is_zero() && elapsed >= total {
+            elapsed = Duration::from_nanos((elapsed.as_nanos() % total.as_nanos()) as u64);
+            if let Some((_, last)) = self.script.last() {
+                if elapsed < self.script[0].0 {
+                    return Some(last.clone());
+                }
+            }
+        }
+
+        let mut at = Duration::ZERO;
+        let mut current = None;
+        for (delay, value) in &self.script {
+            at += *delay;
+            if at > elapsed {
+                break;
+            }
+            current = Some(value);
+        }
+        current.cloned()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SimulationSource<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimulationSource")
+            .field("script", &self.script)
+            .field("looping", &self.looping)
+            .field("started", &self.started)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor;
+
+    use super::*;
+    use crate::{
+        characteristic::{Characteristic, Format, MockClock, Perm},
+        HapType,
+    };
+
+    #[test]
+    fn test_simulation_source() {
+        let clock = MockClock::new();
+        let script = vec![
+            (Duration::from_secs(1), 20.0),
+            (Duration::from_secs(2), 21.5),
+            (Duration::from_secs(3), 23.0),
+        ];
+
+        let source = SimulationSource::new(script.clone()).clock(clock.clone());
+        let mut values = Vec::new();
+        for _ in 0..8 {
+            values.push(source.current());
+            clock.advance(Duration::from_secs(1));
+        }
+        assert_eq!(values, vec![
+            None,
+            Some(20.0),
+            Some(20.0),
+            Some(21.5),
+            Some(21.5),
+            Some(21.5),
+            Some(23.0),
+            Some(23.0),
+        ]);
+
+        let source = SimulationSource::new(script).looping(true).clock(clock.clone());
+        let mut characteristic = Characteristic::<f32> {
+            id: 1,
+            accessory_id: 1,
+            hap_type: HapType::CurrentTemperature,
+            format: Format::Float,
+            perms: vec![Perm::PairedRead, Perm::Events],
+            ..Default::default()
+        };
+        characteristic.set_simulation_source(Some(source));
+        let mut values = Vec::new();
+        for _ in 0..8 {
+            clock.advance(Duration::from_secs(1));
+            values.push(executor::block_on(characteristic.get_value()).unwrap());
+        }
+        assert_eq!(values, vec![20.0, 20.0, 21.5, 21.5, 21.5, 23.0, 20.0, 20.0]);
+    }
+}
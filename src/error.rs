@@ -20,14 +20,56 @@ pub enum Error {
     ValueBelowMinValue,
     #[error("The value is above the `max_value` of the characteristic.")]
     ValueAboveMaxValue,
+    #[error("The value is not a multiple of the `step_value` of the characteristic.")]
+    ValueNotOnStep,
+    #[error("The value is not one of the `valid_values` of the characteristic.")]
+    ValueNotInValidValues,
+    #[error("The value contains control characters.")]
+    ValueContainsControlCharacters,
+    #[error("The value doesn't match the pattern of the characteristic.")]
+    ValueDoesNotMatchPattern,
+    #[error("The `valid_values` and `valid_values_range` of the characteristic are mutually exclusive.")]
+    ConflictingValidValues,
+    #[error("The metadata of the characteristic is frozen.")]
+    MetadataFrozen,
+    #[error("The index {0} is out of bounds of the list value of the characteristic.")]
+    ElementIndexOutOfBounds(usize),
+    #[error("The `min_value` of the characteristic is above its `max_value`.")]
+    InvertedRange,
+    #[error("The `step_value` of the characteristic isn't positive.")]
+    InvalidStepValue,
+    #[error("The `valid_values` of the characteristic aren't within its `min_value` and `max_value`.")]
+    ValidValuesOutOfRange,
+    #[error("A callback of the characteristic panicked.")]
+    CallbackPanicked,
+    #[error("The expected version {expected} of the characteristic doesn't match its current version {actual}.")]
+    VersionMismatch { expected: u64, actual: u64 },
     #[error("The selected accessory is not present on the server.")]
     AccessoryNotFound,
+    #[error("The selected characteristic is not present on the accessory.")]
+    CharacteristicNotFound,
     #[error("The provided accessory was already added to the server.")]
     DuplicateAccessory,
+    #[error("The instance IDs {0:?} are used more than once within the accessory.")]
+    DuplicateInstanceIds(Vec<u64>),
     #[error(
         "The provided value has an invalid data type for the characteristic. The characteristic's format is {0:?}."
     )]
     InvalidValue(Format),
+    #[error("The characteristic is currently not accepting writes.")]
+    ResourceBusy,
+    #[error("The characteristic is hidden and doesn't accept writes by controllers.")]
+    HiddenCharacteristic,
+    #[error("The accessory of the characteristic is unreachable.")]
+    Unreachable,
+    #[error("Invalid HAP type: {0}")]
+    InvalidHapType(String),
+    #[error("Invalid characteristic format: {0}")]
+    InvalidFormat(String),
+    #[error("Invalid characteristic template: {0}")]
+    InvalidCharacteristicTemplate(String),
+    #[error("The bytes don't encode a valid characteristic.")]
+    InvalidCharacteristicBytes,
 
     // converted errors
     #[error("IO Error: {0}")]
@@ -56,6 +98,68 @@ pub enum Error {
     MpscSend(#[from] mpsc::SendError<()>),
 }
 
+impl Error {
+    /// Returns the name of the variant of an error without its fields, e.g. `"ValueAboveMaxValue"`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::PinTooEasy => "PinTooEasy",
+            Error::InvalidPin => "InvalidPin",
+            Error::InvalidPairingPermission(..) => "InvalidPairingPermission",
+            Error::ValueBelowMinValue => "ValueBelowMinValue",
+            Error::ValueAboveMaxValue => "ValueAboveMaxValue",
+            Error::ValueNotOnStep => "ValueNotOnStep",
+            Error::ValueNotInValidValues => "ValueNotInValidValues",
+            Error::ValueContainsControlCharacters => "ValueContainsControlCharacters",
+            Error::ValueDoesNotMatchPattern => "ValueDoesNotMatchPattern",
+            Error::ConflictingValidValues => "ConflictingValidValues",
+            Error::MetadataFrozen => "MetadataFrozen",
+            Error::ElementIndexOutOfBounds(..) => "ElementIndexOutOfBounds",
+            Error::InvertedRange => "InvertedRange",
+            Error::InvalidStepValue => "InvalidStepValue",
+            Error::ValidValuesOutOfRange => "ValidValuesOutOfRange",
+            Error::CallbackPanicked => "CallbackPanicked",
+            Error::VersionMismatch { .. } => "VersionMismatch",
+            Error::AccessoryNotFound => "AccessoryNotFound",
+            Error::CharacteristicNotFound => "CharacteristicNotFound",
+            Error::DuplicateAccessory => "DuplicateAccessory",
+            Error::DuplicateInstanceIds(..) => "DuplicateInstanceIds",
+            Error::InvalidValue(..) => "InvalidValue",
+            Error::ResourceBusy => "ResourceBusy",
+            Error::HiddenCharacteristic => "HiddenCharacteristic",
+            Error::Unreachable => "Unreachable",
+            Error::InvalidHapType(..) => "InvalidHapType",
+            Error::InvalidFormat(..) => "InvalidFormat",
+            Error::InvalidCharacteristicTemplate(..) => "InvalidCharacteristicTemplate",
+            Error::InvalidCharacteristicBytes => "InvalidCharacteristicBytes",
+            Error::Io(..) => "Io",
+            Error::Json(..) => "Json",
+            Error::HttpStatus(..) => "HttpStatus",
+            Error::Http(..) => "Http",
+            Error::Hyper(..) => "Hyper",
+            Error::TaskJoin(..) => "TaskJoin",
+            Error::Aead => "Aead",
+            Error::HkdfInvalidLength => "HkdfInvalidLength",
+            Error::Utf8(..) => "Utf8",
+            Error::ParseEui48(..) => "ParseEui48",
+            Error::ParseInt(..) => "ParseInt",
+            Error::MpscSend(..) => "MpscSend",
+        }
+    }
+}
+
 impl From<aead::Error> for Error {
     fn from(_: aead::Error) -> Self { Error::Aead }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind() {
+        assert_eq!(Error::ValueAboveMaxValue.kind(), "ValueAboveMaxValue");
+        assert_eq!(Error::InvalidValue(Format::Int32).kind(), "InvalidValue");
+        assert_eq!(Error::VersionMismatch { expected: 1, actual: 2 }.kind(), "VersionMismatch");
+        assert_eq!(Error::Aead.kind(), "Aead");
+    }
+}
@@ -1,32 +1,209 @@
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use futures::future::{join_all, BoxFuture};
+use futures::{
+    channel::mpsc,
+    future::{join_all, BoxFuture},
+    lock::Mutex,
+    sink::SinkExt,
+};
 use log::debug;
-use serde_json::Value;
+use serde_json::{json, Value};
 use uuid::Uuid;
 
-#[derive(Debug)]
+use crate::characteristic::{Format, Unit};
+
+#[derive(Debug, Clone)]
 pub enum Event {
     ControllerPaired { id: Uuid },
     ControllerUnpaired { id: Uuid },
-    CharacteristicValueChanged { aid: u64, iid: u64, value: Value },
+    CharacteristicValueChanged {
+        aid: u64,
+        iid: u64,
+        value: Value,
+        unit: Option<Unit>,
+        format: Option<Format>,
+        first: bool,
+    },
+    CharacteristicStructureChanged { aid: u64, iid: u64 },
+    CharacteristicError { aid: u64, iid: u64, kind: String },
+}
+
+impl Event {
+    /// Formats a `CharacteristicValueChanged` event as a single newline-terminated JSON object for a JSON Lines
+    /// stream, e.g. to feed a dashboard. The timestamp is given in milliseconds since the Unix epoch. Unit and format
+    /// are included if set and events for the first value of a Characteristic are marked with `"first": true`. Returns
+    /// `None` for other events.
+    pub fn to_json_line(&self, timestamp: SystemTime) -> Option<String> {
+        match self {
+            Event::CharacteristicValueChanged {
+                aid,
+                iid,
+                value,
+                unit,
+                format,
+                first,
+            } => {
+                let timestamp = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+                let mut line = json!({
+                    "aid": aid,
+                    "iid": iid,
+                    "value": value,
+                    "timestamp": timestamp,
+                });
+                if let Some(unit) = unit {
+                    line["unit"] = json!(unit);
+                }
+                if let Some(format) = format {
+                    line["format"] = json!(format);
+                }
+                if *first {
+                    line["first"] = json!(true);
+                }
+                Some(format!("{}\n", line))
+            },
+            _ => None,
+        }
+    }
 }
 
 #[derive(Default)]
 pub struct EventEmitter {
     listeners: Vec<Box<dyn (Fn(&Event) -> BoxFuture<()>) + Send + Sync>>,
+    senders: Mutex<Vec<mpsc::Sender<Event>>>,
+    closed: AtomicBool,
 }
 
 impl EventEmitter {
-    pub fn new() -> EventEmitter { EventEmitter { listeners: vec![] } }
+    pub fn new() -> EventEmitter {
+        EventEmitter {
+            listeners: vec![],
+            senders: Mutex::new(vec![]),
+            closed: AtomicBool::new(false),
+        }
+    }
 
     pub fn add_listener(&mut self, listener: Box<dyn (Fn(&Event) -> BoxFuture<()>) + Send + Sync>) {
         self.listeners.push(listener);
     }
 
+    /// Adds a bounded channel the emitted events are sent to. Emitting waits for capacity on the channel, so a slow
+    /// consumer applies backpressure to the emitter instead of events being buffered without bound. Once the receiving
+    /// half is dropped, the channel is removed and events are no longer sent to it.
+    pub fn add_sender(&mut self, sender: mpsc::Sender<Event>) { self.senders.get_mut().push(sender); }
+
+    /// Closes the emitter, removing all of its listeners and channels. Events emitted afterwards are discarded.
+    pub fn close(&mut self) {
+        self.listeners.clear();
+        self.senders.get_mut().clear();
+        *self.closed.get_mut() = true;
+    }
+
+    /// Returns whether the emitter has been closed, either explicitly or because the receiving halves of all of its
+    /// channels were dropped while it had no listeners.
+    pub fn is_closed(&self) -> bool { self.closed.load(Ordering::Relaxed) }
+
     pub async fn emit(&self, event: &Event) {
+        if self.is_closed() {
+            debug!("discarding event on closed emitter: {:?}", event);
+            return;
+        }
+
         debug!("emitting event: {:?}", event);
 
         join_all(self.listeners.iter().map(|listener| listener(&event))).await;
+
+        // the channels are locked while sending, so concurrent emits keep the order of events on each channel
+        let mut senders = self.senders.lock().await;
+        let had_senders = !senders.is_empty();
+        let mut open_senders = Vec::with_capacity(senders.len());
+        for mut sender in senders.drain(..) {
+            match sender.feed(event.clone()).await {
+                Ok(()) => open_senders.push(sender),
+                Err(_) => debug!("removing closed event channel"),
+            }
+        }
+        if self.listeners.is_empty() && open_senders.is_empty() && had_senders {
+            debug!("closing event emitter without listeners or channels");
+            self.closed.store(true, Ordering::Relaxed);
+        }
+        *senders = open_senders;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_to_json_line() {
+        let timestamp = UNIX_EPOCH + Duration::from_millis(1_600_000_000_123);
+        let event = Event::CharacteristicValueChanged {
+            aid: 1,
+            iid: 9,
+            value: json!({ "text": "line\nbreak" }),
+            unit: None,
+            format: None,
+            first: false,
+        };
+
+        let line = event.to_json_line(timestamp).unwrap();
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.matches('\n').count(), 1);
+        assert_eq!(
+            serde_json::from_str::<Value>(&line).unwrap(),
+            json!({ "aid": 1, "iid": 9, "value": { "text": "line\nbreak" }, "timestamp": 1_600_000_000_123u64 })
+        );
+
+        let event = Event::CharacteristicValueChanged {
+            aid: 1,
+            iid: 10,
+            value: json!(21.5),
+            unit: Some(Unit::Celsius),
+            format: Some(Format::Float),
+            first: true,
+        };
+        assert_eq!(
+            serde_json::from_str::<Value>(&event.to_json_line(timestamp).unwrap()).unwrap(),
+            json!({
+                "aid": 1,
+                "iid": 10,
+                "value": 21.5,
+                "unit": "celsius",
+                "format": "float",
+                "first": true,
+                "timestamp": 1_600_000_000_123u64,
+            })
+        );
+
+        assert!(Event::CharacteristicStructureChanged { aid: 1, iid: 9 }
+            .to_json_line(timestamp)
+            .is_none());
+    }
+
+    #[test]
+    fn test_close_on_dropped_receivers() {
+        let (sender, receiver) = mpsc::channel(1);
+        let mut event_emitter = EventEmitter::new();
+        event_emitter.add_sender(sender);
+        let event = Event::CharacteristicStructureChanged { aid: 1, iid: 9 };
+
+        futures::executor::block_on(event_emitter.emit(&event));
+        assert!(!event_emitter.is_closed());
+
+        drop(receiver);
+        futures::executor::block_on(event_emitter.emit(&event));
+        assert!(event_emitter.is_closed());
+
+        let mut event_emitter = EventEmitter::new();
+        futures::executor::block_on(event_emitter.emit(&event));
+        assert!(!event_emitter.is_closed());
+        event_emitter.close();
+        assert!(event_emitter.is_closed());
     }
 }
@@ -1,7 +1,11 @@
 // this file is auto-generated by hap-codegen
 
+use std::str::FromStr;
+
 use serde::ser::{Serialize, Serializer};
 
+use crate::{characteristic::Format, Error};
+
 /// HAP Service and Characteristic type representation.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum HapType {
@@ -392,6 +396,614 @@ impl ToString for HapType {
     }
 }
 
+impl FromStr for HapType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<HapType, Error> {
+        match s {
+            "unknown" => Ok(HapType::Unknown),
+			"A6" => Ok(HapType::AccessoryFlags),
+			"B0" => Ok(HapType::Active),
+			"E7" => Ok(HapType::ActiveIdentifier),
+			"1" => Ok(HapType::AdministratorOnlyAccess),
+			"64" => Ok(HapType::AirParticulateDensity),
+			"65" => Ok(HapType::AirParticulateSize),
+			"95" => Ok(HapType::AirQuality),
+			"5" => Ok(HapType::AudioFeedback),
+			"68" => Ok(HapType::BatteryLevel),
+			"8" => Ok(HapType::Brightness),
+			"92" => Ok(HapType::CarbonDioxideDetected),
+			"93" => Ok(HapType::CarbonDioxideLevel),
+			"94" => Ok(HapType::CarbonDioxidePeakLevel),
+			"69" => Ok(HapType::CarbonMonoxideDetected),
+			"90" => Ok(HapType::CarbonMonoxideLevel),
+			"91" => Ok(HapType::CarbonMonoxidePeakLevel),
+			"8F" => Ok(HapType::ChargingState),
+			"DD" => Ok(HapType::ClosedCaptions),
+			"E3" => Ok(HapType::ConfiguredName),
+			"136" => Ok(HapType::DisplayOrder),
+			"CE" => Ok(HapType::ColorTemperature),
+			"6A" => Ok(HapType::ContactSensorState),
+			"D" => Ok(HapType::CoolingThresholdTemperature),
+			"A9" => Ok(HapType::CurrentAirPurifierState),
+			"6B" => Ok(HapType::CurrentAmbientLightLevel),
+			"E" => Ok(HapType::CurrentDoorState),
+			"AF" => Ok(HapType::CurrentFanState),
+			"B1" => Ok(HapType::CurrentHeaterCoolerState),
+			"F" => Ok(HapType::CurrentHeatingCoolingState),
+			"6C" => Ok(HapType::CurrentHorizontalTiltAngle),
+			"B3" => Ok(HapType::CurrentHumidifierDehumidifierState),
+			"E0" => Ok(HapType::CurrentMediaState),
+			"137" => Ok(HapType::TargetMediaState),
+			"6D" => Ok(HapType::CurrentPosition),
+			"10" => Ok(HapType::CurrentRelativeHumidity),
+			"AA" => Ok(HapType::CurrentSlatState),
+			"11" => Ok(HapType::CurrentTemperature),
+			"C1" => Ok(HapType::CurrentTiltAngle),
+			"6E" => Ok(HapType::CurrentVerticalTiltAngle),
+			"11D" => Ok(HapType::DigitalZoom),
+			"AC" => Ok(HapType::FilterChangeIndication),
+			"AB" => Ok(HapType::FilterLifeLevel),
+			"52" => Ok(HapType::FirmwareRevision),
+			"53" => Ok(HapType::HardwareRevision),
+			"12" => Ok(HapType::HeatingThresholdTemperature),
+			"6F" => Ok(HapType::HoldPosition),
+			"13" => Ok(HapType::Hue),
+			"14" => Ok(HapType::Identify),
+			"DB" => Ok(HapType::InputSourceType),
+			"DC" => Ok(HapType::InputDeviceType),
+			"E6" => Ok(HapType::Identifier),
+			"135" => Ok(HapType::CurrentVisibilityState),
+			"134" => Ok(HapType::TargetVisibilityState),
+			"11F" => Ok(HapType::ImageMirroring),
+			"11E" => Ok(HapType::ImageRotation),
+			"D2" => Ok(HapType::InUse),
+			"D6" => Ok(HapType::IsConfigured),
+			"70" => Ok(HapType::LeakDetected),
+			"19" => Ok(HapType::LockControlPoint),
+			"1D" => Ok(HapType::LockCurrentState),
+			"1C" => Ok(HapType::LockLastKnownAction),
+			"1A" => Ok(HapType::LockManagementAutoSecurityTimeout),
+			"A7" => Ok(HapType::LockPhysicalControls),
+			"1E" => Ok(HapType::LockTargetState),
+			"1F" => Ok(HapType::Logs),
+			"20" => Ok(HapType::Manufacturer),
+			"21" => Ok(HapType::Model),
+			"22" => Ok(HapType::MotionDetected),
+			"11A" => Ok(HapType::Mute),
+			"23" => Ok(HapType::Name),
+			"11B" => Ok(HapType::NightVision),
+			"C4" => Ok(HapType::NitrogenDioxideDensity),
+			"24" => Ok(HapType::ObstructionDetected),
+			"71" => Ok(HapType::OccupancyDetected),
+			"25" => Ok(HapType::On),
+			"11C" => Ok(HapType::OpticalZoom),
+			"26" => Ok(HapType::OutletInUse),
+			"C3" => Ok(HapType::OzoneDensity),
+			"4C" => Ok(HapType::PairSetup),
+			"4E" => Ok(HapType::PairVerify),
+			"4F" => Ok(HapType::PairingFeatures),
+			"50" => Ok(HapType::PairingPairings),
+			"C7" => Ok(HapType::Pm10Density),
+			"C6" => Ok(HapType::Pm2_5Density),
+			"72" => Ok(HapType::PositionState),
+			"E2" => Ok(HapType::PictureMode),
+			"DF" => Ok(HapType::PowerModeSelection),
+			"D1" => Ok(HapType::ProgramMode),
+			"73" => Ok(HapType::ProgrammableSwitchEvent),
+			"E1" => Ok(HapType::RemoteKey),
+			"C9" => Ok(HapType::RelativeHumidityDehumidifierThreshold),
+			"CA" => Ok(HapType::RelativeHumidityHumidifierThreshold),
+			"D4" => Ok(HapType::RemainingDuration),
+			"AD" => Ok(HapType::ResetFilterIndication),
+			"28" => Ok(HapType::RotationDirection),
+			"29" => Ok(HapType::RotationSpeed),
+			"2F" => Ok(HapType::Saturation),
+			"8E" => Ok(HapType::SecuritySystemAlarmType),
+			"66" => Ok(HapType::SecuritySystemCurrentState),
+			"67" => Ok(HapType::SecuritySystemTargetState),
+			"117" => Ok(HapType::SelectedRtpStreamConfiguration),
+			"30" => Ok(HapType::SerialNumber),
+			"CB" => Ok(HapType::ServiceLabelIndex),
+			"CD" => Ok(HapType::ServiceLabelNamespace),
+			"D3" => Ok(HapType::SetDuration),
+			"118" => Ok(HapType::SetupEndpoints),
+			"C0" => Ok(HapType::SlatType),
+			"E8" => Ok(HapType::SleepDiscoveryMode),
+			"76" => Ok(HapType::SmokeDetected),
+			"75" => Ok(HapType::StatusActive),
+			"77" => Ok(HapType::StatusFault),
+			"78" => Ok(HapType::StatusJammed),
+			"79" => Ok(HapType::StatusLowBattery),
+			"7A" => Ok(HapType::StatusTampered),
+			"120" => Ok(HapType::StreamingStatus),
+			"C5" => Ok(HapType::SulphurDioxideDensity),
+			"115" => Ok(HapType::SupportedAudioStreamConfiguration),
+			"116" => Ok(HapType::SupportedRtpConfiguration),
+			"114" => Ok(HapType::SupportedVideoStreamConfiguration),
+			"B6" => Ok(HapType::SwingMode),
+			"A8" => Ok(HapType::TargetAirPurifierState),
+			"AE" => Ok(HapType::TargetAirQuality),
+			"32" => Ok(HapType::TargetDoorState),
+			"BF" => Ok(HapType::TargetFanState),
+			"B2" => Ok(HapType::TargetHeaterCoolerState),
+			"33" => Ok(HapType::TargetHeatingCoolingState),
+			"7B" => Ok(HapType::TargetHorizontalTiltAngle),
+			"B4" => Ok(HapType::TargetHumidifierDehumidifierState),
+			"7C" => Ok(HapType::TargetPosition),
+			"34" => Ok(HapType::TargetRelativeHumidity),
+			"BE" => Ok(HapType::TargetSlatState),
+			"35" => Ok(HapType::TargetTemperature),
+			"C2" => Ok(HapType::TargetTiltAngle),
+			"7D" => Ok(HapType::TargetVerticalTiltAngle),
+			"36" => Ok(HapType::TemperatureDisplayUnits),
+			"D5" => Ok(HapType::ValveType),
+			"37" => Ok(HapType::Version),
+			"C8" => Ok(HapType::VocDensity),
+			"119" => Ok(HapType::Volume),
+			"E9" => Ok(HapType::VolumeControlType),
+			"EA" => Ok(HapType::VolumeSelector),
+			"B5" => Ok(HapType::WaterLevel),
+			"205" => Ok(HapType::SupportedCameraRecordingConfiguration),
+			"206" => Ok(HapType::SupportedVideoRecordingConfiguration),
+			"207" => Ok(HapType::SupportedAudioRecordingConfiguration),
+			"209" => Ok(HapType::SelectedCameraRecordingConfiguration),
+			"3E" => Ok(HapType::AccessoryInformation),
+			"BB" => Ok(HapType::AirPurifier),
+			"8D" => Ok(HapType::AirQualitySensor),
+			"96" => Ok(HapType::BatteryService),
+			"110" => Ok(HapType::CameraRtpStreamManagement),
+			"97" => Ok(HapType::CarbonDioxideSensor),
+			"7F" => Ok(HapType::CarbonMonoxideSensor),
+			"80" => Ok(HapType::ContactSensor),
+			"81" => Ok(HapType::Door),
+			"121" => Ok(HapType::Doorbell),
+			"40" => Ok(HapType::Fan),
+			"B7" => Ok(HapType::FanV2),
+			"BA" => Ok(HapType::FilterMaintenance),
+			"D7" => Ok(HapType::Faucet),
+			"41" => Ok(HapType::GarageDoorOpener),
+			"BC" => Ok(HapType::HeaterCooler),
+			"BD" => Ok(HapType::HumidifierDehumidifier),
+			"82" => Ok(HapType::HumiditySensor),
+			"CF" => Ok(HapType::IrrigationSystem),
+			"83" => Ok(HapType::LeakSensor),
+			"84" => Ok(HapType::LightSensor),
+			"43" => Ok(HapType::Lightbulb),
+			"44" => Ok(HapType::LockManagement),
+			"45" => Ok(HapType::LockMechanism),
+			"112" => Ok(HapType::Microphone),
+			"85" => Ok(HapType::MotionSensor),
+			"86" => Ok(HapType::OccupancySensor),
+			"47" => Ok(HapType::Outlet),
+			"7E" => Ok(HapType::SecuritySystem),
+			"CC" => Ok(HapType::ServiceLabel),
+			"B9" => Ok(HapType::Slat),
+			"87" => Ok(HapType::SmokeSensor),
+			"113" => Ok(HapType::Speaker),
+			"89" => Ok(HapType::StatelessProgrammableSwitch),
+			"49" => Ok(HapType::Switch),
+			"8A" => Ok(HapType::TemperatureSensor),
+			"4A" => Ok(HapType::Thermostat),
+			"D0" => Ok(HapType::Valve),
+			"8B" => Ok(HapType::Window),
+			"8C" => Ok(HapType::WindowCovering),
+			"D8" => Ok(HapType::Television),
+			"D9" => Ok(HapType::InputSource),
+			_ => Err(Error::InvalidHapType(s.into())),
+        }
+    }
+}
+
+impl HapType {
+    /// Returns the full UUID of the type, e.g. `00000008-0000-1000-8000-0026BB765291` for `HapType::Brightness`.
+    pub fn to_uuid_string(&self) -> String {
+        match self {
+            HapType::Unknown => "unknown".into(),
+            _ => format!("{:0>8}-0000-1000-8000-0026BB765291", self.to_string()),
+        }
+    }
+
+    /// Returns the human-readable name of the type, e.g. `Current Temperature` for `HapType::CurrentTemperature`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HapType::Unknown => "Unknown",
+			HapType::AccessoryFlags => "Accessory Flags",
+			HapType::Active => "Active",
+			HapType::ActiveIdentifier => "Active Identifier",
+			HapType::AdministratorOnlyAccess => "Administrator Only Access",
+			HapType::AirParticulateDensity => "Air Particulate Density",
+			HapType::AirParticulateSize => "Air Particulate Size",
+			HapType::AirQuality => "Air Quality",
+			HapType::AudioFeedback => "Audio Feedback",
+			HapType::BatteryLevel => "Battery Level",
+			HapType::Brightness => "Brightness",
+			HapType::CarbonDioxideDetected => "Carbon Dioxide Detected",
+			HapType::CarbonDioxideLevel => "Carbon Dioxide Level",
+			HapType::CarbonDioxidePeakLevel => "Carbon Dioxide Peak Level",
+			HapType::CarbonMonoxideDetected => "Carbon Monoxide Detected",
+			HapType::CarbonMonoxideLevel => "Carbon Monoxide Level",
+			HapType::CarbonMonoxidePeakLevel => "Carbon Monoxide Peak Level",
+			HapType::ChargingState => "Charging State",
+			HapType::ClosedCaptions => "Closed Captions",
+			HapType::ConfiguredName => "Configured Name",
+			HapType::DisplayOrder => "Display Order",
+			HapType::ColorTemperature => "Color Temperature",
+			HapType::ContactSensorState => "Contact Sensor State",
+			HapType::CoolingThresholdTemperature => "Cooling Threshold Temperature",
+			HapType::CurrentAirPurifierState => "Current Air Purifier State",
+			HapType::CurrentAmbientLightLevel => "Current Ambient Light Level",
+			HapType::CurrentDoorState => "Current Door State",
+			HapType::CurrentFanState => "Current Fan State",
+			HapType::CurrentHeaterCoolerState => "Current Heater Cooler State",
+			HapType::CurrentHeatingCoolingState => "Current Heating Cooling State",
+			HapType::CurrentHorizontalTiltAngle => "Current Horizontal Tilt Angle",
+			HapType::CurrentHumidifierDehumidifierState => "Current Humidifier Dehumidifier State",
+			HapType::CurrentMediaState => "Current Media State",
+			HapType::TargetMediaState => "Target Media State",
+			HapType::CurrentPosition => "Current Position",
+			HapType::CurrentRelativeHumidity => "Current Relative Humidity",
+			HapType::CurrentSlatState => "Current Slat State",
+			HapType::CurrentTemperature => "Current Temperature",
+			HapType::CurrentTiltAngle => "Current Tilt Angle",
+			HapType::CurrentVerticalTiltAngle => "Current Vertical Tilt Angle",
+			HapType::DigitalZoom => "Digital Zoom",
+			HapType::FilterChangeIndication => "Filter Change Indication",
+			HapType::FilterLifeLevel => "Filter Life Level",
+			HapType::FirmwareRevision => "Firmware Revision",
+			HapType::HardwareRevision => "Hardware Revision",
+			HapType::HeatingThresholdTemperature => "Heating Threshold Temperature",
+			HapType::HoldPosition => "Hold Position",
+			HapType::Hue => "Hue",
+			HapType::Identify => "Identify",
+			HapType::InputSourceType => "Input Source Type",
+			HapType::InputDeviceType => "Input Device Type",
+			HapType::Identifier => "Identifier",
+			HapType::CurrentVisibilityState => "Current Visibility State",
+			HapType::TargetVisibilityState => "Target Visibility State",
+			HapType::ImageMirroring => "Image Mirroring",
+			HapType::ImageRotation => "Image Rotation",
+			HapType::InUse => "In Use",
+			HapType::IsConfigured => "Is Configured",
+			HapType::LeakDetected => "Leak Detected",
+			HapType::LockControlPoint => "Lock Control Point",
+			HapType::LockCurrentState => "Lock Current State",
+			HapType::LockLastKnownAction => "Lock Last Known Action",
+			HapType::LockManagementAutoSecurityTimeout => "Lock Management Auto Security Timeout",
+			HapType::LockPhysicalControls => "Lock Physical Controls",
+			HapType::LockTargetState => "Lock Target State",
+			HapType::Logs => "Logs",
+			HapType::Manufacturer => "Manufacturer",
+			HapType::Model => "Model",
+			HapType::MotionDetected => "Motion Detected",
+			HapType::Mute => "Mute",
+			HapType::Name => "Name",
+			HapType::NightVision => "Night Vision",
+			HapType::NitrogenDioxideDensity => "Nitrogen Dioxide Density",
+			HapType::ObstructionDetected => "Obstruction Detected",
+			HapType::OccupancyDetected => "Occupancy Detected",
+			HapType::On => "On",
+			HapType::OpticalZoom => "Optical Zoom",
+			HapType::OutletInUse => "Outlet In Use",
+			HapType::OzoneDensity => "Ozone Density",
+			HapType::PairSetup => "Pair Setup",
+			HapType::PairVerify => "Pair Verify",
+			HapType::PairingFeatures => "Pairing Features",
+			HapType::PairingPairings => "Pairing Pairings",
+			HapType::Pm10Density => "PM10 Density",
+			HapType::Pm2_5Density => "PM2.5 Density",
+			HapType::PositionState => "Position State",
+			HapType::PictureMode => "Picture Mode",
+			HapType::PowerModeSelection => "Power Mode Selection",
+			HapType::ProgramMode => "Program Mode",
+			HapType::ProgrammableSwitchEvent => "Programmable Switch Event",
+			HapType::RemoteKey => "Remote Key",
+			HapType::RelativeHumidityDehumidifierThreshold => "Relative Humidity Dehumidifier Threshold",
+			HapType::RelativeHumidityHumidifierThreshold => "Relative Humidity Humidifier Threshold",
+			HapType::RemainingDuration => "Remaining Duration",
+			HapType::ResetFilterIndication => "Reset Filter Indication",
+			HapType::RotationDirection => "Rotation Direction",
+			HapType::RotationSpeed => "Rotation Speed",
+			HapType::Saturation => "Saturation",
+			HapType::SecuritySystemAlarmType => "Security System Alarm Type",
+			HapType::SecuritySystemCurrentState => "Security System Current State",
+			HapType::SecuritySystemTargetState => "Security System Target State",
+			HapType::SelectedRtpStreamConfiguration => "Selected RTP Stream Configuration",
+			HapType::SerialNumber => "Serial Number",
+			HapType::ServiceLabelIndex => "Service Label Index",
+			HapType::ServiceLabelNamespace => "Service Label Namespace",
+			HapType::SetDuration => "Set Duration",
+			HapType::SetupEndpoints => "Setup Endpoints",
+			HapType::SlatType => "Slat Type",
+			HapType::SleepDiscoveryMode => "Sleep Discovery Mode",
+			HapType::SmokeDetected => "Smoke Detected",
+			HapType::StatusActive => "Status Active",
+			HapType::StatusFault => "Status Fault",
+			HapType::StatusJammed => "Status Jammed",
+			HapType::StatusLowBattery => "Status Low Battery",
+			HapType::StatusTampered => "Status Tampered",
+			HapType::StreamingStatus => "Streaming Status",
+			HapType::SulphurDioxideDensity => "Sulphur Dioxide Density",
+			HapType::SupportedAudioStreamConfiguration => "Supported Audio Stream Configuration",
+			HapType::SupportedRtpConfiguration => "Supported RTP Configuration",
+			HapType::SupportedVideoStreamConfiguration => "Supported Video Stream Configuration",
+			HapType::SwingMode => "Swing Mode",
+			HapType::TargetAirPurifierState => "Target Air Purifier State",
+			HapType::TargetAirQuality => "Target Air Quality",
+			HapType::TargetDoorState => "Target Door State",
+			HapType::TargetFanState => "Target Fan State",
+			HapType::TargetHeaterCoolerState => "Target Heater Cooler State",
+			HapType::TargetHeatingCoolingState => "Target Heating Cooling State",
+			HapType::TargetHorizontalTiltAngle => "Target Horizontal Tilt Angle",
+			HapType::TargetHumidifierDehumidifierState => "Target Humidifier Dehumidifier State",
+			HapType::TargetPosition => "Target Position",
+			HapType::TargetRelativeHumidity => "Target Relative Humidity",
+			HapType::TargetSlatState => "Target Slat State",
+			HapType::TargetTemperature => "Target Temperature",
+			HapType::TargetTiltAngle => "Target Tilt Angle",
+			HapType::TargetVerticalTiltAngle => "Target Vertical Tilt Angle",
+			HapType::TemperatureDisplayUnits => "Temperature Display Units",
+			HapType::ValveType => "Valve Type",
+			HapType::Version => "Version",
+			HapType::VocDensity => "VOC Density",
+			HapType::Volume => "Volume",
+			HapType::VolumeControlType => "Volume Control Type",
+			HapType::VolumeSelector => "Volume Selector",
+			HapType::WaterLevel => "Water Level",
+			HapType::SupportedCameraRecordingConfiguration => "Supported Camera Recording Configuration",
+			HapType::SupportedVideoRecordingConfiguration => "Supported Video Recording Configuration",
+			HapType::SupportedAudioRecordingConfiguration => "Supported Audio Recording Configuration",
+			HapType::SelectedCameraRecordingConfiguration => "Selected Camera Recording Configuration",
+			HapType::AccessoryInformation => "Accessory Information",
+			HapType::AirPurifier => "Air Purifier",
+			HapType::AirQualitySensor => "Air Quality Sensor",
+			HapType::BatteryService => "Battery Service",
+			HapType::CameraRtpStreamManagement => "Camera RTP Stream Management",
+			HapType::CarbonDioxideSensor => "Carbon Dioxide Sensor",
+			HapType::CarbonMonoxideSensor => "Carbon Monoxide Sensor",
+			HapType::ContactSensor => "Contact Sensor",
+			HapType::Door => "Door",
+			HapType::Doorbell => "Doorbell",
+			HapType::Fan => "Fan",
+			HapType::FanV2 => "Fan v2",
+			HapType::FilterMaintenance => "Filter Maintenance",
+			HapType::Faucet => "Faucet",
+			HapType::GarageDoorOpener => "Garage Door Opener",
+			HapType::HeaterCooler => "Heater Cooler",
+			HapType::HumidifierDehumidifier => "Humidifier Dehumidifier",
+			HapType::HumiditySensor => "Humidity Sensor",
+			HapType::IrrigationSystem => "Irrigation System",
+			HapType::LeakSensor => "Leak Sensor",
+			HapType::LightSensor => "Light Sensor",
+			HapType::Lightbulb => "Lightbulb",
+			HapType::LockManagement => "Lock Management",
+			HapType::LockMechanism => "Lock Mechanism",
+			HapType::Microphone => "Microphone",
+			HapType::MotionSensor => "Motion Sensor",
+			HapType::OccupancySensor => "Occupancy Sensor",
+			HapType::Outlet => "Outlet",
+			HapType::SecuritySystem => "Security System",
+			HapType::ServiceLabel => "Service Label",
+			HapType::Slat => "Slat",
+			HapType::SmokeSensor => "Smoke Sensor",
+			HapType::Speaker => "Speaker",
+			HapType::StatelessProgrammableSwitch => "Stateless Programmable Switch",
+			HapType::Switch => "Switch",
+			HapType::TemperatureSensor => "Temperature Sensor",
+			HapType::Thermostat => "Thermostat",
+			HapType::Valve => "Valve",
+			HapType::Window => "Window",
+			HapType::WindowCovering => "Window Covering",
+			HapType::Television => "Television",
+			HapType::InputSource => "Input Source",
+		}
+    }
+
+    /// Returns the `Format` the HAP specification defines for a Characteristic type, e.g. `Format::Int32` for
+    /// `HapType::Brightness`. Returns `None` for Service types.
+    pub fn format(&self) -> Option<Format> {
+        match self {
+			HapType::AccessoryFlags => Some(Format::UInt32),
+			HapType::Active => Some(Format::UInt8),
+			HapType::ActiveIdentifier => Some(Format::UInt32),
+			HapType::AdministratorOnlyAccess => Some(Format::Bool),
+			HapType::AirParticulateDensity => Some(Format::Float),
+			HapType::AirParticulateSize => Some(Format::UInt8),
+			HapType::AirQuality => Some(Format::UInt8),
+			HapType::AudioFeedback => Some(Format::Bool),
+			HapType::BatteryLevel => Some(Format::UInt8),
+			HapType::Brightness => Some(Format::Int32),
+			HapType::CarbonDioxideDetected => Some(Format::UInt8),
+			HapType::CarbonDioxideLevel => Some(Format::Float),
+			HapType::CarbonDioxidePeakLevel => Some(Format::Float),
+			HapType::CarbonMonoxideDetected => Some(Format::UInt8),
+			HapType::CarbonMonoxideLevel => Some(Format::Float),
+			HapType::CarbonMonoxidePeakLevel => Some(Format::Float),
+			HapType::ChargingState => Some(Format::UInt8),
+			HapType::ClosedCaptions => Some(Format::UInt8),
+			HapType::ConfiguredName => Some(Format::String),
+			HapType::DisplayOrder => Some(Format::Tlv8),
+			HapType::ColorTemperature => Some(Format::UInt32),
+			HapType::ContactSensorState => Some(Format::UInt8),
+			HapType::CoolingThresholdTemperature => Some(Format::Float),
+			HapType::CurrentAirPurifierState => Some(Format::UInt8),
+			HapType::CurrentAmbientLightLevel => Some(Format::Float),
+			HapType::CurrentDoorState => Some(Format::UInt8),
+			HapType::CurrentFanState => Some(Format::UInt8),
+			HapType::CurrentHeaterCoolerState => Some(Format::UInt8),
+			HapType::CurrentHeatingCoolingState => Some(Format::UInt8),
+			HapType::CurrentHorizontalTiltAngle => Some(Format::Int32),
+			HapType::CurrentHumidifierDehumidifierState => Some(Format::UInt8),
+			HapType::CurrentMediaState => Some(Format::UInt8),
+			HapType::TargetMediaState => Some(Format::UInt8),
+			HapType::CurrentPosition => Some(Format::UInt8),
+			HapType::CurrentRelativeHumidity => Some(Format::Float),
+			HapType::CurrentSlatState => Some(Format::UInt8),
+			HapType::CurrentTemperature => Some(Format::Float),
+			HapType::CurrentTiltAngle => Some(Format::Int32),
+			HapType::CurrentVerticalTiltAngle => Some(Format::Int32),
+			HapType::DigitalZoom => Some(Format::Float),
+			HapType::FilterChangeIndication => Some(Format::UInt8),
+			HapType::FilterLifeLevel => Some(Format::Float),
+			HapType::FirmwareRevision => Some(Format::String),
+			HapType::HardwareRevision => Some(Format::String),
+			HapType::HeatingThresholdTemperature => Some(Format::Float),
+			HapType::HoldPosition => Some(Format::Bool),
+			HapType::Hue => Some(Format::Float),
+			HapType::Identify => Some(Format::Bool),
+			HapType::InputSourceType => Some(Format::UInt8),
+			HapType::InputDeviceType => Some(Format::UInt8),
+			HapType::Identifier => Some(Format::UInt32),
+			HapType::CurrentVisibilityState => Some(Format::UInt8),
+			HapType::TargetVisibilityState => Some(Format::UInt8),
+			HapType::ImageMirroring => Some(Format::Bool),
+			HapType::ImageRotation => Some(Format::Float),
+			HapType::InUse => Some(Format::UInt8),
+			HapType::IsConfigured => Some(Format::UInt8),
+			HapType::LeakDetected => Some(Format::UInt8),
+			HapType::LockControlPoint => Some(Format::Tlv8),
+			HapType::LockCurrentState => Some(Format::UInt8),
+			HapType::LockLastKnownAction => Some(Format::UInt8),
+			HapType::LockManagementAutoSecurityTimeout => Some(Format::UInt32),
+			HapType::LockPhysicalControls => Some(Format::UInt8),
+			HapType::LockTargetState => Some(Format::UInt8),
+			HapType::Logs => Some(Format::Tlv8),
+			HapType::Manufacturer => Some(Format::String),
+			HapType::Model => Some(Format::String),
+			HapType::MotionDetected => Some(Format::Bool),
+			HapType::Mute => Some(Format::Bool),
+			HapType::Name => Some(Format::String),
+			HapType::NightVision => Some(Format::Bool),
+			HapType::NitrogenDioxideDensity => Some(Format::Float),
+			HapType::ObstructionDetected => Some(Format::Bool),
+			HapType::OccupancyDetected => Some(Format::UInt8),
+			HapType::On => Some(Format::Bool),
+			HapType::OpticalZoom => Some(Format::Float),
+			HapType::OutletInUse => Some(Format::Bool),
+			HapType::OzoneDensity => Some(Format::Float),
+			HapType::PairSetup => Some(Format::Tlv8),
+			HapType::PairVerify => Some(Format::Tlv8),
+			HapType::PairingFeatures => Some(Format::UInt8),
+			HapType::PairingPairings => Some(Format::Tlv8),
+			HapType::Pm10Density => Some(Format::Float),
+			HapType::Pm2_5Density => Some(Format::Float),
+			HapType::PositionState => Some(Format::UInt8),
+			HapType::PictureMode => Some(Format::UInt16),
+			HapType::PowerModeSelection => Some(Format::UInt8),
+			HapType::ProgramMode => Some(Format::UInt8),
+			HapType::ProgrammableSwitchEvent => Some(Format::UInt8),
+			HapType::RemoteKey => Some(Format::UInt8),
+			HapType::RelativeHumidityDehumidifierThreshold => Some(Format::Float),
+			HapType::RelativeHumidityHumidifierThreshold => Some(Format::Float),
+			HapType::RemainingDuration => Some(Format::UInt32),
+			HapType::ResetFilterIndication => Some(Format::UInt8),
+			HapType::RotationDirection => Some(Format::Int32),
+			HapType::RotationSpeed => Some(Format::Float),
+			HapType::Saturation => Some(Format::Float),
+			HapType::SecuritySystemAlarmType => Some(Format::UInt8),
+			HapType::SecuritySystemCurrentState => Some(Format::UInt8),
+			HapType::SecuritySystemTargetState => Some(Format::UInt8),
+			HapType::SelectedRtpStreamConfiguration => Some(Format::Tlv8),
+			HapType::SerialNumber => Some(Format::String),
+			HapType::ServiceLabelIndex => Some(Format::UInt8),
+			HapType::ServiceLabelNamespace => Some(Format::UInt8),
+			HapType::SetDuration => Some(Format::UInt32),
+			HapType::SetupEndpoints => Some(Format::Tlv8),
+			HapType::SlatType => Some(Format::UInt8),
+			HapType::SleepDiscoveryMode => Some(Format::UInt8),
+			HapType::SmokeDetected => Some(Format::UInt8),
+			HapType::StatusActive => Some(Format::Bool),
+			HapType::StatusFault => Some(Format::UInt8),
+			HapType::StatusJammed => Some(Format::UInt8),
+			HapType::StatusLowBattery => Some(Format::UInt8),
+			HapType::StatusTampered => Some(Format::UInt8),
+			HapType::StreamingStatus => Some(Format::Tlv8),
+			HapType::SulphurDioxideDensity => Some(Format::Float),
+			HapType::SupportedAudioStreamConfiguration => Some(Format::Tlv8),
+			HapType::SupportedRtpConfiguration => Some(Format::Tlv8),
+			HapType::SupportedVideoStreamConfiguration => Some(Format::Tlv8),
+			HapType::SwingMode => Some(Format::UInt8),
+			HapType::TargetAirPurifierState => Some(Format::UInt8),
+			HapType::TargetAirQuality => Some(Format::UInt8),
+			HapType::TargetDoorState => Some(Format::UInt8),
+			HapType::TargetFanState => Some(Format::UInt8),
+			HapType::TargetHeaterCoolerState => Some(Format::UInt8),
+			HapType::TargetHeatingCoolingState => Some(Format::UInt8),
+			HapType::TargetHorizontalTiltAngle => Some(Format::Int32),
+			HapType::TargetHumidifierDehumidifierState => Some(Format::UInt8),
+			HapType::TargetPosition => Some(Format::UInt8),
+			HapType::TargetRelativeHumidity => Some(Format::Float),
+			HapType::TargetSlatState => Some(Format::UInt8),
+			HapType::TargetTemperature => Some(Format::Float),
+			HapType::TargetTiltAngle => Some(Format::Int32),
+			HapType::TargetVerticalTiltAngle => Some(Format::Int32),
+			HapType::TemperatureDisplayUnits => Some(Format::UInt8),
+			HapType::ValveType => Some(Format::UInt8),
+			HapType::Version => Some(Format::String),
+			HapType::VocDensity => Some(Format::Float),
+			HapType::Volume => Some(Format::UInt8),
+			HapType::VolumeControlType => Some(Format::UInt8),
+			HapType::VolumeSelector => Some(Format::UInt8),
+			HapType::WaterLevel => Some(Format::Float),
+			HapType::SupportedCameraRecordingConfiguration => Some(Format::Tlv8),
+			HapType::SupportedVideoRecordingConfiguration => Some(Format::Tlv8),
+			HapType::SupportedAudioRecordingConfiguration => Some(Format::Tlv8),
+			HapType::SelectedCameraRecordingConfiguration => Some(Format::Tlv8),
+			_ => None,
+		}
+    }
+
+    /// Returns the Characteristic types the HAP specification requires for a Service type, e.g. `HapType::On` for
+    /// `HapType::Lightbulb`. Returns an empty slice for Characteristic types.
+    pub fn required_characteristics(&self) -> &'static [HapType] {
+        match self {
+			HapType::AccessoryInformation => &[HapType::Identify, HapType::Manufacturer, HapType::Model, HapType::Name, HapType::SerialNumber, HapType::FirmwareRevision],
+			HapType::AirPurifier => &[HapType::Active, HapType::CurrentAirPurifierState, HapType::TargetAirPurifierState],
+			HapType::AirQualitySensor => &[HapType::AirQuality],
+			HapType::BatteryService => &[HapType::BatteryLevel, HapType::ChargingState, HapType::StatusLowBattery],
+			HapType::CameraRtpStreamManagement => &[HapType::SupportedVideoStreamConfiguration, HapType::SupportedAudioStreamConfiguration, HapType::SupportedRtpConfiguration, HapType::SelectedRtpStreamConfiguration, HapType::StreamingStatus, HapType::SetupEndpoints],
+			HapType::CarbonDioxideSensor => &[HapType::CarbonDioxideDetected],
+			HapType::CarbonMonoxideSensor => &[HapType::CarbonMonoxideDetected],
+			HapType::ContactSensor => &[HapType::ContactSensorState],
+			HapType::Door => &[HapType::CurrentPosition, HapType::PositionState, HapType::TargetPosition],
+			HapType::Doorbell => &[HapType::ProgrammableSwitchEvent],
+			HapType::Fan => &[HapType::On],
+			HapType::FanV2 => &[HapType::Active],
+			HapType::FilterMaintenance => &[HapType::FilterChangeIndication],
+			HapType::Faucet => &[HapType::Active],
+			HapType::GarageDoorOpener => &[HapType::CurrentDoorState, HapType::TargetDoorState, HapType::ObstructionDetected],
+			HapType::HeaterCooler => &[HapType::Active, HapType::CurrentHeaterCoolerState, HapType::TargetHeaterCoolerState, HapType::CurrentTemperature],
+			HapType::HumidifierDehumidifier => &[HapType::CurrentRelativeHumidity, HapType::CurrentHumidifierDehumidifierState, HapType::TargetHumidifierDehumidifierState, HapType::Active],
+			HapType::HumiditySensor => &[HapType::CurrentRelativeHumidity],
+			HapType::IrrigationSystem => &[HapType::Active, HapType::ProgramMode, HapType::InUse],
+			HapType::LeakSensor => &[HapType::LeakDetected],
+			HapType::LightSensor => &[HapType::CurrentAmbientLightLevel],
+			HapType::Lightbulb => &[HapType::On],
+			HapType::LockManagement => &[HapType::LockControlPoint, HapType::Version],
+			HapType::LockMechanism => &[HapType::LockCurrentState, HapType::LockTargetState],
+			HapType::Microphone => &[HapType::Volume, HapType::Mute],
+			HapType::MotionSensor => &[HapType::MotionDetected],
+			HapType::OccupancySensor => &[HapType::OccupancyDetected],
+			HapType::Outlet => &[HapType::On, HapType::OutletInUse],
+			HapType::SecuritySystem => &[HapType::SecuritySystemCurrentState, HapType::SecuritySystemTargetState],
+			HapType::ServiceLabel => &[HapType::ServiceLabelNamespace],
+			HapType::Slat => &[HapType::SlatType, HapType::CurrentSlatState],
+			HapType::SmokeSensor => &[HapType::SmokeDetected],
+			HapType::Speaker => &[HapType::Mute],
+			HapType::StatelessProgrammableSwitch => &[HapType::ProgrammableSwitchEvent],
+			HapType::Switch => &[HapType::On],
+			HapType::TemperatureSensor => &[HapType::CurrentTemperature],
+			HapType::Thermostat => &[HapType::CurrentHeatingCoolingState, HapType::TargetHeatingCoolingState, HapType::CurrentTemperature, HapType::TargetTemperature, HapType::TemperatureDisplayUnits],
+			HapType::Valve => &[HapType::Active, HapType::InUse, HapType::ValveType],
+			HapType::Window => &[HapType::CurrentPosition, HapType::TargetPosition, HapType::PositionState],
+			HapType::WindowCovering => &[HapType::CurrentPosition, HapType::TargetPosition, HapType::PositionState],
+			HapType::Television => &[HapType::Active, HapType::ActiveIdentifier, HapType::ConfiguredName, HapType::SleepDiscoveryMode],
+			HapType::InputSource => &[HapType::ConfiguredName, HapType::InputSourceType, HapType::IsConfigured, HapType::CurrentVisibilityState],
+			_ => &[],
+		}
+    }
+}
+
 impl Default for HapType {
     fn default() -> HapType { HapType::Unknown }
 }
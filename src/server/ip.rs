@@ -1,11 +1,11 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use futures::{
     future::{self, BoxFuture, FutureExt},
     lock::Mutex,
 };
-use log::info;
+use log::{error, info};
 
 use crate::server::ServerPersistence;
 
@@ -15,9 +15,13 @@ use crate::{
     event::{Event, EventEmitter},
     pointer,
     server::Server,
-    storage::{accessory_list::AccessoryList, Storage},
+    storage::{
+        accessory_list::{run_timers, AccessoryList},
+        Storage,
+    },
     transport::{http::server::Server as HttpServer, mdns::MdnsResponder},
     BonjourStatusFlag,
+    HapType,
     Result,
 };
 
@@ -31,6 +35,7 @@ pub struct IpServer {
     http_server: HttpServer,
     mdns_responder: MdnsResponder,
     persistence: ServerPersistence,
+    timer_interval: Option<Duration>,
 }
 
 impl IpServer {
@@ -124,6 +129,15 @@ impl IpServer {
                             }
                         }
                     },
+                    Event::CharacteristicStructureChanged { aid, iid } => {
+                        info!("structure of characteristic {}.{} changed", aid, iid);
+
+                        let mut c = config_.lock().await;
+                        c.configuration_number += 1;
+                        if let Err(e) = storage_.lock().await.save_config(&c).await {
+                            error!("couldn't save config: {}", e);
+                        }
+                    },
                     _ => {},
                 }
             }
@@ -153,10 +167,49 @@ impl IpServer {
             http_server,
             mdns_responder,
             persistence,
+            timer_interval: None,
         };
 
         Ok(server)
     }
+
+    /// Returns the interval in which the server applies the time-dependent updates of all Characteristics.
+    pub fn get_timer_interval(&self) -> Option<Duration> { self.timer_interval }
+
+    /// Sets the interval in which the server applies the time-dependent updates of all Characteristics, i.e. auto
+    /// resets, coalesced writes and notifications held back by a minimum notify interval, without waiting for a read
+    /// or write by a controller, e.g. `Duration::from_millis(100)`. Every tick locks all Accessories, so this is only
+    /// worth enabling if Characteristics use these features. Defaults to `None`, applying them on the next read or
+    /// write only. Takes effect on the next `run_handle`.
+    pub fn set_timer_interval(&mut self, timer_interval: Option<Duration>) { self.timer_interval = timer_interval; }
+
+    /// Returns the accessory database of the server as gzip-compressed JSON, e.g. to save bandwidth to controllers of
+    /// a large bridge, along with the content encoding to send with it.
+    #[cfg(feature = "flate2")]
+    pub async fn compressed_accessory_database(&self) -> Result<(Vec<u8>, &'static str)> {
+        self.accessory_list.lock().await.as_compressed_json().await
+    }
+
+    /// Returns the Accessory and instance IDs of all readable Characteristics whose values weren't updated within
+    /// `interval`.
+    pub async fn characteristics_needing_refresh(&self, interval: Duration) -> Vec<(u64, u64)> {
+        self.accessory_list
+            .lock()
+            .await
+            .characteristics_needing_refresh(interval)
+            .await
+    }
+
+    /// Returns the Accessory and instance IDs of all Characteristics of a `HapType` across all Accessories.
+    pub async fn characteristics_of_type(&self, hap_type: HapType) -> Vec<(u64, u64)> {
+        self.accessory_list.lock().await.characteristics_of_type(hap_type).await
+    }
+
+    /// Reads the values of a group of Characteristics, identified by their Accessory and instance IDs, as one
+    /// consistent snapshot that concurrent writes can't tear.
+    pub async fn read_snapshot(&self, ids: &[(u64, u64)]) -> Result<Vec<serde_json::Value>> {
+        self.accessory_list.lock().await.read_snapshot(ids).await
+    }
 }
 
 #[async_trait]
@@ -164,8 +217,12 @@ impl Server for IpServer {
     fn run_handle(&self) -> BoxFuture<()> {
         let http_handle = self.http_server.run_handle();
         let mdns_handle = self.mdns_responder.run_handle();
+        let timers_handle = match self.timer_interval {
+            Some(timer_interval) => run_timers(self.accessory_list.clone(), timer_interval).boxed(),
+            None => future::ready(()).boxed(),
+        };
 
-        Box::pin(future::join(http_handle, mdns_handle).map(|_| ()).boxed())
+        Box::pin(future::join3(http_handle, mdns_handle, timers_handle).map(|_| ()).boxed())
     }
 
     fn config_pointer(&self) -> pointer::Config { self.config.clone() }
@@ -0,0 +1,277 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use crate::{
+    accessory::HapAccessory,
+    characteristic::HapCharacteristic,
+    event::Event,
+    pointer,
+    service::HapService,
+    Error,
+    HapType,
+    Result,
+};
+
+/// A Service whose Characteristics can be added and removed at runtime, e.g. a camera gaining a doorbell. Adding or
+/// removing a Characteristic emits a `CharacteristicStructureChanged` event, which makes the server bump its
+/// configuration number so controllers re-fetch the accessory database. The instance IDs of the other Characteristics
+/// are left untouched.
+#[derive(Default)]
+pub struct DynService {
+    /// ID of the Service.
+    id: u64,
+    /// ID of the Accessory the Service belongs to.
+    accessory_id: u64,
+    /// `HapType` of the Service.
+    hap_type: HapType,
+    /// Specifies if the Service is hidden.
+    hidden: bool,
+    /// Specifies if the Service is the primary Service of the Accessory.
+    primary: bool,
+    /// Characteristics of the Service.
+    characteristics: Vec<Box<dyn HapCharacteristic>>,
+    /// Event emitter the Characteristics of the Service are attached to.
+    event_emitter: Option<pointer::EventEmitter>,
+}
+
+impl DynService {
+    /// Creates a new Service without Characteristics.
+    pub fn new(id: u64, accessory_id: u64, hap_type: HapType) -> Self {
+        DynService {
+            id,
+            accessory_id,
+            hap_type,
+            ..Default::default()
+        }
+    }
+
+    /// Adds a Characteristic to the `DynService` of `accessory` returned by `service`, e.g. `|a| &mut a.doorbell`, and
+    /// emits a `CharacteristicStructureChanged` event for it. Returns an `Error::DuplicateInstanceIds` and leaves the
+    /// Service unchanged if its instance ID is already taken by any Service or Characteristic of the Accessory.
+    pub async fn add_characteristic<A, F>(
+        accessory: &mut A,
+        service: F,
+        mut characteristic: Box<dyn HapCharacteristic>,
+    ) -> Result<()>
+    where
+        A: HapAccessory,
+        F: Fn(&mut A) -> &mut DynService,
+    {
+        let iid = characteristic.get_id();
+        let dyn_service = service(accessory);
+        characteristic.set_event_emitter(dyn_service.event_emitter.clone());
+        dyn_service.characteristics.push(characteristic);
+
+        // instance IDs that collided before aren't blamed on the new Characteristic
+        if let Err(Error::DuplicateInstanceIds(iids)) = accessory.validate_instance_ids() {
+            if iids.contains(&iid) {
+                service(accessory).characteristics.pop();
+                return Err(Error::DuplicateInstanceIds(vec![iid]));
+            }
+        }
+        service(accessory).emit_structure_changed(iid).await;
+
+        Ok(())
+    }
+
+    /// Removes the Characteristic with the instance ID `iid` from the Service and emits a
+    /// `CharacteristicStructureChanged` event for it. Returns the removed Characteristic or `None` if the Service
+    /// doesn't have it.
+    pub async fn remove_characteristic(&mut self, iid: u64) -> Option<Box<dyn HapCharacteristic>> {
+        let position = self.characteristics.iter().position(|c| c.get_id() == iid)?;
+        let mut characteristic = self.characteristics.remove(position);
+        characteristic.set_event_emitter(None);
+        self.emit_structure_changed(iid).await;
+
+        Some(characteristic)
+    }
+
+    async fn emit_structure_changed(&self, iid: u64) {
+        if let Some(ref event_emitter) = self.event_emitter {
+            event_emitter
+                .lock()
+                .await
+                .emit(&Event::CharacteristicStructureChanged {
+                    aid: self.accessory_id,
+                    iid,
+                })
+                .await;
+        }
+    }
+}
+
+impl HapService for DynService {
+    fn get_id(&self) -> u64 { self.id }
+
+    fn get_type(&self) -> HapType { self.hap_type }
+
+    fn get_hidden(&self) -> bool { self.hidden }
+
+    fn set_hidden(&mut self, hidden: bool) { self.hidden = hidden; }
+
+    fn get_primary(&self) -> bool { self.primary }
+
+    fn set_primary(&mut self, primary: bool) { self.primary = primary; }
+
+    fn get_characteristic(&self, hap_type: HapType) -> Option<&dyn HapCharacteristic> {
+        self.get_characteristics().into_iter().find(|c| c.get_type() == hap_type)
+    }
+
+    fn get_mut_characteristic(&mut self, hap_type: HapType) -> Option<&mut dyn HapCharacteristic> {
+        self.get_mut_characteristics()
+            .into_iter()
+            .find(|c| c.get_type() == hap_type)
+    }
+
+    fn get_characteristics(&self) -> Vec<&dyn HapCharacteristic> {
+        self.characteristics.iter().map(|c| c.as_ref()).collect()
+    }
+
+    fn get_mut_characteristics(&mut self) -> Vec<&mut dyn HapCharacteristic> {
+        self.characteristics
+            .iter_mut()
+            .map(|c| c.as_mut() as &mut dyn HapCharacteristic)
+            .collect()
+    }
+
+    fn set_event_emitter(&mut self, event_emitter: Option<pointer::EventEmitter>) {
+        self.event_emitter = event_emitter;
+    }
+}
+
+impl Serialize for DynService {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("HapService", 5)?;
+        state.serialize_field("iid", &self.get_id())?;
+        state.serialize_field("type", &self.get_type())?;
+        state.serialize_field("hidden", &self.get_hidden())?;
+        state.serialize_field("primary", &self.get_primary())?;
+        state.serialize_field("characteristics", &self.get_characteristics())?;
+        state.end()
+    }
+}
+
+impl std::fmt::Debug for DynService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DynService")
+            .field("id", &self.id)
+            .field("accessory_id", &self.accessory_id)
+            .field("hap_type", &self.hap_type)
+            .field("hidden", &self.hidden)
+            .field("primary", &self.primary)
+            .field(
+                "characteristics",
+                &self.characteristics.iter().map(|c| c.get_id()).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex as StdMutex};
+
+    use futures::{executor, lock::Mutex, FutureExt};
+    use serde::Serialize;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{characteristic::DynCharacteristic, event::EventEmitter, service::HapService};
+
+    /// Accessory with two `DynService`s, e.g. to add Characteristics whose instance IDs collide across Services.
+    #[derive(Serialize)]
+    struct DynAccessory {
+        id: u64,
+        first: DynService,
+        second: DynService,
+    }
+
+    impl HapAccessory for DynAccessory {
+        fn get_id(&self) -> u64 { self.id }
+
+        fn set_id(&mut self, id: u64) { self.id = id; }
+
+        fn get_service(&self, hap_type: HapType) -> Option<&dyn HapService> {
+            self.get_services().into_iter().find(|s| s.get_type() == hap_type)
+        }
+
+        fn get_mut_service(&mut self, hap_type: HapType) -> Option<&mut dyn HapService> {
+            self.get_mut_services().into_iter().find(|s| s.get_type() == hap_type)
+        }
+
+        fn get_services(&self) -> Vec<&dyn HapService> { vec![&self.first, &self.second] }
+
+        fn get_mut_services(&mut self) -> Vec<&mut dyn HapService> { vec![&mut self.first, &mut self.second] }
+    }
+
+    fn accessory() -> DynAccessory {
+        DynAccessory {
+            id: 1,
+            first: DynService::new(2, 1, HapType::Doorbell),
+            second: DynService::new(8, 1, HapType::Doorbell),
+        }
+    }
+
+    fn structure_events() -> (pointer::EventEmitter, Arc<StdMutex<Vec<u64>>>) {
+        let events = Arc::new(StdMutex::new(Vec::new()));
+        let mut event_emitter = EventEmitter::new();
+        let e = events.clone();
+        event_emitter.add_listener(Box::new(move |event| {
+            if let Event::CharacteristicStructureChanged { iid, .. } = event {
+                e.lock().unwrap().push(*iid);
+            }
+            async {}.boxed()
+        }));
+
+        (Arc::new(Mutex::new(event_emitter)), events)
+    }
+
+    fn characteristic(iid: u64, hap_type: &str) -> Box<dyn HapCharacteristic> {
+        let template = json!({ "type": hap_type, "format": "bool", "perms": ["pr", "ev"] });
+        Box::new(DynCharacteristic::from_json(iid, 1, &template).unwrap())
+    }
+
+    #[test]
+    fn test_add_characteristic() {
+        let (event_emitter, events) = structure_events();
+        let mut accessory = accessory();
+        accessory.second.set_event_emitter(Some(event_emitter));
+        executor::block_on(DynService::add_characteristic(&mut accessory, |a| &mut a.first, characteristic(3, "73")))
+            .unwrap();
+
+        let add = |accessory: &mut DynAccessory, iid| {
+            executor::block_on(DynService::add_characteristic(accessory, |a| &mut a.second, characteristic(iid, "23")))
+        };
+        add(&mut accessory, 9).unwrap();
+        for iid in [9, 8, 3, 2] {
+            assert!(matches!(
+                add(&mut accessory, iid),
+                Err(Error::DuplicateInstanceIds(iids)) if iids == vec![iid]
+            ));
+        }
+        add(&mut accessory, 10).unwrap();
+
+        assert_eq!(*events.lock().unwrap(), vec![9, 10]);
+        let iids: Vec<u64> = accessory.second.get_characteristics().iter().map(|c| c.get_id()).collect();
+        assert_eq!(iids, vec![9, 10]);
+    }
+
+    #[test]
+    fn test_remove_characteristic() {
+        let (event_emitter, events) = structure_events();
+        let mut accessory = accessory();
+        for iid in 9..12 {
+            let characteristic = characteristic(iid, "23");
+            executor::block_on(DynService::add_characteristic(&mut accessory, |a| &mut a.second, characteristic))
+                .unwrap();
+        }
+        let service = &mut accessory.second;
+        service.set_event_emitter(Some(event_emitter));
+
+        assert_eq!(executor::block_on(service.remove_characteristic(10)).unwrap().get_id(), 10);
+        assert!(executor::block_on(service.remove_characteristic(10)).is_none());
+
+        assert_eq!(*events.lock().unwrap(), vec![10]);
+        let iids: Vec<u64> = service.get_characteristics().iter().map(|c| c.get_id()).collect();
+        assert_eq!(iids, vec![9, 11]);
+    }
+}
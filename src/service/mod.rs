@@ -1,10 +1,15 @@
 use erased_serde::serialize_trait_object;
 
-use crate::{characteristic::HapCharacteristic, HapType};
+use crate::{
+    characteristic::{Format, HapCharacteristic, Perm},
+    pointer,
+    HapType,
+};
 
+mod dynamic;
 mod generated;
 
-pub use crate::service::generated::*;
+pub use crate::service::{dynamic::DynService, generated::*};
 
 /// `HapService` is implemented by the inner type of every `Service`.
 pub trait HapService: erased_serde::Serialize + Send + Sync {
@@ -28,6 +33,103 @@ pub trait HapService: erased_serde::Serialize + Send + Sync {
     fn get_characteristics(&self) -> Vec<&dyn HapCharacteristic>;
     /// Returns mutable references to the Characteristics of a Service.
     fn get_mut_characteristics(&mut self) -> Vec<&mut dyn HapCharacteristic>;
+    /// Checks the Characteristics of a Service against the HAP specification. See `check_characteristics`.
+    fn spec_issues(&self) -> Vec<SpecIssue> { check_characteristics(self.get_type(), &self.get_characteristics()) }
+    /// Sets a `hap::event::pointer::EventEmitter` on a Service that adds or removes Characteristics at runtime. Does
+    /// nothing by default.
+    fn set_event_emitter(&mut self, _event_emitter: Option<pointer::EventEmitter>) {}
 }
 
 serialize_trait_object!(HapService);
+
+/// Violation of the HAP specification found in the Characteristics of a Service.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpecIssue {
+    /// A Characteristic the type of the Service requires is missing.
+    MissingCharacteristic(HapType),
+    /// The instance ID is used by more than one Characteristic.
+    DuplicateInstanceId(u64),
+    /// The permissions of the Characteristic with the instance ID contradict each other, e.g. events on a
+    /// Characteristic that can't be read.
+    ConflictingPerms(u64),
+    /// The Characteristic with the instance ID has another `Format` than the one the HAP specification defines for its
+    /// type.
+    WrongFormat { iid: u64, expected: Format, actual: Format },
+}
+
+/// Checks the Characteristics of a Service of type `service_type` against the rules of the HAP specification that
+/// span the whole collection, e.g. to catch mistakes in assembling a custom accessory before publishing it: every
+/// Characteristic required by the Service type is present, instance IDs are unique, permissions don't contradict each
+/// other and Characteristics of well-known types have the format the specification defines. Returns all issues found.
+pub fn check_characteristics(service_type: HapType, characteristics: &[&dyn HapCharacteristic]) -> Vec<SpecIssue> {
+    let mut issues = Vec::new();
+
+    for required in service_type.required_characteristics() {
+        if !characteristics.iter().any(|c| c.get_type() == *required) {
+            issues.push(SpecIssue::MissingCharacteristic(*required));
+        }
+    }
+
+    let mut iids = characteristics.iter().map(|c| c.get_id()).collect::<Vec<u64>>();
+    iids.sort_unstable();
+    let mut duplicates = iids.windows(2).filter(|w| w[0] == w[1]).map(|w| w[0]).collect::<Vec<u64>>();
+    duplicates.dedup();
+    issues.extend(duplicates.into_iter().map(SpecIssue::DuplicateInstanceId));
+
+    for characteristic in characteristics {
+        let iid = characteristic.get_id();
+        let perms = characteristic.get_perms();
+        let unreadable_events = perms.contains(&Perm::Events) && !perms.contains(&Perm::PairedRead);
+        let unwritable_auth = (perms.contains(&Perm::TimedWrite) || perms.contains(&Perm::AdditionalAuthorization))
+            && !perms.contains(&Perm::PairedWrite);
+        if unreadable_events || unwritable_auth {
+            issues.push(SpecIssue::ConflictingPerms(iid));
+        }
+
+        if let Some(expected) = characteristic.get_type().format() {
+            let actual = characteristic.get_format();
+            if actual != expected {
+                issues.push(SpecIssue::WrongFormat { iid, expected, actual });
+            }
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        characteristic::DynCharacteristic,
+        service::{lightbulb::LightbulbService, thermostat::ThermostatService},
+    };
+
+    #[test]
+    fn test_check_characteristics() {
+        assert!(LightbulbService::new(1, 1).spec_issues().is_empty());
+        assert!(ThermostatService::new(1, 1).spec_issues().is_empty());
+
+        let brightness = DynCharacteristic::from_json(
+            10,
+            1,
+            &json!({ "type": "8", "format": "float", "perms": ["pr", "pw", "ev"] }),
+        )
+        .unwrap();
+        let hue = DynCharacteristic::from_json(10, 1, &json!({ "type": "13", "format": "float", "perms": ["ev"] })).unwrap();
+        let characteristics: [&dyn HapCharacteristic; 2] = [&brightness, &hue];
+
+        assert_eq!(check_characteristics(HapType::Lightbulb, &characteristics), vec![
+            SpecIssue::MissingCharacteristic(HapType::On),
+            SpecIssue::DuplicateInstanceId(10),
+            SpecIssue::WrongFormat {
+                iid: 10,
+                expected: Format::Int32,
+                actual: Format::Float,
+            },
+            SpecIssue::ConflictingPerms(10),
+        ]);
+    }
+}
@@ -1,8 +1,12 @@
-use std::sync::Arc;
+#[cfg(feature = "flate2")]
+use std::io::Write;
+use std::{sync::Arc, time::Duration};
 
+#[cfg(feature = "flate2")]
+use flate2::{write::GzEncoder, Compression};
 use futures::lock::Mutex;
-use log::debug;
-use serde_json::json;
+use log::{debug, error};
+use tokio::time;
 
 use crate::{
     accessory::HapAccessory,
@@ -10,6 +14,7 @@ use crate::{
     pointer,
     transport::http::{ReadResponseObject, Status, WriteObject, WriteResponseObject},
     Error,
+    HapType,
     Result,
 };
 
@@ -32,6 +37,7 @@ impl AccessoryList {
     /// Adds an Accessory to the `AccessoryList` and returns a pointer to the added Accessory.
     pub fn add_accessory(&mut self, accessory: Box<dyn HapAccessory>) -> Result<pointer::Accessory> {
         let mut accessory = accessory;
+        accessory.validate_instance_ids()?;
         accessory.set_event_emitter_on_characteristics(Some(self.event_emitter.clone()));
 
         let accessory = Arc::new(Mutex::new(accessory));
@@ -64,6 +70,74 @@ impl AccessoryList {
         Err(Error::AccessoryNotFound)
     }
 
+    /// Returns the Accessory and instance IDs of all readable Characteristics whose values weren't updated within
+    /// `interval`, e.g. to drive the poll loop of a bridge.
+    pub async fn characteristics_needing_refresh(&self, interval: Duration) -> Vec<(u64, u64)> {
+        let mut overdue = Vec::new();
+        for accessory in self.accessories.iter() {
+            let a = accessory.lock().await;
+            for service in a.get_services() {
+                for characteristic in service.get_characteristics() {
+                    if characteristic.needs_refresh(interval) {
+                        overdue.push((a.get_id(), characteristic.get_id()));
+                    }
+                }
+            }
+        }
+
+        overdue
+    }
+
+    /// Returns the Accessory and instance IDs of all Characteristics of a `HapType` across all Accessories, e.g. to
+    /// find every Current Temperature Characteristic of a bridge.
+    pub async fn characteristics_of_type(&self, hap_type: HapType) -> Vec<(u64, u64)> {
+        let mut matching = Vec::new();
+        for accessory in self.accessories.iter() {
+            let a = accessory.lock().await;
+            for service in a.get_services() {
+                for characteristic in service.get_characteristics() {
+                    if characteristic.get_type() == hap_type {
+                        matching.push((a.get_id(), characteristic.get_id()));
+                    }
+                }
+            }
+        }
+
+        matching
+    }
+
+    /// Reads the values of a group of Characteristics, identified by their Accessory and instance IDs, as one
+    /// consistent snapshot, e.g. the Hue and Saturation of a Lightbulb. The Accessories of all Characteristics are
+    /// locked before the first value is read and only released after the last one, so a concurrent write to the
+    /// Accessories can't tear the group. Values are returned in the order of `ids`.
+    pub async fn read_snapshot(&self, ids: &[(u64, u64)]) -> Result<Vec<serde_json::Value>> {
+        // accessories are always locked in the order of the list, so concurrent snapshots can't deadlock
+        let mut locked = Vec::new();
+        for accessory in self.accessories.iter() {
+            let a = accessory.lock().await;
+            if ids.iter().any(|&(aid, _)| aid == a.get_id()) {
+                locked.push(a);
+            }
+        }
+
+        let mut values = Vec::with_capacity(ids.len());
+        for &(aid, iid) in ids {
+            let a = locked
+                .iter_mut()
+                .find(|a| a.get_id() == aid)
+                .ok_or(Error::AccessoryNotFound)?;
+            let characteristic = a
+                .get_mut_services()
+                .into_iter()
+                .flat_map(|service| service.get_mut_characteristics())
+                .find(|characteristic| characteristic.get_id() == iid)
+                .ok_or(Error::CharacteristicNotFound)?;
+            values.push(characteristic.get_value().await?);
+        }
+
+        Ok(values)
+    }
+
     pub(crate) async fn read_characteristic(
         &self,
         aid: u64,
@@ -97,7 +171,18 @@ impl AccessoryList {
                         if characteristic.get_id() == iid {
                             let characteristic_perms = characteristic.get_perms();
                             if characteristic_perms.contains(&Perm::PairedRead) {
-                                result_object.value = Some(characteristic.get_value().await?);
+                                match characteristic.get_value().await {
+                                    Ok(value) => result_object.value = Some(value),
+                                    Err(Error::CallbackPanicked)
+                                    | Err(Error::Unreachable)
+                                    | Err(Error::ValueBelowMinValue)
+                                    | Err(Error::ValueAboveMaxValue)
+                                    | Err(Error::InvalidValue(_)) => {
+                                        result_object.status = Some(Status::ServiceCommunicationFailure as i32);
+                                        break 'l;
+                                    },
+                                    Err(e) => return Err(e),
+                                }
                                 if meta {
                                     result_object.format = Some(characteristic.get_format());
                                     result_object.unit = characteristic.get_unit();
@@ -112,8 +197,9 @@ impl AccessoryList {
                                 if hap_type {
                                     result_object.hap_type = Some(characteristic.get_type());
                                 }
+                                // a requested subscription state is included even if it was never set
                                 if ev {
-                                    result_object.ev = characteristic.get_event_notifications();
+                                    result_object.ev = Some(characteristic.get_event_notifications().unwrap_or(false));
                                 }
                             } else {
                                 result_object.status = Some(Status::WriteOnlyCharacteristic as i32);
@@ -139,10 +225,10 @@ impl AccessoryList {
             status: 0,
         };
 
-        'l: for accessory in self.accessories.iter_mut() {
+        for accessory in self.accessories.iter_mut() {
             let mut a = accessory.lock().await;
             if a.get_id() == write_object.aid {
-                for service in a.get_mut_services() {
+                's: for service in a.get_mut_services() {
                     for characteristic in service.get_mut_characteristics() {
                         if characteristic.get_id() == write_object.iid {
                             let characteristic_perms = characteristic.get_perms();
@@ -152,14 +238,23 @@ impl AccessoryList {
                                     let subscription = (write_object.aid, write_object.iid);
                                     let mut es = event_subscriptions.lock().await;
                                     let pos = es.iter().position(|&s| s == subscription);
-                                    match (ev, pos) {
+                                    let change = match (ev, pos) {
                                         (true, None) => {
                                             es.push(subscription);
+                                            Some(true)
                                         },
                                         (false, Some(p)) => {
                                             es.remove(p);
+                                            Some(false)
                                         },
-                                        _ => {},
+                                        _ => None,
+                                    };
+                                    // the event listeners lock the subscriptions themselves
+                                    drop(es);
+                                    match change {
+                                        Some(true) => characteristic.handle_subscribe().await,
+                                        Some(false) => characteristic.handle_unsubscribe(),
+                                        None => {},
                                     }
                                 } else {
                                     result_object.status = Status::NotificationNotSupported as i32;
@@ -167,15 +262,39 @@ impl AccessoryList {
                             }
                             if let Some(value) = write_object.value {
                                 if characteristic_perms.contains(&Perm::PairedWrite) {
-                                    characteristic.set_value(value).await?;
+                                    match characteristic.set_value(value).await {
+                                        Err(Error::ResourceBusy) => {
+                                            result_object.status = Status::ResourceBusy as i32;
+                                        },
+                                        Err(Error::HiddenCharacteristic) => {
+                                            result_object.status = Status::ReadOnlyCharacteristic as i32;
+                                        },
+                                        Err(Error::ValueBelowMinValue)
+                                        | Err(Error::ValueAboveMaxValue)
+                                        | Err(Error::ValueNotOnStep)
+                                        | Err(Error::ValueNotInValidValues)
+                                        | Err(Error::ValueContainsControlCharacters)
+                                        | Err(Error::ValueDoesNotMatchPattern)
+                                        | Err(Error::InvalidValue(_)) => {
+                                            result_object.status = Status::InvalidValueInRequest as i32;
+                                        },
+                                        Err(Error::CallbackPanicked) | Err(Error::Unreachable) => {
+                                            result_object.status = Status::ServiceCommunicationFailure as i32;
+                                        },
+                                        res => res?,
+                                    }
                                 } else {
                                     result_object.status = Status::ReadOnlyCharacteristic as i32;
                                 }
                             }
-                            break 'l;
+                            break 's;
                         }
                     }
                 }
+                for (iid, e) in a.apply_linked_updates().await {
+                    error!("couldn't apply linked update of characteristic {}.{}: {}", write_object.aid, iid, e);
+                }
+                break;
             }
         }
 
@@ -183,24 +302,342 @@ impl AccessoryList {
     }
 
     pub(crate) async fn as_serialized_json(&self) -> Result<Vec<u8>> {
-        let mut accessory_values = Vec::new();
-        for accessory in &self.accessories {
+        // serialize directly into the response buffer instead of building an intermediate `serde_json::Value`
+        let mut json = b"{\"accessories\":[".to_vec();
+        for (i, accessory) in self.accessories.iter().enumerate() {
+            if i > 0 {
+                json.push(b',');
+            }
             let a = accessory.lock().await;
-            accessory_values.push(serde_json::to_value(&*a)?);
+            serde_json::to_writer(&mut json, &*a)?;
         }
+        json.extend_from_slice(b"]}");
+
+        debug!("accessory list JSON: {}", String::from_utf8_lossy(&json));
+
+        Ok(json)
+    }
 
-        let json = json!({ "accessories": accessory_values });
+    /// Applies the time-dependent updates that are due on all Characteristics, e.g. deferred update callbacks, so they
+    /// don't wait for the next read or write by a controller.
+    pub(crate) async fn tick(&self) {
+        for accessory in &self.accessories {
+            let mut a = accessory.lock().await;
+            let aid = a.get_id();
+            for service in a.get_mut_services() {
+                for characteristic in service.get_mut_characteristics() {
+                    if let Err(e) = characteristic.tick().await {
+                        let iid = characteristic.get_id();
+                        error!("couldn't apply timed update of characteristic {}.{}: {}", aid, iid, e);
+                    }
+                }
+            }
+        }
+    }
 
-        debug!("accessory list JSON: {}", &json);
+    /// Serializes the accessory database like `as_serialized_json` and compresses it with gzip. Returns the
+    /// compressed bytes and the content encoding to send with them.
+    #[cfg(feature = "flate2")]
+    pub(crate) async fn as_compressed_json(&self) -> Result<(Vec<u8>, &'static str)> {
+        let json = self.as_serialized_json().await?;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&json)?;
+        Ok((encoder.finish()?, "gzip"))
+    }
+}
 
-        Ok(serde_json::to_vec(&json)?)
+/// Ticks all Characteristics of an `AccessoryList` every `interval`, so time-dependent updates are applied without a
+/// read or write by a controller. Runs until it's dropped.
+pub(crate) async fn run_timers(accessory_list: pointer::AccessoryList, interval: Duration) {
+    let mut timer = time::interval(interval);
+    loop {
+        timer.tick().await;
+        accessory_list.lock().await.tick().await;
     }
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
+#[cfg(test)]
+mod tests {
+    use std::{sync::Mutex as StdMutex, task::Poll};
+
+    use futures::{executor, future, FutureExt};
+    use serde::Serialize;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        accessory::{lightbulb::LightbulbAccessory, temperature_sensor::TemperatureSensorAccessory, AccessoryInformation},
+        characteristic::{
+            hue::HueCharacteristic,
+            saturation::SaturationCharacteristic,
+            Characteristic,
+            CharacteristicCallbacks,
+            DynCharacteristic,
+            HapCharacteristic,
+            MockClock,
+            Sanitization,
+        },
+        event::EventEmitter,
+        service::{DynService, HapService},
+    };
+
+    /// Accessory with a single `DynService`, e.g. to write to Characteristics none of the generated Services has.
+    #[derive(Serialize)]
+    struct DynAccessory {
+        id: u64,
+        service: DynService,
+    }
 
-//     #[test]
-//     fn test_json_serialization() {} // TODO: test it
-// }
+    impl HapAccessory for DynAccessory {
+        fn get_id(&self) -> u64 { self.id }
+
+        fn set_id(&mut self, id: u64) { self.id = id; }
+
+        fn get_service(&self, hap_type: HapType) -> Option<&dyn HapService> {
+            self.get_services().into_iter().find(|s| s.get_type() == hap_type)
+        }
+
+        fn get_mut_service(&mut self, hap_type: HapType) -> Option<&mut dyn HapService> {
+            self.get_mut_services().into_iter().find(|s| s.get_type() == hap_type)
+        }
+
+        fn get_services(&self) -> Vec<&dyn HapService> { vec![&self.service] }
+
+        fn get_mut_services(&mut self) -> Vec<&mut dyn HapService> { vec![&mut self.service] }
+    }
+
+    // TODO: test the JSON serialization
+
+    #[test]
+    fn test_characteristics_of_type() {
+        let mut accessory_list = AccessoryList::new(Arc::new(Mutex::new(EventEmitter::new())));
+        let accessories: [Box<dyn HapAccessory>; 3] = [
+            Box::new(TemperatureSensorAccessory::new(1, AccessoryInformation::default()).unwrap()),
+            Box::new(LightbulbAccessory::new(2, AccessoryInformation::default()).unwrap()),
+            Box::new(TemperatureSensorAccessory::new(3, AccessoryInformation::default()).unwrap()),
+        ];
+        for accessory in accessories {
+            accessory_list.add_accessory(accessory).unwrap();
+        }
+
+        let temperatures = executor::block_on(accessory_list.characteristics_of_type(HapType::CurrentTemperature));
+        assert_eq!(temperatures.len(), 2);
+        assert_eq!(temperatures.iter().map(|(aid, _)| *aid).collect::<Vec<u64>>(), vec![1, 3]);
+        assert!(executor::block_on(accessory_list.characteristics_of_type(HapType::CurrentRelativeHumidity)).is_empty());
+    }
+
+    #[test]
+    fn test_read_snapshot() {
+        let mut accessory_list = AccessoryList::new(Arc::new(Mutex::new(EventEmitter::new())));
+        let mut lightbulb = LightbulbAccessory::new(1, AccessoryInformation::default()).unwrap();
+        lightbulb.lightbulb.hue = Some(HueCharacteristic::new(100, 1));
+        lightbulb.lightbulb.saturation = Some(SaturationCharacteristic::new(101, 1));
+        let lightbulb = accessory_list.add_accessory(Box::new(lightbulb)).unwrap();
+
+        // the writer yields between its two writes, which a read of the group must not observe
+        let write = async {
+            let mut a = lightbulb.lock().await;
+            let service = a.get_mut_service(HapType::Lightbulb).unwrap();
+            let hue = service.get_mut_characteristic(HapType::Hue).unwrap();
+            hue.set_value(json!(120.0)).await.unwrap();
+            let mut yielded = false;
+            future::poll_fn(|cx| {
+                if yielded {
+                    return Poll::Ready(());
+                }
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            })
+            .await;
+            let saturation = service.get_mut_characteristic(HapType::Saturation).unwrap();
+            saturation.set_value(json!(50.0)).await.unwrap();
+        };
+        let read = accessory_list.read_snapshot(&[(1, 100), (1, 101)]);
+        let (_, snapshot) = executor::block_on(future::join(write, read));
+        assert_eq!(snapshot.unwrap(), vec![json!(120.0), json!(50.0)]);
+
+        assert!(matches!(
+            executor::block_on(accessory_list.read_snapshot(&[(1, 100), (2, 100)])),
+            Err(Error::AccessoryNotFound)
+        ));
+        assert!(matches!(
+            executor::block_on(accessory_list.read_snapshot(&[(1, 102)])),
+            Err(Error::CharacteristicNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_read_characteristic_ev() {
+        let mut accessory_list = AccessoryList::new(Arc::new(Mutex::new(EventEmitter::new())));
+        let lightbulb = LightbulbAccessory::new(1, AccessoryInformation::default()).unwrap();
+        let lightbulb = accessory_list.add_accessory(Box::new(lightbulb)).unwrap();
+        let (aid, iid) = executor::block_on(accessory_list.characteristics_of_type(HapType::On))[0];
+
+        let read = |ev| {
+            let response = executor::block_on(accessory_list.read_characteristic(aid, iid, false, false, false, ev));
+            serde_json::to_value(response.unwrap()).unwrap()
+        };
+        assert_eq!(read(false).get("ev"), None);
+        assert_eq!(read(true).get("ev"), Some(&json!(false)));
+
+        executor::block_on(async {
+            let mut a = lightbulb.lock().await;
+            let service = a.get_mut_service(HapType::Lightbulb).unwrap();
+            let on = service.get_mut_characteristic(HapType::On).unwrap();
+            on.set_event_notifications(Some(true));
+        });
+        assert_eq!(read(false).get("ev"), None);
+        assert_eq!(read(true).get("ev"), Some(&json!(true)));
+    }
+
+    #[test]
+    fn test_read_unreachable_characteristic() {
+        let mut accessory_list = AccessoryList::new(Arc::new(Mutex::new(EventEmitter::new())));
+        let lightbulb = LightbulbAccessory::new(1, AccessoryInformation::default()).unwrap();
+        let lightbulb = accessory_list.add_accessory(Box::new(lightbulb)).unwrap();
+        let (aid, iid) = executor::block_on(accessory_list.characteristics_of_type(HapType::On))[0];
+
+        let read = || executor::block_on(accessory_list.read_characteristic(aid, iid, false, false, false, false));
+        let response = read().unwrap();
+        assert_eq!((response.status, response.value), (Some(0), Some(json!(false))));
+
+        executor::block_on(async {
+            let mut a = lightbulb.lock().await;
+            let service = a.get_mut_service(HapType::Lightbulb).unwrap();
+            service.get_mut_characteristic(HapType::On).unwrap().set_reachable(false);
+        });
+        let response = read().unwrap();
+        assert_eq!(response.status, Some(Status::ServiceCommunicationFailure as i32));
+        assert_eq!(response.value, None);
+    }
+
+    #[test]
+    fn test_read_value_outside_bounds() {
+        let mut accessory_list = AccessoryList::new(Arc::new(Mutex::new(EventEmitter::new())));
+        let mut sensor = TemperatureSensorAccessory::new(1, AccessoryInformation::default()).unwrap();
+        sensor.temperature_sensor.current_temperature.on_read(Some(|| Some(-5.0)));
+        let iid = sensor.temperature_sensor.current_temperature.get_id();
+        accessory_list.add_accessory(Box::new(sensor)).unwrap();
+
+        let response = executor::block_on(accessory_list.read_characteristic(1, iid, false, false, false, false));
+        let response = response.unwrap();
+        assert_eq!((response.status, response.value), (Some(0), Some(json!(-5.0))));
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn test_compressed_json() {
+        use std::io::Read;
+
+        use flate2::read::GzDecoder;
+
+        let mut accessory_list = AccessoryList::new(Arc::new(Mutex::new(EventEmitter::new())));
+        for aid in 1..=10 {
+            let lightbulb = LightbulbAccessory::new(aid, AccessoryInformation::default()).unwrap();
+            accessory_list.add_accessory(Box::new(lightbulb)).unwrap();
+        }
+
+        let json = executor::block_on(accessory_list.as_serialized_json()).unwrap();
+        let (compressed, content_encoding) = executor::block_on(accessory_list.as_compressed_json()).unwrap();
+        assert_eq!(content_encoding, "gzip");
+        assert!(compressed.len() < json.len());
+
+        let mut decompressed = Vec::new();
+        GzDecoder::new(compressed.as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, json);
+    }
+
+    #[tokio::test]
+    async fn test_run_timers() {
+        let updates = Arc::new(StdMutex::new(Vec::new()));
+        let clock = MockClock::new();
+        let mut lightbulb = LightbulbAccessory::new(1, AccessoryInformation::default()).unwrap();
+        let on = lightbulb
+            .lightbulb
+            .on
+            .as_any_mut()
+            .downcast_mut::<Characteristic<bool>>()
+            .unwrap();
+        on.set_clock(Some(clock.clone()));
+        on.set_write_coalescing(Some(Duration::from_millis(300)));
+        let u = updates.clone();
+        on.on_update(Some(move |old: &bool, new: &bool| u.lock().unwrap().push((*old, *new))));
+        on.set_value(true).await.unwrap();
+        on.set_value(false).await.unwrap();
+        on.set_value(true).await.unwrap();
+
+        let mut accessory_list = AccessoryList::new(Arc::new(Mutex::new(EventEmitter::new())));
+        accessory_list.add_accessory(Box::new(lightbulb)).unwrap();
+        let accessory_list = Arc::new(Mutex::new(accessory_list));
+        clock.advance(Duration::from_millis(300));
+        assert!(updates.lock().unwrap().is_empty());
+
+        // the burst settles without any further read or write
+        let timers = run_timers(accessory_list, Duration::from_millis(10)).boxed();
+        future::select(timers, time::delay_for(Duration::from_millis(50)).boxed()).await;
+        assert_eq!(*updates.lock().unwrap(), vec![(false, true)]);
+    }
+
+    #[test]
+    fn test_write_invalid_value() {
+        let mut accessory = DynAccessory { id: 1, service: DynService::new(8, 1, HapType::Lightbulb) };
+        let templates = [
+            json!({
+                "type": "8",
+                "format": "int32",
+                "perms": ["pr", "pw"],
+                "minValue": 0,
+                "maxValue": 100,
+                "minStep": 5,
+            }),
+            json!({ "type": "B0", "format": "uint8", "perms": ["pr", "pw"], "valid-values": [0, 1] }),
+            json!({ "type": "23", "format": "string", "perms": ["pr", "pw"] }),
+            json!({ "type": "13", "format": "float", "perms": ["pr", "pw"] }),
+        ];
+        for (iid, template) in (9..).zip(templates.iter()) {
+            let mut characteristic = DynCharacteristic::from_json(iid, 1, template).unwrap();
+            if let Some(c) = characteristic.as_any_mut().downcast_mut::<Characteristic<Value>>() {
+                c.set_sanitization(Sanitization::Reject);
+            }
+            let characteristic = Box::new(characteristic);
+            executor::block_on(DynService::add_characteristic(&mut accessory, |a| &mut a.service, characteristic))
+                .unwrap();
+        }
+        let mut accessory_list = AccessoryList::new(Arc::new(Mutex::new(EventEmitter::new())));
+        accessory_list.add_accessory(Box::new(accessory)).unwrap();
+        let event_subscriptions = Arc::new(Mutex::new(Vec::new()));
+
+        let mut write = |iid, value| {
+            let write_object = WriteObject {
+                aid: 1,
+                iid,
+                ev: None,
+                value: Some(value),
+                auth_data: None,
+                remote: None,
+            };
+            executor::block_on(accessory_list.write_characteristic(write_object, &event_subscriptions))
+                .unwrap()
+                .status
+        };
+        assert_eq!(write(9, json!(50)), 0);
+
+        let invalid_writes = [
+            (9, json!(150)),
+            (9, json!(-5)),
+            (9, json!(7)),
+            (9, json!(3.5)),
+            (10, json!(2)),
+            (11, json!("a\u{0}b")),
+            (11, json!("a\nb")),
+            (11, json!(5)),
+            (12, json!("NaN")),
+        ];
+        for (iid, value) in invalid_writes.iter() {
+            assert_eq!(write(*iid, value.clone()), Status::InvalidValueInRequest as i32, "{} {}", iid, value);
+        }
+    }
+}
@@ -121,7 +121,28 @@ pub fn status_response(status: StatusCode) -> Result<Response<Body>> {
         .map_err(Error::from)
 }
 
-pub fn event_response(event_objects: Vec<EventObject>) -> Result<Vec<u8>> {
+/// Builder of the `EVENT/1.0 200 OK` message notifying a controller of the changed values of one or more
+/// Characteristics.
+#[derive(Debug, Default)]
+pub struct EventBuilder {
+    event_objects: Vec<EventObject>,
+}
+
+impl EventBuilder {
+    /// Creates a new, empty event message builder.
+    pub fn new() -> Self { EventBuilder::default() }
+
+    /// Adds the changed value of the Characteristic with the instance ID `iid` on the accessory with the ID `aid`.
+    pub fn value(mut self, aid: u64, iid: u64, value: serde_json::Value) -> Self {
+        self.event_objects.push(EventObject { iid, aid, value });
+        self
+    }
+
+    /// Returns the full event message with the `{"characteristics":[...]}` body holding all added values.
+    pub fn build(self) -> Result<Vec<u8>> { event_response(self.event_objects) }
+}
+
+fn event_response(event_objects: Vec<EventObject>) -> Result<Vec<u8>> {
     let body = serde_json::to_string(&CharacteristicResponseBody {
         characteristics: event_objects,
     })?;
@@ -142,3 +163,36 @@ fn response(body: Vec<u8>, status: StatusCode, content_type: ContentType) -> Res
         .body(body.into())
         .map_err(Error::from)
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    #[test]
+    fn test_event_builder() {
+        let message = EventBuilder::new()
+            .value(1, 10, json!(true))
+            .value(2, 11, json!(42))
+            .build()
+            .unwrap();
+        let message = String::from_utf8(message).unwrap();
+
+        let (head, body) = message.split_at(message.find("\n\n").unwrap());
+        let body = &body[2..];
+        let mut head = head.lines();
+        assert_eq!(head.next(), Some("EVENT/1.0 200 OK"));
+        assert_eq!(head.next(), Some("Content-Type: application/hap+json"));
+        assert_eq!(head.next(), Some(format!("Content-Length: {}", body.len()).as_str()));
+        assert_eq!(
+            serde_json::from_str::<Value>(body).unwrap(),
+            json!({
+                "characteristics": [
+                    { "aid": 1, "iid": 10, "value": true },
+                    { "aid": 2, "iid": 11, "value": 42 },
+                ]
+            })
+        );
+    }
+}
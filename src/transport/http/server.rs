@@ -19,7 +19,6 @@ use crate::{
     pointer,
     transport::{
         http::{
-            event_response,
             handler::{
                 accessories::Accessories,
                 characteristics::{GetCharacteristics, UpdateCharacteristics},
@@ -32,7 +31,7 @@ use crate::{
                 TlvHandler,
             },
             status_response,
-            EventObject,
+            EventBuilder,
         },
         tcp::{EncryptedStream, Session, StreamWrapper},
     },
@@ -217,17 +216,14 @@ impl Server {
                     let stream_outgoing_ = stream_outgoing.clone();
                     async move {
                         match *event {
-                            Event::CharacteristicValueChanged { aid, iid, ref value } => {
+                            Event::CharacteristicValueChanged { aid, iid, ref value, .. } => {
                                 let mut dropped_subscriptions = vec![];
                                 for (i, &(s_aid, s_iid)) in event_subscriptions_.lock().await.iter().enumerate() {
                                     if s_aid == aid && s_iid == iid {
-                                        let event = EventObject {
-                                            aid,
-                                            iid,
-                                            value: value.clone(),
-                                        };
-                                        let event_res =
-                                            event_response(vec![event]).expect("couldn't create event response");
+                                        let event_res = EventBuilder::new()
+                                            .value(aid, iid, value.clone())
+                                            .build()
+                                            .expect("couldn't create event response");
                                         if stream_outgoing_.unbounded_send(event_res).is_err() {
                                             dropped_subscriptions.push(i);
                                         }